@@ -0,0 +1,154 @@
+//! Streaming request bodies.
+//!
+//! Routes registered with [`Router::on_stream`][crate::Router::on_stream]
+//! skip buffering: the handler reads the body straight off the connection
+//! through a [`BodyStream`], a `Content-Length`-bounded `AsyncRead`. A 2 GB
+//! upload then costs a read buffer, not 2 GB:
+//!
+//! ```rust,no_run
+//! use astor::{BodyStream, Method, Router, Status};
+//!
+//! async fn upload(mut body: BodyStream) -> Status {
+//!     let Ok(mut file) = tokio::fs::File::create("/var/uploads/blob").await else {
+//!         return Status::InternalServerError;
+//!     };
+//!     match tokio::io::copy(&mut body, &mut file).await {
+//!         Ok(_)  => Status::Created,
+//!         Err(_) => Status::BadRequest,
+//!     }
+//! }
+//!
+//! let app = Router::new().on_stream(Method::Put, "/blob", upload, ());
+//! ```
+//!
+//! # nginx and chunked bodies
+//!
+//! With `proxy_request_buffering on` (the default) nginx spools the whole
+//! upload to its own disk before forwarding it — streaming saves astor's
+//! memory, not nginx's. Turn it off on the upload `location` to stream end
+//! to end. Only `Content-Length` bodies stream: a chunked body (the `chunked`
+//! feature) is decoded in full first, up to `Server::max_chunked_body`, and
+//! then read from memory.
+//!
+//! # Unread bytes
+//!
+//! The connection carries the next request right after this body, so astor
+//! must get past whatever the handler left unread. Up to 256 KiB is read and
+//! discarded before the response goes out; past that, the response carries
+//! `connection: close` and the connection is dropped instead.
+
+use std::any::Any;
+use std::future::{self, Future};
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, ready};
+
+use tokio::io::{AsyncBufRead, AsyncRead, ReadBuf};
+
+use crate::extract::FromRequest;
+use crate::request::Request;
+
+/// The connection's read half, as the server hands it around.
+pub(crate) type Reader = Box<dyn ReadHalf>;
+
+/// A read half that can be taken back by its concrete type — how
+/// [`Upgraded::into_parts`][crate::Upgraded::into_parts] finds the socket.
+pub(crate) trait ReadHalf: AsyncBufRead + Send + Unpin {
+    fn as_any(&self) -> &dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: AsyncBufRead + Send + Unpin + 'static> ReadHalf for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// A request body, read as it arrives.
+///
+/// Ends after `Content-Length` bytes. A connection that closes before then
+/// fails the read with [`io::ErrorKind::UnexpectedEof`]; a read after the
+/// response has been sent fails too — the connection has moved on.
+///
+/// As a handler argument it takes the body: streamed on routes registered
+/// with [`Router::on_stream`][crate::Router::on_stream], from memory
+/// everywhere else. Never rejects.
+pub struct BodyStream(Source);
+
+enum Source {
+    /// A body the server already read — routes registered with `on`, and
+    /// chunked bodies.
+    Buffered(io::Cursor<Vec<u8>>),
+    /// A body still on the connection.
+    Connection(Arc<Mutex<Slot>>),
+}
+
+/// The connection, lent to a [`BodyStream`] for the length of one handler.
+pub(crate) struct Slot {
+    /// `None` once the server has taken the connection back.
+    pub(crate) reader: Option<Reader>,
+    /// Body bytes not yet read.
+    pub(crate) remaining: u64,
+}
+
+impl BodyStream {
+    pub(crate) fn buffered(body: Vec<u8>) -> Self {
+        Self(Source::Buffered(io::Cursor::new(body)))
+    }
+
+    pub(crate) fn connection(slot: Arc<Mutex<Slot>>) -> Self {
+        Self(Source::Connection(slot))
+    }
+}
+
+impl AsyncRead for BodyStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let slot = match &mut self.0 {
+            Source::Buffered(cursor) => return Pin::new(cursor).poll_read(cx, buf),
+            Source::Connection(slot) => slot,
+        };
+        let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+        if slot.remaining == 0 || buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        let remaining = slot.remaining;
+        let Some(reader) = slot.reader.as_mut() else {
+            return Poll::Ready(Err(io::Error::other("request body read after the response was sent")));
+        };
+        let available = ready!(Pin::new(&mut **reader).poll_fill_buf(cx))?;
+        if available.is_empty() {
+            return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+        let n = available.len().min(buf.remaining()).min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        buf.put_slice(&available[..n]);
+        Pin::new(&mut **reader).consume(n);
+        slot.remaining -= n as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl FromRequest for BodyStream {
+    type Rejection = std::convert::Infallible;
+
+    fn from_request(req: Request) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        future::ready(Ok(req.into_body_stream()))
+    }
+}
+
+/// How a route wants its request body.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum BodyMode {
+    /// Read in full before the handler runs.
+    Buffered,
+    /// Handed to the handler unread.
+    Streamed,
+}
@@ -0,0 +1,484 @@
+//! Cookies — reading `cookie`, writing `set-cookie` (RFC 6265).
+//!
+//! Read with [`Request::cookie`][crate::Request::cookie] and
+//! [`Request::cookies`][crate::Request::cookies]; write by building a
+//! [`Cookie`] and appending it to a [`Response::builder`][crate::Response::builder]
+//! with `.cookie(…)`:
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//!
+//! use astor::{Cookie, Request, Response, SameSite};
+//!
+//! async fn set_theme(req: Request) -> Response {
+//!     let theme = match req.cookie("theme") {
+//!         Some("dark") => "light",
+//!         _            => "dark",
+//!     };
+//!     Response::builder()
+//!         .cookie(
+//!             Cookie::new("theme", theme)
+//!                 .path("/")
+//!                 .max_age(Duration::from_secs(365 * 24 * 60 * 60))
+//!                 .same_site(SameSite::Lax),
+//!         )
+//!         .text(format!("theme is now {theme}"))
+//! }
+//! ```
+//!
+//! Values are sent and read as-is — no percent-encoding. Encode anything that
+//! is not already cookie-safe (ids, base64url tokens and the like are)
+//! before building the cookie, or build it with
+//! [`Cookie::try_new`] and handle the [`CookieError`][crate::CookieError].
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::CookieError;
+
+/// A `set-cookie` header, built attribute by attribute.
+///
+/// `Display` renders the header value.
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use astor::{Cookie, SameSite};
+///
+/// let cookie = Cookie::new("session", "abc123")
+///     .path("/")
+///     .max_age(Duration::from_secs(3600))
+///     .secure()
+///     .http_only()
+///     .same_site(SameSite::Strict);
+///
+/// assert_eq!(
+///     cookie.to_string(),
+///     "session=abc123; Path=/; Max-Age=3600; Secure; HttpOnly; SameSite=Strict",
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Cookie {
+    domain: Option<String>,
+    expires: Option<SystemTime>,
+    http_only: bool,
+    max_age: Option<Duration>,
+    name: String,
+    partitioned: bool,
+    path: Option<String>,
+    same_site: Option<SameSite>,
+    secure: bool,
+    value: String,
+}
+
+impl Cookie {
+    /// A session cookie — no attributes set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is not an HTTP token or `value` holds a character
+    /// RFC 6265 forbids (whitespace, `"`, `,`, `;`, `\`, controls, non-ASCII).
+    /// Either would corrupt the header. See [`try_new`][Cookie::try_new] for
+    /// the non-panicking version.
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self::try_new(name, value).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// [`new`][Cookie::new], returning a [`CookieError`] instead of
+    /// panicking — for values that come from the request or other runtime
+    /// data.
+    ///
+    /// ```rust
+    /// use astor::{Cookie, CookieError};
+    ///
+    /// assert!(Cookie::try_new("theme", "dark").is_ok());
+    /// assert_eq!(
+    ///     Cookie::try_new("theme", "dark; Domain=evil.example").unwrap_err(),
+    ///     CookieError::InvalidValue("theme".into()),
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// [`CookieError::InvalidName`] or [`CookieError::InvalidValue`].
+    pub fn try_new(name: impl Into<String>, value: impl Into<String>) -> Result<Self, CookieError> {
+        let (name, value) = (name.into(), value.into());
+        if !is_token(&name) {
+            return Err(CookieError::InvalidName(name));
+        }
+        if !is_cookie_value(&value) {
+            return Err(CookieError::InvalidValue(name));
+        }
+        Ok(Self {
+            domain: None,
+            expires: None,
+            http_only: false,
+            max_age: None,
+            name,
+            partitioned: false,
+            path: None,
+            same_site: None,
+            secure: false,
+            value,
+        })
+    }
+
+    /// A cookie that deletes `name` from the browser: empty value,
+    /// `Max-Age=0` and an `Expires` in the past.
+    ///
+    /// Set the same `path` and `domain` the cookie was created with, or the
+    /// browser keeps it.
+    ///
+    /// ```rust
+    /// use astor::Cookie;
+    ///
+    /// assert_eq!(
+    ///     Cookie::removal("session").path("/").to_string(),
+    ///     "session=; Path=/; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+    /// );
+    /// ```
+    pub fn removal(name: impl Into<String>) -> Self {
+        Self::new(name, "").max_age(Duration::ZERO).expires(UNIX_EPOCH)
+    }
+
+    /// The cookie name.
+    pub fn name(&self) -> &str { &self.name }
+
+    /// The cookie value.
+    pub fn value(&self) -> &str { &self.value }
+
+    /// `Domain` — send the cookie to this domain and its subdomains. Without
+    /// it, only the exact host that set it receives it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `domain` contains `;` or a control character. See
+    /// [`try_domain`][Cookie::try_domain] for the non-panicking version.
+    pub fn domain(self, domain: &str) -> Self {
+        self.try_domain(domain).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// [`domain`][Cookie::domain], returning a [`CookieError`] instead of
+    /// panicking.
+    ///
+    /// # Errors
+    ///
+    /// [`CookieError::InvalidDomain`] if `domain` contains `;` or a control
+    /// character.
+    pub fn try_domain(mut self, domain: &str) -> Result<Self, CookieError> {
+        if !is_attribute_value(domain) {
+            return Err(CookieError::InvalidDomain(domain.to_owned()));
+        }
+        self.domain = Some(domain.to_owned());
+        Ok(self)
+    }
+
+    /// `Expires` — an absolute expiry time. Prefer
+    /// [`max_age`][Cookie::max_age]; browsers use `Max-Age` when both are
+    /// present.
+    ///
+    /// ```rust
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// use astor::Cookie;
+    ///
+    /// let at = UNIX_EPOCH + Duration::from_secs(784_111_777);
+    /// assert_eq!(
+    ///     Cookie::new("a", "b").expires(at).to_string(),
+    ///     "a=b; Expires=Sun, 06 Nov 1994 08:49:37 GMT",
+    /// );
+    /// ```
+    pub fn expires(mut self, at: SystemTime) -> Self {
+        self.expires = Some(at);
+        self
+    }
+
+    /// `HttpOnly` — hide the cookie from JavaScript.
+    pub fn http_only(mut self) -> Self {
+        self.http_only = true;
+        self
+    }
+
+    /// `Max-Age` — expire the cookie this long after it is received, to the
+    /// second.
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+
+    /// `Partitioned` — CHIPS: keep the cookie in a per-top-level-site jar.
+    /// Browsers require [`secure`][Cookie::secure] with it.
+    pub fn partitioned(mut self) -> Self {
+        self.partitioned = true;
+        self
+    }
+
+    /// `Path` — send the cookie only for this path and below.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `path` contains `;` or a control character. See
+    /// [`try_path`][Cookie::try_path] for the non-panicking version.
+    pub fn path(self, path: &str) -> Self {
+        self.try_path(path).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// [`path`][Cookie::path], returning a [`CookieError`] instead of
+    /// panicking.
+    ///
+    /// # Errors
+    ///
+    /// [`CookieError::InvalidPath`] if `path` contains `;` or a control
+    /// character.
+    pub fn try_path(mut self, path: &str) -> Result<Self, CookieError> {
+        if !is_attribute_value(path) {
+            return Err(CookieError::InvalidPath(path.to_owned()));
+        }
+        self.path = Some(path.to_owned());
+        Ok(self)
+    }
+
+    /// `SameSite` — whether cross-site requests carry the cookie.
+    /// [`SameSite::None`] requires [`secure`][Cookie::secure].
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// `Secure` — send the cookie over HTTPS only. nginx terminates TLS, so
+    /// set this whenever clients reach nginx over HTTPS.
+    pub fn secure(mut self) -> Self {
+        self.secure = true;
+        self
+    }
+
+    /// Replaces the value, attributes kept. Unchecked — callers pass
+    /// base64url or an already-validated value.
+    pub(crate) fn with_value(mut self, value: String) -> Self {
+        self.value = value;
+        self
+    }
+
+    /// This cookie as a removal: empty value, `Max-Age=0`, `Expires` at the
+    /// epoch, `Path` and `Domain` kept so the browser matches it.
+    pub(crate) fn into_removal(self) -> Self {
+        self.with_value(String::new()).max_age(Duration::ZERO).expires(UNIX_EPOCH)
+    }
+
+    /// `true` for a cookie built by [`removal`][Cookie::removal] or
+    /// [`into_removal`][Cookie::into_removal].
+    pub(crate) fn is_removal(&self) -> bool {
+        self.max_age == Some(Duration::ZERO)
+    }
+}
+
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(path) = &self.path {
+            write!(f, "; Path={path}")?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={domain}")?;
+        }
+        if let Some(age) = self.max_age {
+            write!(f, "; Max-Age={}", age.as_secs())?;
+        }
+        if let Some(at) = self.expires {
+            write!(f, "; Expires={}", HttpDate(at))?;
+        }
+        if self.secure {
+            f.write_str("; Secure")?;
+        }
+        if self.http_only {
+            f.write_str("; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={}", same_site.as_str())?;
+        }
+        if self.partitioned {
+            f.write_str("; Partitioned")?;
+        }
+        Ok(())
+    }
+}
+
+/// The `SameSite` cookie attribute.
+///
+/// All variants are listed alphabetically — add new ones in order.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SameSite {
+    /// Sent on top-level navigations from other sites, not on cross-site
+    /// subrequests. The browser default.
+    Lax,
+    /// Sent on every request, cross-site included. Requires `Secure`.
+    None,
+    /// Sent only on requests originating from the same site.
+    Strict,
+}
+
+impl SameSite {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Lax    => "Lax",
+            Self::None   => "None",
+            Self::Strict => "Strict",
+        }
+    }
+}
+
+// ── Parsing ───────────────────────────────────────────────────────────────────
+
+/// Splits `cookie` header values into name-value pairs, in order.
+///
+/// Lenient, as RFC 6265 §5.4 asks of servers: pairs without `=` and empty
+/// names are skipped, whitespace around names and values is trimmed, and a
+/// value wrapped in double quotes loses them.
+pub(crate) fn parse<'a>(headers: impl Iterator<Item = &'a str>) -> impl Iterator<Item = (&'a str, &'a str)> {
+    headers
+        .flat_map(|header| header.split(';'))
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            let name = name.trim();
+            if name.is_empty() {
+                return None;
+            }
+            let value = value.trim();
+            let value = value.strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            Some((name, value))
+        })
+}
+
+// ── Internal ──────────────────────────────────────────────────────────────────
+
+/// RFC 7230 `token`.
+fn is_token(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| {
+        b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+    })
+}
+
+/// RFC 6265 `cookie-value`: `cookie-octet`s, optionally in double quotes.
+fn is_cookie_value(s: &str) -> bool {
+    let inner = s.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(s);
+    inner.bytes().all(|b| matches!(b, 0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E))
+}
+
+/// RFC 6265 `av-octet`s: no controls, no `;`.
+fn is_attribute_value(s: &str) -> bool {
+    s.bytes().all(|b| !b.is_ascii_control() && b != b';')
+}
+
+/// Formats a time as an IMF-fixdate (RFC 9110 §5.6.7):
+/// `Sun, 06 Nov 1994 08:49:37 GMT`. Times before 1970 format as the epoch.
+struct HttpDate(SystemTime);
+
+impl fmt::Display for HttpDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+
+        let secs = self.0.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let days = secs / 86_400;
+        let time = secs % 86_400;
+
+        // Civil date from days since 1970-01-01 — Howard Hinnant's
+        // `civil_from_days`, shifted so eras start on 0000-03-01.
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z % 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + u64::from(month <= 2);
+
+        write!(
+            f,
+            "{}, {day:02} {} {year} {:02}:{:02}:{:02} GMT",
+            DAYS[(days % 7) as usize],
+            MONTHS[(month - 1) as usize],
+            time / 3600,
+            time % 3600 / 60,
+            time % 60,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_new_rejects_what_would_corrupt_the_header() {
+        assert_eq!(Cookie::try_new("", "v").unwrap_err(), CookieError::InvalidName(String::new()));
+        assert_eq!(Cookie::try_new("a b", "v").unwrap_err(), CookieError::InvalidName("a b".into()));
+        for value in ["a b", "a;b", "a,b", "a\"b", "a\\b", "caf\u{e9}", "a\r\nb"] {
+            assert_eq!(Cookie::try_new("n", value).unwrap_err(), CookieError::InvalidValue("n".into()));
+        }
+        assert_eq!(Cookie::try_new("n", "\"quoted\"").unwrap().value(), "\"quoted\"");
+        assert_eq!(Cookie::try_new("n", "").unwrap().to_string(), "n=");
+    }
+
+    #[test]
+    fn try_domain_and_try_path_reject_semicolons_and_controls() {
+        let cookie = || Cookie::new("a", "b");
+        let err = cookie().try_domain("x.com; Secure").unwrap_err();
+        assert_eq!(err, CookieError::InvalidDomain("x.com; Secure".into()));
+        assert_eq!(cookie().try_path("/\n").unwrap_err(), CookieError::InvalidPath("/\n".into()));
+        let ok = cookie().try_domain("example.com").and_then(|c| c.try_path("/app")).unwrap();
+        assert_eq!(ok.to_string(), "a=b; Path=/app; Domain=example.com");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid value for cookie `n`")]
+    fn new_panics_with_the_error_message() {
+        Cookie::new("n", "bad value");
+    }
+
+    #[test]
+    fn renders_every_attribute() {
+        let cookie = Cookie::new("id", "1")
+            .path("/")
+            .domain("example.com")
+            .max_age(Duration::from_millis(90_500))
+            .expires(UNIX_EPOCH + Duration::from_secs(951_782_400))
+            .secure()
+            .http_only()
+            .same_site(SameSite::None)
+            .partitioned();
+        assert_eq!(
+            cookie.to_string(),
+            "id=1; Path=/; Domain=example.com; Max-Age=90; Expires=Tue, 29 Feb 2000 00:00:00 GMT; \
+             Secure; HttpOnly; SameSite=None; Partitioned",
+        );
+    }
+
+    #[test]
+    fn formats_http_dates() {
+        let at = |secs| HttpDate(UNIX_EPOCH + Duration::from_secs(secs)).to_string();
+        assert_eq!(at(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(at(784_111_777), "Sun, 06 Nov 1994 08:49:37 GMT");
+        // 2100 is not a leap year.
+        assert_eq!(at(4_107_542_399), "Sun, 28 Feb 2100 23:59:59 GMT");
+        assert_eq!(HttpDate(UNIX_EPOCH - Duration::from_secs(1)).to_string(), "Thu, 01 Jan 1970 00:00:00 GMT");
+    }
+
+    #[test]
+    fn removal_expires_the_cookie() {
+        let removal = Cookie::new("s", "v").path("/").into_removal();
+        assert!(removal.is_removal());
+        assert_eq!(removal.to_string(), "s=; Path=/; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT");
+    }
+
+    #[test]
+    fn parses_leniently_across_headers() {
+        let pairs: Vec<_> = parse(["a=1; b = \"two\" ;junk; =x", "c=3=3"].into_iter()).collect();
+        assert_eq!(pairs, [("a", "1"), ("b", "two"), ("c", "3=3")]);
+    }
+}
@@ -0,0 +1,567 @@
+//! The few cryptographic primitives astor needs, on std alone.
+//!
+//! astor keeps to two dependencies, so signed and private cookies bring their
+//! own: SHA-256 and HMAC-SHA256 (FIPS 180-4, RFC 2104), ChaCha20-Poly1305
+//! (RFC 8439), base64url (RFC 4648 §5) and OS randomness. The WebSocket
+//! handshake adds SHA-1 and padded base64 (RFC 4648 §4) — a fixed hash the
+//! protocol asks for, not a security boundary. Each is the straightforward
+//! reference construction, checked against the published test vectors in
+//! the tests below — not tuned for throughput, which a few hundred bytes of
+//! cookie never needs.
+
+use std::io;
+#[cfg(unix)]
+use std::io::Read;
+
+// ── SHA-256 ───────────────────────────────────────────────────────────────────
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 of the concatenation of `parts`.
+pub(crate) fn sha256(parts: &[&[u8]]) -> [u8; 32] {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+    let len: usize = parts.iter().map(|p| p.len()).sum();
+    let mut data = Vec::with_capacity(len + 72);
+    for part in parts {
+        data.extend_from_slice(part);
+    }
+    data.push(0x80);
+    while data.len() % 64 != 56 {
+        data.push(0);
+    }
+    data.extend_from_slice(&((len as u64) * 8).to_be_bytes());
+
+    for block in data.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, v) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(v);
+        }
+    }
+
+    let mut out = [0u8; 32];
+    for (chunk, word) in out.chunks_exact_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+/// SHA-1 of `data`. Only for the WebSocket handshake (RFC 6455 §4.2.2) —
+/// SHA-1 is broken for anything that needs collision resistance.
+pub(crate) fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut padded = Vec::with_capacity(data.len() + 72);
+    padded.extend_from_slice(data);
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in padded.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19  => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _       => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let t = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (state, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(v);
+        }
+    }
+
+    let mut out = [0u8; 20];
+    for (chunk, word) in out.chunks_exact_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+/// HMAC-SHA256 of the concatenation of `parts`.
+pub(crate) fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        block[..32].copy_from_slice(&sha256(&[key]));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let ipad = block.map(|b| b ^ 0x36);
+    let opad = block.map(|b| b ^ 0x5c);
+
+    let mut inner = Vec::with_capacity(parts.len() + 1);
+    inner.push(&ipad[..]);
+    inner.extend_from_slice(parts);
+    let inner = sha256(&inner);
+    sha256(&[&opad, &inner])
+}
+
+/// Compares two byte strings in time independent of where they differ.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// ── ChaCha20-Poly1305 ─────────────────────────────────────────────────────────
+
+/// Bytes of authentication tag appended by [`seal`].
+pub(crate) const TAG_LEN: usize = 16;
+
+/// Bytes of nonce [`seal`] and [`open`] take.
+pub(crate) const NONCE_LEN: usize = 12;
+
+/// Encrypts `plaintext` and authenticates it together with `aad`. Returns
+/// ciphertext followed by the 16-byte tag. Never reuse a nonce with a key.
+pub(crate) fn seal(key: &[u8; 32], nonce: &[u8; NONCE_LEN], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let mut out = plaintext.to_vec();
+    chacha20_xor(key, 1, nonce, &mut out);
+    let tag = aead_tag(key, nonce, aad, &out);
+    out.extend_from_slice(&tag);
+    out
+}
+
+/// Verifies and decrypts what [`seal`] produced. `None` if the tag does not
+/// match — wrong key, wrong `aad`, or tampered bytes.
+pub(crate) fn open(key: &[u8; 32], nonce: &[u8; NONCE_LEN], aad: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+    let split = sealed.len().checked_sub(TAG_LEN)?;
+    let (ciphertext, tag) = sealed.split_at(split);
+    if !constant_time_eq(&aead_tag(key, nonce, aad, ciphertext), tag) {
+        return None;
+    }
+    let mut out = ciphertext.to_vec();
+    chacha20_xor(key, 1, nonce, &mut out);
+    Some(out)
+}
+
+/// RFC 8439 §2.8: Poly1305 over `aad` and `ciphertext`, keyed from block 0.
+fn aead_tag(key: &[u8; 32], nonce: &[u8; NONCE_LEN], aad: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+    let block = chacha20_block(key, 0, nonce);
+    let mut otk = [0u8; 32];
+    otk.copy_from_slice(&block[..32]);
+
+    let pad = |len: usize| vec![0u8; (16 - len % 16) % 16];
+    let mut mac_data = Vec::with_capacity(aad.len() + ciphertext.len() + 48);
+    mac_data.extend_from_slice(aad);
+    mac_data.extend_from_slice(&pad(aad.len()));
+    mac_data.extend_from_slice(ciphertext);
+    mac_data.extend_from_slice(&pad(ciphertext.len()));
+    mac_data.extend_from_slice(&(aad.len() as u64).to_le_bytes());
+    mac_data.extend_from_slice(&(ciphertext.len() as u64).to_le_bytes());
+    poly1305(&otk, &mac_data)
+}
+
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]); s[d] ^= s[a]; s[d] = s[d].rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]); s[b] ^= s[c]; s[b] = s[b].rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]); s[d] ^= s[a]; s[d] = s[d].rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]); s[b] ^= s[c]; s[b] = s[b].rotate_left(7);
+}
+
+/// RFC 8439 §2.3: one 64-byte keystream block.
+fn chacha20_block(key: &[u8; 32], counter: u32, nonce: &[u8; NONCE_LEN]) -> [u8; 64] {
+    let le = |b: &[u8]| u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+    let mut init = [0u32; 16];
+    init[..4].copy_from_slice(&[0x61707865, 0x3320646e, 0x79622d32, 0x6b206574]);
+    for i in 0..8 {
+        init[4 + i] = le(&key[i * 4..]);
+    }
+    init[12] = counter;
+    for i in 0..3 {
+        init[13 + i] = le(&nonce[i * 4..]);
+    }
+
+    let mut s = init;
+    for _ in 0..10 {
+        quarter_round(&mut s, 0, 4, 8, 12);
+        quarter_round(&mut s, 1, 5, 9, 13);
+        quarter_round(&mut s, 2, 6, 10, 14);
+        quarter_round(&mut s, 3, 7, 11, 15);
+        quarter_round(&mut s, 0, 5, 10, 15);
+        quarter_round(&mut s, 1, 6, 11, 12);
+        quarter_round(&mut s, 2, 7, 8, 13);
+        quarter_round(&mut s, 3, 4, 9, 14);
+    }
+
+    let mut out = [0u8; 64];
+    for (i, chunk) in out.chunks_exact_mut(4).enumerate() {
+        chunk.copy_from_slice(&s[i].wrapping_add(init[i]).to_le_bytes());
+    }
+    out
+}
+
+/// RFC 8439 §2.4: XORs `data` with the keystream starting at `counter`.
+fn chacha20_xor(key: &[u8; 32], counter: u32, nonce: &[u8; NONCE_LEN], data: &mut [u8]) {
+    for (i, chunk) in data.chunks_mut(64).enumerate() {
+        let block = chacha20_block(key, counter.wrapping_add(i as u32), nonce);
+        for (b, k) in chunk.iter_mut().zip(block) {
+            *b ^= k;
+        }
+    }
+}
+
+/// RFC 8439 §2.5: Poly1305 with 26-bit limbs.
+fn poly1305(key: &[u8; 32], msg: &[u8]) -> [u8; 16] {
+    let le = |b: &[u8]| u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+
+    // r, clamped, split into five 26-bit limbs.
+    let r0 = le(&key[0..]) & 0x3ff_ffff;
+    let r1 = (le(&key[3..]) >> 2) & 0x3ff_ff03;
+    let r2 = (le(&key[6..]) >> 4) & 0x3ff_c0ff;
+    let r3 = (le(&key[9..]) >> 6) & 0x3f0_3fff;
+    let r4 = (le(&key[12..]) >> 8) & 0x00f_ffff;
+    let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+
+    let mut h = [0u32; 5];
+    for chunk in msg.chunks(16) {
+        let mut block = [0u8; 17];
+        block[..chunk.len()].copy_from_slice(chunk);
+        block[chunk.len()] = 1;
+
+        h[0] += le(&block[0..]) & 0x3ff_ffff;
+        h[1] += (le(&block[3..]) >> 2) & 0x3ff_ffff;
+        h[2] += (le(&block[6..]) >> 4) & 0x3ff_ffff;
+        h[3] += (le(&block[9..]) >> 6) & 0x3ff_ffff;
+        h[4] += (le(&block[12..]) >> 8) | (u32::from(block[16]) << 24);
+
+        let m = |a: u32, b: u32| u64::from(a) * u64::from(b);
+        let d0 = m(h[0], r0) + m(h[1], s4) + m(h[2], s3) + m(h[3], s2) + m(h[4], s1);
+        let mut d1 = m(h[0], r1) + m(h[1], r0) + m(h[2], s4) + m(h[3], s3) + m(h[4], s2);
+        let mut d2 = m(h[0], r2) + m(h[1], r1) + m(h[2], r0) + m(h[3], s4) + m(h[4], s3);
+        let mut d3 = m(h[0], r3) + m(h[1], r2) + m(h[2], r1) + m(h[3], r0) + m(h[4], s4);
+        let mut d4 = m(h[0], r4) + m(h[1], r3) + m(h[2], r2) + m(h[3], r1) + m(h[4], r0);
+
+        let mut c = d0 >> 26;
+        h[0] = d0 as u32 & 0x3ff_ffff;
+        d1 += c; c = d1 >> 26; h[1] = d1 as u32 & 0x3ff_ffff;
+        d2 += c; c = d2 >> 26; h[2] = d2 as u32 & 0x3ff_ffff;
+        d3 += c; c = d3 >> 26; h[3] = d3 as u32 & 0x3ff_ffff;
+        d4 += c; c = d4 >> 26; h[4] = d4 as u32 & 0x3ff_ffff;
+        h[0] += c as u32 * 5;
+        let c = h[0] >> 26;
+        h[0] &= 0x3ff_ffff;
+        h[1] += c;
+    }
+
+    // Full carry, then reduce mod 2^130 - 5 by comparing h + 5 against 2^130.
+    let mut c = h[1] >> 26; h[1] &= 0x3ff_ffff;
+    h[2] += c; c = h[2] >> 26; h[2] &= 0x3ff_ffff;
+    h[3] += c; c = h[3] >> 26; h[3] &= 0x3ff_ffff;
+    h[4] += c; c = h[4] >> 26; h[4] &= 0x3ff_ffff;
+    h[0] += c * 5; c = h[0] >> 26; h[0] &= 0x3ff_ffff;
+    h[1] += c;
+
+    let mut g = [0u32; 5];
+    g[0] = h[0].wrapping_add(5); c = g[0] >> 26; g[0] &= 0x3ff_ffff;
+    g[1] = h[1].wrapping_add(c); c = g[1] >> 26; g[1] &= 0x3ff_ffff;
+    g[2] = h[2].wrapping_add(c); c = g[2] >> 26; g[2] &= 0x3ff_ffff;
+    g[3] = h[3].wrapping_add(c); c = g[3] >> 26; g[3] &= 0x3ff_ffff;
+    g[4] = h[4].wrapping_add(c).wrapping_sub(1 << 26);
+
+    // g[4]'s top bit is set when h + 5 < 2^130, i.e. h is already reduced.
+    let mask = (g[4] >> 31).wrapping_sub(1);
+    for i in 0..5 {
+        h[i] = (h[i] & !mask) | (g[i] & mask);
+    }
+
+    // h as 128 bits, plus s.
+    let h0 = h[0] | (h[1] << 26);
+    let h1 = (h[1] >> 6) | (h[2] << 20);
+    let h2 = (h[2] >> 12) | (h[3] << 14);
+    let h3 = (h[3] >> 18) | (h[4] << 8);
+
+    let mut f = u64::from(h0) + u64::from(le(&key[16..]));
+    let t0 = f as u32;
+    f = u64::from(h1) + u64::from(le(&key[20..])) + (f >> 32);
+    let t1 = f as u32;
+    f = u64::from(h2) + u64::from(le(&key[24..])) + (f >> 32);
+    let t2 = f as u32;
+    f = u64::from(h3) + u64::from(le(&key[28..])) + (f >> 32);
+    let t3 = f as u32;
+
+    let mut tag = [0u8; 16];
+    for (chunk, word) in tag.chunks_exact_mut(4).zip([t0, t1, t2, t3]) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    tag
+}
+
+// ── base64 ────────────────────────────────────────────────────────────────────
+
+pub(crate) const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Standard base64 with `=` padding — what HTTP headers carry.
+pub(crate) fn base64_encode(data: &[u8]) -> String {
+    let mut out = encode(BASE64, data);
+    out.push_str(match data.len() % 3 {
+        1 => "==",
+        2 => "=",
+        _ => "",
+    });
+    out
+}
+
+/// base64url without padding — cookie-safe as-is.
+pub(crate) fn base64url_encode(data: &[u8]) -> String {
+    encode(BASE64URL, data)
+}
+
+fn encode(alphabet: &[u8; 64], data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(alphabet[(n >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    out
+}
+
+/// Decodes unpadded base64url. `None` on any other character, an
+/// impossible length, or set bits past the last byte — every byte string
+/// has exactly one encoding, so a signed value cannot be respelled.
+pub(crate) fn base64url_decode(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 4 == 1 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    for chunk in s.as_bytes().chunks(4) {
+        let mut n = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let v = BASE64URL.iter().position(|&a| a == c)? as u32;
+            n |= v << (18 - 6 * i);
+        }
+        let bytes = chunk.len() - 1;
+        if n & (0xff_ffff >> (8 * bytes)) != 0 {
+            return None;
+        }
+        out.extend_from_slice(&n.to_be_bytes()[1..=bytes]);
+    }
+    Some(out)
+}
+
+// ── Randomness ────────────────────────────────────────────────────────────────
+
+/// Fills `buf` from the operating system's CSPRNG.
+///
+/// Fails if `/dev/urandom` cannot be read — there is no safe fallback for
+/// key and nonce material.
+#[cfg(unix)]
+pub(crate) fn random_bytes(buf: &mut [u8]) -> io::Result<()> {
+    std::fs::File::open("/dev/urandom")?.read_exact(buf)
+}
+
+/// Always fails: astor reads randomness from `/dev/urandom` only.
+#[cfg(not(unix))]
+pub(crate) fn random_bytes(_: &mut [u8]) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "astor: no OS randomness source on this platform"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    // FIPS 180-2 appendix B and the NIST example values.
+    #[test]
+    fn sha256_fips_vectors() {
+        let cases: [(&[u8], &str); 3] = [
+            (b"",    "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            (b"abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+        ];
+        for (input, digest) in cases {
+            assert_eq!(hex(&sha256(&[input])), digest);
+        }
+        let million = vec![b'a'; 1_000_000];
+        assert_eq!(hex(&sha256(&[&million])), "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
+    }
+
+    #[test]
+    fn sha256_hashes_the_concatenation_of_parts() {
+        assert_eq!(sha256(&[b"ab", b"", b"c"]), sha256(&[b"abc"]));
+    }
+
+    // FIPS 180-1 appendices A and B, plus the empty message.
+    #[test]
+    fn sha1_fips_vectors() {
+        let cases: [(&[u8], &str); 3] = [
+            (b"",    "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            (b"abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            ),
+        ];
+        for (input, digest) in cases {
+            assert_eq!(hex(&sha1(input)), digest);
+        }
+        assert_eq!(hex(&sha1(&vec![b'a'; 1_000_000])), "34aa973cd4c4daa4f61eeb2bdbad27316534016f");
+    }
+
+    // RFC 4231 §4.2, §4.3 and §4.7.
+    #[test]
+    fn hmac_sha256_rfc4231_vectors() {
+        assert_eq!(
+            hex(&hmac_sha256(&[0x0b; 20], &[b"Hi There"])),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+        );
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", &[b"what do ya want ", b"for nothing?"])),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        );
+        assert_eq!(
+            hex(&hmac_sha256(&[0xaa; 131], &[b"Test Using Larger Than Block-Size Key - Hash Key First"])),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+        );
+    }
+
+    #[test]
+    fn constant_time_eq_compares_length_and_content() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"ab"));
+    }
+
+    // RFC 8439 §2.8.2.
+    #[test]
+    fn chacha20_poly1305_rfc8439_vector() {
+        let key: [u8; 32] = std::array::from_fn(|i| 0x80 + i as u8);
+        let nonce: [u8; NONCE_LEN] = unhex("070000004041424344454647").try_into().unwrap();
+        let aad = unhex("50515253c0c1c2c3c4c5c6c7");
+        let plaintext: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you only one \
+            tip for the future, sunscreen would be it.";
+        let expected = unhex(concat!(
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6",
+            "3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36",
+            "92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc",
+            "3ff4def08e4b7a9de576d26586cec64b6116",
+            "1ae10b594f09e26a7e902ecbd0600691",
+        ));
+
+        let sealed = seal(&key, &nonce, &aad, plaintext);
+        assert_eq!(hex(&sealed), hex(&expected));
+        assert_eq!(open(&key, &nonce, &aad, &sealed).as_deref(), Some(plaintext));
+    }
+
+    #[test]
+    fn open_rejects_tampering() {
+        let key = [7; 32];
+        let nonce = [9; NONCE_LEN];
+        let sealed = seal(&key, &nonce, b"name", b"value");
+
+        let mut flipped = sealed.clone();
+        flipped[0] ^= 1;
+        assert_eq!(open(&key, &nonce, b"name", &flipped), None);
+        assert_eq!(open(&key, &nonce, b"other", &sealed), None);
+        assert_eq!(open(&[8; 32], &nonce, b"name", &sealed), None);
+        assert_eq!(open(&key, &nonce, b"name", &sealed[..TAG_LEN - 1]), None);
+    }
+
+    // RFC 4648 §10.
+    #[test]
+    fn base64_rfc4648_vectors() {
+        let cases = [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy")];
+        for (input, encoded) in cases {
+            assert_eq!(base64_encode(input.as_bytes()), encoded);
+            assert_eq!(base64url_encode(input.as_bytes()), encoded.trim_end_matches('='));
+        }
+    }
+
+    #[test]
+    fn base64url_round_trips() {
+        for len in 0..70 {
+            let data: Vec<u8> = (0..len).map(|i| (i * 37 + 250) as u8).collect();
+            let encoded = base64url_encode(&data);
+            assert!(!encoded.contains(['+', '/', '=']));
+            assert_eq!(base64url_decode(&encoded), Some(data));
+        }
+        assert_eq!(base64url_encode(&[0xfb, 0xff]), "-_8");
+    }
+
+    #[test]
+    fn base64url_decode_rejects_malformed_input() {
+        assert_eq!(base64url_decode("A"), None);
+        assert_eq!(base64url_decode("AAAAA"), None);
+        assert_eq!(base64url_decode("AA=="), None);
+        assert_eq!(base64url_decode("A+AA"), None);
+    }
+
+    #[test]
+    fn base64url_decode_rejects_unused_bits() {
+        assert_eq!(base64url_decode("AA"), Some(vec![0]));
+        assert_eq!(base64url_decode("AB"), None);
+        assert_eq!(base64url_decode("AAA"), Some(vec![0, 0]));
+        assert_eq!(base64url_decode("AAB"), None);
+        assert_eq!(base64url_decode("AAAAAP"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn random_bytes_fills_the_buffer() {
+        let (mut a, mut b) = ([0u8; 32], [0u8; 32]);
+        random_bytes(&mut a).unwrap();
+        random_bytes(&mut b).unwrap();
+        assert_ne!(a, b);
+    }
+}
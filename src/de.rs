@@ -0,0 +1,410 @@
+//! A serde `Deserializer` over decoded urlencoded pairs.
+//!
+//! Backs [`Request::query_as`][crate::Request::query_as] and
+//! [`Request::form_as`][crate::Request::form_as]. The input is the
+//! already-decoded `(key, value)` list, so strings can borrow from it.
+//!
+//! The top level is a map from key to every value of that key:
+//!
+//! - a scalar field (`u32`, `bool`, `String`, a unit enum, …) takes the last
+//!   value — the same rule as [`Request::query`][crate::Request::query];
+//! - a sequence field (`Vec<T>`) takes every value, in order;
+//! - an `Option` field is `None` when the key is absent or every value is
+//!   empty (`?page=`), as HTML forms send blank inputs.
+//!
+//! Booleans accept `true` / `false`, `1` / `0` and `on` / `off` — `on` is
+//! what a checked HTML checkbox sends.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::de::value::{SeqDeserializer, StrDeserializer};
+use serde::de::{self, Deserialize, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::forward_to_deserialize_any;
+
+/// Deserializes `T` from decoded pairs.
+pub(crate) fn from_pairs<'de, T: Deserialize<'de>>(pairs: &'de [(String, String)]) -> Result<T, Error> {
+    T::deserialize(Pairs(pairs))
+}
+
+// ── Error ─────────────────────────────────────────────────────────────────────
+
+/// Why the pairs do not fit the target type. The message names the field
+/// when there is one.
+#[derive(Debug)]
+pub(crate) struct Error(String);
+
+impl Error {
+    pub(crate) fn into_message(self) -> String { self.0 }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+// ── Top level ─────────────────────────────────────────────────────────────────
+
+/// The whole pair list, deserialized as a map.
+struct Pairs<'de>(&'de [(String, String)]);
+
+impl<'de> de::Deserializer<'de> for Pairs<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(Fields::new(self.0))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit_struct seq tuple tuple_struct map struct
+        enum identifier ignored_any
+    }
+}
+
+/// Keys in first-appearance order, each with every one of its values.
+struct Fields<'de> {
+    current: Option<(&'de str, Vec<&'de str>)>,
+    groups: std::vec::IntoIter<(&'de str, Vec<&'de str>)>,
+}
+
+impl<'de> Fields<'de> {
+    fn new(pairs: &'de [(String, String)]) -> Self {
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        let mut groups: Vec<(&str, Vec<&str>)> = Vec::new();
+        for (k, v) in pairs {
+            match index.get(k.as_str()) {
+                Some(&i) => groups[i].1.push(v.as_str()),
+                None     => {
+                    index.insert(k.as_str(), groups.len());
+                    groups.push((k.as_str(), vec![v.as_str()]));
+                }
+            }
+        }
+        Self { current: None, groups: groups.into_iter() }
+    }
+}
+
+impl<'de> MapAccess<'de> for Fields<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        let Some((key, values)) = self.groups.next() else { return Ok(None) };
+        self.current = Some((key, values));
+        seed.deserialize(Part(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (key, values) = self.current.take().expect("next_value_seed called before next_key_seed");
+        seed.deserialize(Values(values)).map_err(|e| Error(format!("`{key}`: {e}")))
+    }
+}
+
+// ── Values of one key ─────────────────────────────────────────────────────────
+
+/// Every value of one key. Sequences take them all; anything else takes the
+/// last one.
+struct Values<'de>(Vec<&'de str>);
+
+impl<'de> Values<'de> {
+    fn last(&self) -> Part<'de> {
+        Part(self.0.last().copied().unwrap_or(""))
+    }
+}
+
+/// Forwards each listed method to the last value.
+macro_rules! to_last {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                self.last().$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Values<'de> {
+    type Error = Error;
+
+    to_last! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32
+        deserialize_u64 deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char
+        deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_unit deserialize_map deserialize_identifier deserialize_ignored_any
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.0.iter().all(|v| v.is_empty()) {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(SeqDeserializer::new(self.0.into_iter().map(Part)))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.last().deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.last().deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.last().deserialize_enum(name, variants, visitor)
+    }
+}
+
+// ── One value ─────────────────────────────────────────────────────────────────
+
+/// A single decoded key or value.
+struct Part<'de>(&'de str);
+
+impl<'de> IntoDeserializer<'de, Error> for Part<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self { self }
+}
+
+/// Parses the value with `FromStr` and visits the result.
+macro_rules! parse {
+    ($($method:ident => $visit:ident($ty:ty), $what:literal;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.0.parse::<$ty>() {
+                    Ok(n)  => visitor.$visit(n),
+                    Err(_) => Err(Error(format!(concat!("expected ", $what, ", got `{}`"), self.0))),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Part<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            "true" | "1" | "on"   => visitor.visit_bool(true),
+            "false" | "0" | "off" => visitor.visit_bool(false),
+            other                 => Err(Error(format!("expected a boolean, got `{other}`"))),
+        }
+    }
+
+    parse! {
+        deserialize_i8   => visit_i8(i8),     "an integer";
+        deserialize_i16  => visit_i16(i16),   "an integer";
+        deserialize_i32  => visit_i32(i32),   "an integer";
+        deserialize_i64  => visit_i64(i64),   "an integer";
+        deserialize_i128 => visit_i128(i128), "an integer";
+        deserialize_u8   => visit_u8(u8),     "a non-negative integer";
+        deserialize_u16  => visit_u16(u16),   "a non-negative integer";
+        deserialize_u32  => visit_u32(u32),   "a non-negative integer";
+        deserialize_u64  => visit_u64(u64),   "a non-negative integer";
+        deserialize_u128 => visit_u128(u128), "a non-negative integer";
+        deserialize_f32  => visit_f32(f32),   "a number";
+        deserialize_f64  => visit_f64(f64),   "a number";
+        deserialize_char => visit_char(char), "a single character";
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.0.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(SeqDeserializer::new(std::iter::once(self)))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(StrDeserializer::<Error>::new(self.0))
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit_struct tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::extract::QueryAs;
+    use crate::method::Method;
+    use crate::router::Router;
+    use crate::testing::get;
+    use crate::urlencoded;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Sort {
+        Asc,
+        Desc,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct List {
+        page: Option<u32>,
+        #[serde(default)]
+        limit: u8,
+        sort: Option<Sort>,
+        #[serde(default)]
+        tag: Vec<String>,
+        archived: Option<bool>,
+        min: Option<f64>,
+    }
+
+    fn list(raw: &str) -> Result<List, String> {
+        from_pairs(&urlencoded::parse(raw)).map_err(Error::into_message)
+    }
+
+    #[test]
+    fn fills_a_struct() {
+        let got = list("page=2&limit=10&sort=desc&tag=a&tag=b+c&archived=on&min=-1.5").unwrap();
+        let want = List {
+            page: Some(2),
+            limit: 10,
+            sort: Some(Sort::Desc),
+            tag: vec!["a".into(), "b c".into()],
+            archived: Some(true),
+            min: Some(-1.5),
+        };
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn missing_and_empty_values_are_absent() {
+        let got = list("page=&sort=").unwrap();
+        assert_eq!(got, List { page: None, limit: 0, sort: None, tag: vec![], archived: None, min: None });
+    }
+
+    #[test]
+    fn the_last_value_wins_for_scalars() {
+        assert_eq!(list("page=1&page=3").unwrap().page, Some(3));
+        assert_eq!(list("tag=solo").unwrap().tag, ["solo"]);
+    }
+
+    #[test]
+    fn booleans_accept_checkbox_spellings() {
+        for (raw, want) in [("1", true), ("true", true), ("off", false), ("0", false)] {
+            assert_eq!(list(&format!("archived={raw}")).unwrap().archived, Some(want));
+        }
+        assert_eq!(list("archived=yes").unwrap_err(), "`archived`: expected a boolean, got `yes`");
+    }
+
+    #[test]
+    fn errors_name_the_parameter() {
+        assert_eq!(list("page=abc").unwrap_err(), "`page`: expected a non-negative integer, got `abc`");
+        assert_eq!(list("limit=300").unwrap_err(), "`limit`: expected a non-negative integer, got `300`");
+        assert!(list("sort=up").unwrap_err().starts_with("`sort`: unknown variant `up`"));
+    }
+
+    #[test]
+    fn strings_borrow_from_the_pairs() {
+        #[derive(Deserialize)]
+        struct Search<'a> {
+            q: &'a str,
+        }
+        let pairs = urlencoded::parse("q=plain");
+        let search: Search = from_pairs(&pairs).unwrap();
+        assert_eq!(search.q, "plain");
+    }
+
+    #[tokio::test]
+    async fn query_as_answers_400_through_the_router() {
+        async fn page(QueryAs(list): QueryAs<List>) -> String {
+            list.page.unwrap_or(1).to_string()
+        }
+        let router = || Router::new().on(Method::Get, "/", page, ());
+        assert_eq!(get(router(), "/?page=4").await.text(), "4");
+        let reply = get(router(), "/?page=-1").await;
+        assert_eq!(reply.status, 400);
+        assert_eq!(reply.text(), "invalid query string: `page`: expected a non-negative integer, got `-1`");
+    }
+}
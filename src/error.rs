@@ -2,33 +2,678 @@
 //!
 //! Application-level problems (wrong input, missing resource) belong in the
 //! response — use `Status::BadRequest`, `Status::NotFound`, etc. This type
-//! surfaces infrastructure failures only: binding to a port, accepting a
-//! connection. Things that mean the process should probably stop.
+//! surfaces connection-level failures only: binding to a port, accepting a
+//! connection, a peer that does not speak HTTP/1.1. Things that mean the
+//! connection — or the process — should probably stop.
+//!
+//! [`RouteError`] is the one startup failure: a route table that cannot be
+//! built.
+//!
+//! The rest are request-level rejections that double as responses — they
+//! implement [`IntoResponse`][crate::IntoResponse], so a handler can return
+//! them with `?`.
 
 use std::fmt;
 
+use crate::method::Method;
+use crate::response::{IntoResponse, Response};
+use crate::status::Status;
+
 /// The error type returned by astor's fallible operations.
 ///
 /// Not for 404s. Not for validation failures. Those are responses, not errors.
-/// This is for infrastructure failures — port binding, connection acceptance.
-/// The kind of failure where the right answer is probably to stop the process.
+/// This is for connection-level failures, split by who is at fault:
+///
+/// - [`Error::Io`] — the socket failed. Port binding, connection acceptance,
+///   a read or write cut short.
+/// - [`Error::Parse`] — the socket worked, but the bytes on it are not a
+///   request astor can serve. astor has already answered with
+///   [`ParseError::status`] and closed the connection.
 #[derive(Debug)]
-pub struct Error(std::io::Error);
+#[non_exhaustive]
+pub enum Error {
+    /// An I/O failure on the listener or a connection.
+    Io(std::io::Error),
+    /// The peer sent a malformed or unsupported HTTP/1.1 request.
+    Parse(ParseError),
+}
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "io: {}", self.0)
+        match self {
+            Self::Io(e)    => write!(f, "io: {e}"),
+            Self::Parse(e) => write!(f, "parse: {e}"),
+        }
     }
 }
 
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        Some(&self.0)
+        match self {
+            Self::Io(e)    => Some(e),
+            Self::Parse(e) => Some(e),
+        }
     }
 }
 
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Self {
-        Self(e)
+        Self::Io(e)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(e)
+    }
+}
+
+// ── ParseError ────────────────────────────────────────────────────────────────
+
+/// Why a request could not be parsed.
+///
+/// Each kind maps to the status astor answers with before it closes the
+/// connection — see [`status`][ParseError::status]. nginx logs that status
+/// instead of an opaque `502 Bad Gateway`.
+///
+/// All variants are listed alphabetically — add new ones in order.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ParseError {
+    /// Both `Transfer-Encoding` and `Content-Length` are present — a
+    /// request-smuggling vector (RFC 9112 §6.3). `400 Bad Request`.
+    AmbiguousFraming,
+    /// A chunked body decoded past `Server::max_chunked_body`.
+    /// `413 Content Too Large`.
+    BodyTooLarge,
+    /// A chunk-size line or chunk terminator is malformed. `400 Bad Request`.
+    InvalidChunk,
+    /// `Content-Length` is not a non-negative decimal integer, or is sent
+    /// more than once with different values. `400 Bad Request`.
+    InvalidContentLength,
+    /// A header line has no `:`, an empty or whitespace-padded name, or uses
+    /// obsolete line folding. `400 Bad Request`.
+    InvalidHeader,
+    /// The request line is not `METHOD SP target SP HTTP-version`.
+    /// `400 Bad Request`.
+    InvalidRequestLine,
+    /// The method is not a [`Method`][crate::Method] variant.
+    /// `501 Not Implemented`.
+    UnsupportedMethod,
+    /// `Transfer-Encoding` is present and astor cannot decode it — anything
+    /// but a single `chunked` across every `Transfer-Encoding` field, or
+    /// `chunked` without the `chunked` feature.
+    /// `501 Not Implemented`.
+    UnsupportedTransferEncoding,
+    /// The HTTP version is neither `HTTP/1.1` nor `HTTP/1.0`.
+    /// `505 HTTP Version Not Supported`.
+    UnsupportedVersion,
+}
+
+impl ParseError {
+    /// The status astor responds with before closing the connection.
+    ///
+    /// ```rust
+    /// use astor::ParseError;
+    ///
+    /// assert_eq!(u16::from(ParseError::InvalidHeader.status()), 400);
+    /// assert_eq!(u16::from(ParseError::UnsupportedMethod.status()), 501);
+    /// assert_eq!(u16::from(ParseError::UnsupportedVersion.status()), 505);
+    /// ```
+    pub fn status(self) -> Status {
+        match self {
+            Self::AmbiguousFraming            => Status::BadRequest,
+            Self::BodyTooLarge                => Status::ContentTooLarge,
+            Self::InvalidChunk                => Status::BadRequest,
+            Self::InvalidContentLength        => Status::BadRequest,
+            Self::InvalidHeader               => Status::BadRequest,
+            Self::InvalidRequestLine          => Status::BadRequest,
+            Self::UnsupportedMethod           => Status::NotImplemented,
+            Self::UnsupportedTransferEncoding => Status::NotImplemented,
+            Self::UnsupportedVersion          => Status::HttpVersionNotSupported,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::AmbiguousFraming            => "both transfer-encoding and content-length present",
+            Self::BodyTooLarge                => "chunked body exceeds limit",
+            Self::InvalidChunk                => "invalid chunked framing",
+            Self::InvalidContentLength        => "invalid content-length",
+            Self::InvalidHeader               => "invalid header line",
+            Self::InvalidRequestLine          => "invalid request line",
+            Self::UnsupportedMethod           => "unsupported method",
+            Self::UnsupportedTransferEncoding => "unsupported transfer-encoding",
+            Self::UnsupportedVersion          => "unsupported http version",
+        })
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// ── RouteError ────────────────────────────────────────────────────────────────
+
+/// Why a route could not be registered.
+///
+/// Returned by [`Router::try_on`][crate::Router::try_on],
+/// [`Router::try_fallback`][crate::Router::try_fallback],
+/// [`Router::try_merge`][crate::Router::try_merge],
+/// [`Router::try_nest`][crate::Router::try_nest] and
+/// [`Router::build`][crate::Router::build]. The panicking counterparts of the
+/// `try_` methods panic with this type's `Display` message.
+///
+/// `origin` fields name the sub-router a route was registered on — set with
+/// [`Router::name`][crate::Router::name] — so a collision between two merged
+/// sub-routers says which two. `None` for unnamed routers.
+///
+/// All variants are listed alphabetically — add new ones in order.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RouteError {
+    /// `path` overlaps a route already registered for `method`.
+    Conflict {
+        /// The route already registered, as written at registration.
+        existing: String,
+        /// The router `existing` was registered on.
+        existing_origin: Option<String>,
+        method: Method,
+        origin: Option<String>,
+        path: String,
+    },
+    /// Both routers being merged or nested have a fallback.
+    DuplicateFallback {
+        /// The router whose fallback was rejected.
+        origin: Option<String>,
+    },
+    /// `path` is not a valid route pattern.
+    InvalidPath {
+        method: Method,
+        origin: Option<String>,
+        path: String,
+        /// What is wrong with the pattern.
+        reason: String,
+    },
+    /// A [`nest`][crate::Router::nest] prefix does not start with `/`.
+    InvalidPrefix {
+        prefix: String,
+    },
+    /// A handler extracts state of a type no router registered with
+    /// [`with_state`][crate::Router::with_state].
+    MissingState {
+        /// `METHOD path`, or `fallback`.
+        handler: String,
+        origin: Option<String>,
+        /// The state's type name.
+        state: String,
+    },
+}
+
+impl fmt::Display for RouteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Conflict { existing, existing_origin, method, origin, path } => write!(
+                f,
+                "route `{method} {path}`{} conflicts with `{method} {existing}`{}",
+                Origin(origin),
+                Origin(existing_origin),
+            ),
+            Self::DuplicateFallback { origin } => write!(
+                f,
+                "fallback{} rejected: a fallback is already registered",
+                Origin(origin),
+            ),
+            Self::InvalidPath { method, origin, path, reason } => {
+                write!(f, "invalid route `{method} {path}`{}: {reason}", Origin(origin))
+            }
+            Self::InvalidPrefix { prefix } => {
+                write!(f, "invalid nest prefix `{prefix}`: must start with `/`")
+            }
+            Self::MissingState { handler, origin, state } => write!(
+                f,
+                "`{handler}`{} needs state `{state}`, which is not registered",
+                Origin(origin),
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RouteError {}
+
+/// Formats an optional router name as ` (router `name`)`, or nothing.
+struct Origin<'a>(&'a Option<String>);
+
+impl fmt::Display for Origin<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(name) => write!(f, " (router `{name}`)"),
+            None       => Ok(()),
+        }
+    }
+}
+
+// ── CookieError ───────────────────────────────────────────────────────────────
+
+/// Why a [`Cookie`][crate::Cookie] could not be built — returned by
+/// [`Cookie::try_new`][crate::Cookie::try_new],
+/// [`Cookie::try_domain`][crate::Cookie::try_domain] and
+/// [`Cookie::try_path`][crate::Cookie::try_path].
+///
+/// Each would corrupt the `set-cookie` header. The panicking builders panic
+/// with this type's `Display` message.
+///
+/// All variants are listed alphabetically — add new ones in order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum CookieError {
+    /// The `Domain` holds `;` or a control character.
+    InvalidDomain(String),
+    /// The name is not an HTTP token.
+    InvalidName(String),
+    /// The `Path` holds `;` or a control character.
+    InvalidPath(String),
+    /// The value holds a character RFC 6265 forbids. Holds the cookie's
+    /// name — the value itself may be a secret.
+    InvalidValue(String),
+}
+
+impl fmt::Display for CookieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidDomain(domain) => write!(f, "invalid cookie domain `{domain}`"),
+            Self::InvalidName(name)     => write!(f, "invalid cookie name `{name}`"),
+            Self::InvalidPath(path)     => write!(f, "invalid cookie path `{path}`"),
+            Self::InvalidValue(name)    => write!(f, "invalid value for cookie `{name}`"),
+        }
+    }
+}
+
+impl std::error::Error for CookieError {}
+
+// ── FormError ─────────────────────────────────────────────────────────────────
+
+/// Why a request body could not be read as a form by
+/// [`Request::form`][crate::Request::form] or
+/// [`Request::form_as`][crate::Request::form_as].
+///
+/// Answers with [`status`][FormError::status] and the message as a text body.
+///
+/// All variants are listed alphabetically — add new ones in order.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FormError {
+    /// The fields do not fit the target type — a missing field, a value that
+    /// does not parse. `422 Unprocessable Content`. Requires the `serde`
+    /// feature.
+    #[cfg(feature = "serde")]
+    Data(String),
+    /// `content-type` is missing or not `application/x-www-form-urlencoded`.
+    /// `415 Unsupported Media Type`.
+    InvalidContentType,
+}
+
+impl FormError {
+    /// The status this error answers with.
+    ///
+    /// ```rust
+    /// use astor::FormError;
+    ///
+    /// assert_eq!(u16::from(FormError::InvalidContentType.status()), 415);
+    /// ```
+    pub fn status(&self) -> Status {
+        match self {
+            #[cfg(feature = "serde")]
+            Self::Data(_)            => Status::UnprocessableContent,
+            Self::InvalidContentType => Status::UnsupportedMediaType,
+        }
+    }
+}
+
+impl fmt::Display for FormError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "serde")]
+            Self::Data(e)            => write!(f, "invalid form body: {e}"),
+            Self::InvalidContentType => f.write_str("expected content-type application/x-www-form-urlencoded"),
+        }
+    }
+}
+
+impl std::error::Error for FormError {}
+
+impl IntoResponse for FormError {
+    fn into_response(self) -> Response {
+        Response::builder().status(self.status()).text(self.to_string())
+    }
+}
+
+// ── JsonError ─────────────────────────────────────────────────────────────────
+
+/// Why a request body could not be read as JSON by
+/// [`Request::json`][crate::Request::json] or the
+/// [`Json`][crate::Json] extractor.
+///
+/// Answers with [`status`][JsonError::status] and the message as a text body.
+///
+/// All variants are listed alphabetically — add new ones in order.
+#[cfg(feature = "json")]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum JsonError {
+    /// Well-formed JSON of the wrong shape — a missing field, a string where
+    /// a number belongs. `422 Unprocessable Content`.
+    Data(String),
+    /// `content-type` is missing or not `application/json` (or
+    /// `application/*+json`). `415 Unsupported Media Type`.
+    InvalidContentType,
+    /// Not JSON at all, or cut short. `400 Bad Request`.
+    Syntax(String),
+}
+
+#[cfg(feature = "json")]
+impl JsonError {
+    /// The status this error answers with.
+    ///
+    /// ```rust
+    /// use astor::JsonError;
+    ///
+    /// assert_eq!(u16::from(JsonError::InvalidContentType.status()), 415);
+    /// ```
+    pub fn status(&self) -> Status {
+        match self {
+            Self::Data(_)            => Status::UnprocessableContent,
+            Self::InvalidContentType => Status::UnsupportedMediaType,
+            Self::Syntax(_)          => Status::BadRequest,
+        }
+    }
+
+    pub(crate) fn new(e: serde_json::Error) -> Self {
+        match e.classify() {
+            serde_json::error::Category::Data => Self::Data(e.to_string()),
+            _                                 => Self::Syntax(e.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Data(e)            => write!(f, "invalid json body: {e}"),
+            Self::InvalidContentType => f.write_str("expected content-type application/json"),
+            Self::Syntax(e)          => write!(f, "malformed json body: {e}"),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl std::error::Error for JsonError {}
+
+#[cfg(feature = "json")]
+impl IntoResponse for JsonError {
+    fn into_response(self) -> Response {
+        Response::builder().status(self.status()).text(self.to_string())
+    }
+}
+
+// ── MultipartError ────────────────────────────────────────────────────────────
+
+/// Why a `multipart/form-data` body could not be read — see
+/// [`Multipart`][crate::multipart::Multipart].
+///
+/// Answers with [`status`][MultipartError::status] and the message as a text
+/// body.
+///
+/// All variants are listed alphabetically — add new ones in order.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum MultipartError {
+    /// `content-type` has no `boundary` parameter, or one longer than 70
+    /// characters. `400 Bad Request`.
+    InvalidBoundary,
+    /// `content-type` is missing or not `multipart/form-data`.
+    /// `415 Unsupported Media Type`.
+    InvalidContentType,
+    /// A part has a malformed header, or no `content-disposition: form-data`
+    /// with a `name`, or its header block — or the preamble before the first
+    /// boundary — runs past 16 KiB. `400 Bad Request`.
+    InvalidPart,
+    /// One part is larger than
+    /// [`max_part_size`][crate::multipart::Multipart::max_part_size].
+    /// `413 Content Too Large`.
+    PartTooLarge,
+    /// The parts together are larger than
+    /// [`max_total_size`][crate::multipart::Multipart::max_total_size].
+    /// `413 Content Too Large`.
+    TooLarge,
+    /// The body ends before its closing boundary. `400 Bad Request`.
+    UnexpectedEnd,
+}
+
+impl MultipartError {
+    /// The status this error answers with.
+    ///
+    /// ```rust
+    /// use astor::MultipartError;
+    ///
+    /// assert_eq!(u16::from(MultipartError::InvalidContentType.status()), 415);
+    /// assert_eq!(u16::from(MultipartError::PartTooLarge.status()), 413);
+    /// ```
+    pub fn status(self) -> Status {
+        match self {
+            Self::InvalidBoundary    => Status::BadRequest,
+            Self::InvalidContentType => Status::UnsupportedMediaType,
+            Self::InvalidPart        => Status::BadRequest,
+            Self::PartTooLarge       => Status::ContentTooLarge,
+            Self::TooLarge           => Status::ContentTooLarge,
+            Self::UnexpectedEnd      => Status::BadRequest,
+        }
+    }
+}
+
+impl fmt::Display for MultipartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidBoundary    => "invalid multipart boundary",
+            Self::InvalidContentType => "expected content-type multipart/form-data",
+            Self::InvalidPart        => "invalid multipart part headers",
+            Self::PartTooLarge       => "multipart part exceeds limit",
+            Self::TooLarge           => "multipart body exceeds limit",
+            Self::UnexpectedEnd      => "multipart body ends before its closing boundary",
+        })
+    }
+}
+
+impl std::error::Error for MultipartError {}
+
+impl IntoResponse for MultipartError {
+    fn into_response(self) -> Response {
+        Response::builder().status(self.status()).text(self.to_string())
+    }
+}
+
+// ── QueryError ────────────────────────────────────────────────────────────────
+
+/// The query string does not fit the type asked of
+/// [`Request::query_as`][crate::Request::query_as].
+///
+/// Answers `400 Bad Request` with the message as a text body, naming the
+/// offending parameter:
+///
+/// ```text
+/// invalid query string: `page`: expected a non-negative integer, got `abc`
+/// ```
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct QueryError {
+    message: String,
+}
+
+#[cfg(feature = "serde")]
+impl QueryError {
+    pub(crate) fn new(e: crate::de::Error) -> Self {
+        Self { message: e.into_message() }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid query string: {}", self.message)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::error::Error for QueryError {}
+
+#[cfg(feature = "serde")]
+impl IntoResponse for QueryError {
+    fn into_response(self) -> Response {
+        Response::builder().status(Status::BadRequest).text(self.to_string())
+    }
+}
+
+// ── UpgradeError ──────────────────────────────────────────────────────────────
+
+/// Why a request could not be upgraded to a WebSocket — see
+/// [`WebSocketUpgrade`][crate::ws::WebSocketUpgrade].
+///
+/// Answers with [`status`][UpgradeError::status] and the message as a text
+/// body. `426` answers also say what the client should have sent: an
+/// `upgrade: websocket` header, or `sec-websocket-version: 13`.
+///
+/// All variants are listed alphabetically — add new ones in order.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum UpgradeError {
+    /// `sec-websocket-key` is missing or not 16 base64-encoded bytes.
+    /// `400 Bad Request`.
+    InvalidKey,
+    /// Not a `GET` with `upgrade: websocket` and `connection: upgrade` —
+    /// often nginx dropping both hop-by-hop headers. `426 Upgrade Required`.
+    NotUpgrade,
+    /// `sec-websocket-version` is missing or not `13`.
+    /// `426 Upgrade Required`.
+    UnsupportedVersion,
+}
+
+impl UpgradeError {
+    /// The status this error answers with.
+    ///
+    /// ```rust
+    /// use astor::UpgradeError;
+    ///
+    /// assert_eq!(u16::from(UpgradeError::InvalidKey.status()), 400);
+    /// assert_eq!(u16::from(UpgradeError::NotUpgrade.status()), 426);
+    /// ```
+    pub fn status(self) -> Status {
+        match self {
+            Self::InvalidKey         => Status::BadRequest,
+            Self::NotUpgrade         => Status::UpgradeRequired,
+            Self::UnsupportedVersion => Status::UpgradeRequired,
+        }
+    }
+}
+
+impl fmt::Display for UpgradeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidKey         => "invalid sec-websocket-key",
+            Self::NotUpgrade         => "expected a websocket upgrade request",
+            Self::UnsupportedVersion => "unsupported websocket version, expected 13",
+        })
+    }
+}
+
+impl std::error::Error for UpgradeError {}
+
+impl IntoResponse for UpgradeError {
+    fn into_response(self) -> Response {
+        let builder = Response::builder().status(self.status());
+        let builder = match self {
+            Self::InvalidKey         => builder,
+            Self::NotUpgrade         => builder.header("upgrade", "websocket"),
+            Self::UnsupportedVersion => builder.header("sec-websocket-version", "13"),
+        };
+        builder.text(self.to_string())
+    }
+}
+
+// ── WebSocketError ────────────────────────────────────────────────────────────
+
+/// Why a [`WebSocket`][crate::ws::WebSocket] stopped.
+///
+/// On a protocol failure astor has already sent the peer a close frame with
+/// the matching [`close_code`][WebSocketError::close_code]; the socket
+/// yields nothing more.
+///
+/// All variants are listed alphabetically — add new ones in order.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WebSocketError {
+    /// A message was sent after the close handshake started.
+    Closed,
+    /// A ping or pong payload over 125 bytes, or a close reason over 123,
+    /// was passed to [`send`][crate::ws::WebSocket::send] — RFC 6455 caps
+    /// control frames. Nothing was sent.
+    ControlFrameTooLarge,
+    /// A text message or a close reason is not valid UTF-8. Close code `1007`.
+    InvalidUtf8,
+    /// The connection failed.
+    Io(std::io::Error),
+    /// A message is larger than
+    /// [`max_message_size`][crate::ws::WebSocketUpgrade::max_message_size].
+    /// Close code `1009`.
+    MessageTooLarge,
+    /// The peer broke RFC 6455 — an unmasked frame, reserved bits, a
+    /// fragmented control frame and the like. Close code `1002`.
+    Protocol(&'static str),
+}
+
+impl WebSocketError {
+    /// The close code sent to the peer, if any.
+    ///
+    /// ```rust
+    /// use astor::WebSocketError;
+    ///
+    /// assert_eq!(WebSocketError::InvalidUtf8.close_code(), Some(1007));
+    /// assert_eq!(WebSocketError::Closed.close_code(), None);
+    /// ```
+    pub fn close_code(&self) -> Option<u16> {
+        match self {
+            Self::Closed | Self::ControlFrameTooLarge | Self::Io(_) => None,
+            Self::InvalidUtf8                                       => Some(1007),
+            Self::MessageTooLarge                                   => Some(1009),
+            Self::Protocol(_)                                       => Some(1002),
+        }
+    }
+}
+
+impl fmt::Display for WebSocketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Closed               => f.write_str("websocket is closing"),
+            Self::ControlFrameTooLarge => f.write_str("websocket control frame over 125 bytes"),
+            Self::InvalidUtf8          => f.write_str("websocket text is not valid UTF-8"),
+            Self::Io(e)                => write!(f, "io: {e}"),
+            Self::MessageTooLarge      => f.write_str("websocket message exceeds limit"),
+            Self::Protocol(why)        => write!(f, "websocket protocol error: {why}"),
+        }
+    }
+}
+
+impl std::error::Error for WebSocketError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _           => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for WebSocketError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
     }
 }
@@ -0,0 +1,159 @@
+//! Typed per-request storage for passing data down the middleware chain.
+//!
+//! Middleware decodes something once — a user id from a token, a request id,
+//! a tenant — and stores it on the request before calling
+//! [`Next::call`][crate::Next::call]. Handlers and later middleware read it
+//! back by type. No re-parsing headers, no stringly-typed keys.
+//!
+//! ```rust,no_run
+//! use astor::{Next, Request, Response, Status};
+//!
+//! struct UserId(u64);
+//!
+//! async fn require_auth(mut req: Request, next: Next) -> Response {
+//!     let Some(id) = req.header("authorization").and_then(|t| t.parse().ok()) else {
+//!         return Response::status(Status::Unauthorized);
+//!     };
+//!     req.extensions_mut().insert(UserId(id));
+//!     next.call(req).await
+//! }
+//!
+//! async fn me(req: Request) -> Response {
+//!     match req.extensions().get::<UserId>() {
+//!         Some(UserId(id)) => Response::text(id.to_string()),
+//!         None             => Response::status(Status::Unauthorized),
+//!     }
+//! }
+//! ```
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A map holding at most one value per type.
+///
+/// Every [`Request`][crate::Request] carries one, empty until middleware
+/// fills it — an empty map does not allocate. Values must be
+/// `Send + Sync + 'static` so the request can cross threads and await points.
+///
+/// Define a newtype per value rather than storing bare `u64`s or `String`s:
+/// the type *is* the key, and two middleware inserting a `String` would
+/// overwrite each other.
+///
+/// ```rust
+/// use astor::Extensions;
+///
+/// struct RequestId(String);
+///
+/// let mut ext = Extensions::new();
+/// assert!(ext.insert(RequestId("abc".into())).is_none());
+/// assert_eq!(ext.get::<RequestId>().map(|r| r.0.as_str()), Some("abc"));
+///
+/// ext.get_mut::<RequestId>().unwrap().0.push('!');
+/// assert_eq!(ext.remove::<RequestId>().map(|r| r.0), Some("abc!".to_owned()));
+/// assert!(ext.get::<RequestId>().is_none());
+/// ```
+#[derive(Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a reference to the value of type `T`, if present.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Returns a mutable reference to the value of type `T`, if present.
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.map.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+
+    /// Stores `value`, returning the previous value of type `T` if there was one.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        let prev = self.map.insert(TypeId::of::<T>(), Box::new(value))?;
+        prev.downcast().ok().map(|boxed| *boxed)
+    }
+
+    /// Removes and returns the value of type `T`, if present.
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        let boxed = self.map.remove(&TypeId::of::<T>())?;
+        boxed.downcast().ok().map(|boxed| *boxed)
+    }
+
+    /// Number of stored values.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// `true` if no value is stored.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions").field("len", &self.map.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::method::Method;
+    use crate::middleware::Next;
+    use crate::request::Request;
+    use crate::response::Response;
+    use crate::router::Router;
+    use crate::status::Status;
+    use crate::testing::{exchange, get, send};
+
+    struct UserId(u64);
+
+    #[test]
+    fn one_value_per_type() {
+        let mut ext = Extensions::new();
+        assert!(ext.is_empty());
+        assert!(ext.insert(UserId(1)).is_none());
+        assert_eq!(ext.insert(UserId(2)).map(|u| u.0), Some(1));
+        ext.insert(7u8);
+        assert_eq!(ext.len(), 2);
+        assert_eq!(ext.get::<UserId>().map(|u| u.0), Some(2));
+        assert!(ext.remove::<u16>().is_none());
+    }
+
+    async fn auth(mut req: Request, next: Next) -> Response {
+        let Some(id) = req.header("authorization").and_then(|v| v.parse().ok()) else {
+            return Response::status(Status::Unauthorized);
+        };
+        req.extensions_mut().insert(UserId(id));
+        next.call(req).await
+    }
+
+    async fn me(req: Request) -> String {
+        req.extensions().get::<UserId>().map_or("none".into(), |u| u.0.to_string())
+    }
+
+    #[tokio::test]
+    async fn middleware_hands_values_to_the_handler() {
+        let router = || Router::new().on(Method::Get, "/me", me, auth).on(Method::Get, "/open", me, ());
+        let reply = send(router(), "GET /me HTTP/1.1\r\nauthorization: 42\r\n\r\n").await;
+        assert_eq!(reply.text(), "42");
+        assert_eq!(get(router(), "/me").await.status, 401);
+        assert_eq!(get(router(), "/open").await.text(), "none");
+    }
+
+    #[tokio::test]
+    async fn extensions_do_not_leak_between_requests() {
+        let router = Router::new().on(Method::Get, "/me", me, ()).on(Method::Get, "/login", me, auth);
+        let raw = "GET /login HTTP/1.1\r\nauthorization: 1\r\n\r\nGET /me HTTP/1.1\r\n\r\n";
+        let out = exchange(router, raw).await;
+        assert!(out.ends_with("\r\n\r\nnone"), "{out}");
+    }
+}
@@ -0,0 +1,625 @@
+//! Extractors — typed handler arguments pulled from the request.
+//!
+//! A handler takes the pieces of the request it needs instead of the whole
+//! [`Request`]. Each argument type says how to get itself out of the request,
+//! and the router does the rest:
+//!
+//! ```rust,no_run
+//! use astor::extract::{Path, Query, State};
+//! use astor::{Method, Response, Router, Status};
+//!
+//! struct Db;
+//! # impl Db { async fn post(&self, _: u64) -> Option<String> { None } }
+//!
+//! // GET /posts/{id}?format=short
+//! async fn get_post(Path(id): Path<u64>, Query(q): Query, State(db): State<Db>) -> Response {
+//!     let Some(post) = db.post(id).await else {
+//!         return Response::status(Status::NotFound);
+//!     };
+//!     match q.get("format").map(String::as_str) {
+//!         Some("short") => Response::text(post.chars().take(80).collect::<String>()),
+//!         _             => Response::text(post),
+//!     }
+//! }
+//!
+//! let app = Router::new()
+//!     .with_state(Db)
+//!     .on(Method::Get, "/posts/{id}", get_post, ());
+//! ```
+//!
+//! # Parts and body
+//!
+//! Extractors come in two kinds:
+//!
+//! - [`FromRequestParts`] — reads the method, path, headers, state or
+//!   extensions. Any number of these, in any argument position.
+//! - [`FromRequest`] — consumes the request: the body as [`Vec<u8>`] or
+//!   [`String`], or the [`Request`] itself. Only the **last** argument may
+//!   be one, because there is one body to consume.
+//!
+//! Every `FromRequestParts` type is also a `FromRequest` type, so a handler
+//! that never reads the body can end on any extractor.
+//!
+//! # Rejections
+//!
+//! An extractor that fails returns its `Rejection`, which becomes the
+//! response — the handler never runs. The built-in extractors reject with a
+//! [`Rejection`]: `400 Bad Request` for client mistakes (an unparseable path
+//! parameter, a body that is not UTF-8) and `500 Internal Server Error` for
+//! server wiring mistakes (state that was never registered, an extension no
+//! middleware inserted).
+//!
+//! Extractors that cannot fail use [`Infallible`] as their rejection.
+//!
+//! # Writing your own
+//!
+//! Implement [`FromRequestParts`] for anything derived from headers,
+//! extensions or state:
+//!
+//! ```rust,no_run
+//! use astor::extract::{FromRequestParts, Rejection};
+//! use astor::{Request, Status};
+//!
+//! struct Bearer(String);
+//!
+//! impl FromRequestParts for Bearer {
+//!     type Rejection = Rejection;
+//!
+//!     fn from_request_parts(req: &mut Request) -> Result<Self, Rejection> {
+//!         req.header("authorization")
+//!             .and_then(|v| v.strip_prefix("Bearer "))
+//!             .map(|token| Bearer(token.to_owned()))
+//!             .ok_or_else(|| Rejection::new(Status::Unauthorized, "missing bearer token"))
+//!     }
+//! }
+//!
+//! async fn me(Bearer(token): Bearer) -> String { token }
+//! ```
+
+use std::any::TypeId;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::future::{self, Future};
+use std::ops::Deref;
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[cfg(feature = "serde")]
+use crate::error::{FormError, QueryError};
+use crate::method::Method;
+use crate::request::Request;
+use crate::response::{IntoResponse, Response};
+use crate::status::Status;
+
+// ── Traits ────────────────────────────────────────────────────────────────────
+
+/// An extractor that reads from the request without consuming it.
+///
+/// Takes `&mut Request` so an extractor may also take what it reads — an
+/// extension, say — out of the request. Later extractors see that change.
+pub trait FromRequestParts: Sized + Send {
+    /// The response sent when extraction fails.
+    type Rejection: IntoResponse + Send;
+
+    /// Extracts `Self` from the request.
+    ///
+    /// # Errors
+    ///
+    /// `Self::Rejection` when the request does not carry what `Self` needs.
+    fn from_request_parts(req: &mut Request) -> Result<Self, Self::Rejection>;
+
+    /// The [`Router::with_state`][crate::Router::with_state] type this
+    /// extractor cannot work without, with its name — checked up front by
+    /// [`Router::build`][crate::Router::build].
+    #[doc(hidden)]
+    fn required_state() -> Option<(TypeId, &'static str)> {
+        None
+    }
+}
+
+/// An extractor that consumes the request — only valid as a handler's last
+/// argument.
+///
+/// `M` is an inference marker that keeps this trait's direct impls apart
+/// from the blanket impl for [`FromRequestParts`] types. Leave it at its
+/// default when implementing the trait.
+///
+/// Implementations may be written with `async fn`.
+pub trait FromRequest<M = private::ViaRequest>: Sized + Send {
+    /// The response sent when extraction fails.
+    type Rejection: IntoResponse + Send;
+
+    /// Extracts `Self` from the request.
+    fn from_request(req: Request) -> impl Future<Output = Result<Self, Self::Rejection>> + Send;
+
+    /// As [`FromRequestParts::required_state`].
+    #[doc(hidden)]
+    fn required_state() -> Option<(TypeId, &'static str)> {
+        None
+    }
+}
+
+/// Marker types for [`FromRequest`]. Public so they can appear in the trait's
+/// signature; unnameable outside the crate.
+mod private {
+    #[derive(Debug)]
+    pub enum ViaParts {}
+
+    #[derive(Debug)]
+    pub enum ViaRequest {}
+}
+
+impl<T: FromRequestParts> FromRequest<private::ViaParts> for T {
+    type Rejection = T::Rejection;
+
+    fn from_request(mut req: Request) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        future::ready(T::from_request_parts(&mut req))
+    }
+
+    fn required_state() -> Option<(TypeId, &'static str)> {
+        <T as FromRequestParts>::required_state()
+    }
+}
+
+// ── Rejection ─────────────────────────────────────────────────────────────────
+
+/// Why a built-in extractor refused a request.
+///
+/// Becomes a plain-text response with [`status`][Rejection::status] and
+/// [`message`][Rejection::message] as the body. Use it for your own
+/// extractors too, or define a rejection type that renders your API's error
+/// format.
+#[derive(Debug)]
+pub struct Rejection {
+    message: Cow<'static, str>,
+    status: Status,
+}
+
+impl Rejection {
+    /// A rejection answering `status` with `message` as a text body.
+    pub fn new(status: Status, message: impl Into<Cow<'static, str>>) -> Self {
+        Self { message: message.into(), status }
+    }
+
+    /// The response status.
+    pub fn status(&self) -> Status { self.status }
+
+    /// The response body.
+    pub fn message(&self) -> &str { &self.message }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", u16::from(self.status), self.message)
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        Response::builder().status(self.status).text(self.message.into_owned())
+    }
+}
+
+// ── Whole request and body ────────────────────────────────────────────────────
+
+/// The whole request — the one-argument handler signature.
+impl FromRequest for Request {
+    type Rejection = Infallible;
+
+    fn from_request(req: Request) -> impl Future<Output = Result<Self, Infallible>> + Send {
+        future::ready(Ok(req))
+    }
+}
+
+/// The raw body bytes.
+impl FromRequest for Vec<u8> {
+    type Rejection = Infallible;
+
+    fn from_request(req: Request) -> impl Future<Output = Result<Self, Infallible>> + Send {
+        future::ready(Ok(req.body))
+    }
+}
+
+/// The body as UTF-8 text. `400 Bad Request` if it is not valid UTF-8.
+impl FromRequest for String {
+    type Rejection = Rejection;
+
+    fn from_request(req: Request) -> impl Future<Output = Result<Self, Rejection>> + Send {
+        future::ready(String::from_utf8(req.body).map_err(|_| {
+            Rejection::new(Status::BadRequest, "request body is not valid UTF-8")
+        }))
+    }
+}
+
+/// The body deserialized from `application/x-www-form-urlencoded` — see
+/// [`Request::form_as`]. Rejects with [`FormError`].
+///
+/// Requires the `serde` feature.
+///
+/// ```rust,no_run
+/// use astor::extract::Form;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Login { user: String, password: String }
+///
+/// async fn login(Form(login): Form<Login>) -> String {
+///     # let _ = login.password;
+///     format!("welcome, {}", login.user)
+/// }
+/// ```
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct Form<T>(pub T);
+
+#[cfg(feature = "serde")]
+impl<T: serde::de::DeserializeOwned + Send> FromRequest for Form<T> {
+    type Rejection = FormError;
+
+    fn from_request(req: Request) -> impl Future<Output = Result<Self, FormError>> + Send {
+        future::ready(req.form_as().map(Form))
+    }
+}
+
+// ── Parts ─────────────────────────────────────────────────────────────────────
+
+impl FromRequestParts for Method {
+    type Rejection = Infallible;
+
+    fn from_request_parts(req: &mut Request) -> Result<Self, Infallible> {
+        Ok(req.method)
+    }
+}
+
+/// A single typed path parameter.
+///
+/// For routes with exactly one `{param}`. The value is parsed with
+/// [`FromStr`] — `400 Bad Request` if that fails. Registering a `Path`
+/// handler on a route with no parameter, or several, answers
+/// `500 Internal Server Error`; use [`Params`] for those.
+///
+/// ```rust,no_run
+/// use astor::extract::Path;
+///
+/// // Route: /users/{id}
+/// async fn get_user(Path(id): Path<u64>) -> String {
+///     format!("user {id}")
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Path<T>(pub T);
+
+impl<T: FromStr + Send> FromRequestParts for Path<T> {
+    type Rejection = Rejection;
+
+    fn from_request_parts(req: &mut Request) -> Result<Self, Rejection> {
+        let mut params = req.params.iter();
+        let (Some((name, value)), None) = (params.next(), params.next()) else {
+            return Err(Rejection::new(
+                Status::InternalServerError,
+                "Path<T> needs a route with exactly one parameter",
+            ));
+        };
+        value.parse().map(Path).map_err(|_| {
+            Rejection::new(Status::BadRequest, format!("invalid path parameter `{name}`"))
+        })
+    }
+}
+
+/// Every path parameter, by name — see [`Request::param`].
+///
+/// ```rust,no_run
+/// use astor::extract::Params;
+///
+/// // Route: /orgs/{org}/repos/{repo}
+/// async fn get_repo(Params(p): Params) -> String {
+///     format!("{}/{}", p["org"], p["repo"])
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Params(pub HashMap<String, String>);
+
+impl FromRequestParts for Params {
+    type Rejection = Infallible;
+
+    fn from_request_parts(req: &mut Request) -> Result<Self, Infallible> {
+        Ok(Params(req.params.clone()))
+    }
+}
+
+/// The decoded query string as a map — see [`Request::query`].
+///
+/// For a repeated key the last value wins. Use
+/// [`Request::query_all`] when repeats matter.
+#[derive(Debug)]
+pub struct Query(pub HashMap<String, String>);
+
+impl FromRequestParts for Query {
+    type Rejection = Infallible;
+
+    fn from_request_parts(req: &mut Request) -> Result<Self, Infallible> {
+        Ok(Query(req.query.iter().cloned().collect()))
+    }
+}
+
+/// The query string deserialized into `T` — see
+/// [`Request::query_as`]. `400 Bad Request` if it does not fit.
+///
+/// Requires the `serde` feature.
+///
+/// ```rust,no_run
+/// use astor::extract::QueryAs;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Page { page: Option<u32> }
+///
+/// async fn list(QueryAs(p): QueryAs<Page>) -> String {
+///     format!("page {}", p.page.unwrap_or(1))
+/// }
+/// ```
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct QueryAs<T>(pub T);
+
+#[cfg(feature = "serde")]
+impl<T: serde::de::DeserializeOwned + Send> FromRequestParts for QueryAs<T> {
+    type Rejection = QueryError;
+
+    fn from_request_parts(req: &mut Request) -> Result<Self, QueryError> {
+        req.query_as().map(QueryAs)
+    }
+}
+
+/// Every request header as name-value pairs — see [`Request::headers`].
+#[derive(Debug)]
+pub struct Headers(pub Vec<(String, String)>);
+
+impl FromRequestParts for Headers {
+    type Rejection = Infallible;
+
+    fn from_request_parts(req: &mut Request) -> Result<Self, Infallible> {
+        Ok(Headers(req.headers.clone()))
+    }
+}
+
+/// Shared state registered with [`Router::with_state`][crate::Router::with_state].
+///
+/// `500 Internal Server Error` if no state of type `S` was registered — a
+/// wiring mistake, caught by the first request that hits the route, or at
+/// startup by [`Router::build`][crate::Router::build].
+///
+/// ```rust,no_run
+/// use astor::extract::State;
+/// use astor::{Method, Router};
+///
+/// struct Config { greeting: String }
+///
+/// async fn hello(State(config): State<Config>) -> String {
+///     config.greeting.clone()
+/// }
+///
+/// let app = Router::new()
+///     .with_state(Config { greeting: "hello".into() })
+///     .on(Method::Get, "/", hello, ());
+/// ```
+pub struct State<S>(pub Arc<S>);
+
+impl<S: Send + Sync + 'static> FromRequestParts for State<S> {
+    type Rejection = Rejection;
+
+    fn from_request_parts(req: &mut Request) -> Result<Self, Rejection> {
+        req.state_arc().map(State).ok_or_else(missing)
+    }
+
+    fn required_state() -> Option<(TypeId, &'static str)> {
+        Some((TypeId::of::<S>(), std::any::type_name::<S>()))
+    }
+}
+
+impl<S> Deref for State<S> {
+    type Target = S;
+
+    fn deref(&self) -> &S { &self.0 }
+}
+
+/// A clone of a value middleware inserted into the request's
+/// [`Extensions`][crate::Extensions].
+///
+/// `500 Internal Server Error` if the extension is missing — the middleware
+/// that inserts it is not on the route.
+///
+/// ```rust,no_run
+/// use astor::extract::Extension;
+///
+/// #[derive(Clone)]
+/// struct UserId(u64);
+///
+/// async fn me(Extension(UserId(id)): Extension<UserId>) -> String {
+///     id.to_string()
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Extension<T>(pub T);
+
+impl<T: Clone + Send + Sync + 'static> FromRequestParts for Extension<T> {
+    type Rejection = Rejection;
+
+    fn from_request_parts(req: &mut Request) -> Result<Self, Rejection> {
+        req.extensions.get::<T>()
+            .cloned()
+            .map(Extension)
+            .ok_or_else(missing)
+    }
+}
+
+impl<T> Deref for Extension<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T { &self.0 }
+}
+
+// ── Internal ──────────────────────────────────────────────────────────────────
+
+/// The `500` for a state or extension type nobody provided. The body stays
+/// empty — type paths are internals, not for clients;
+/// [`Router::build`][crate::Router::build] names missing state at startup.
+pub(crate) fn missing() -> Rejection {
+    Rejection::new(Status::InternalServerError, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::Next;
+    use crate::router::Router;
+    use crate::testing::{Reply, get, send};
+
+    async fn user(Path(id): Path<u64>) -> String {
+        format!(r#"{{"id":{id}}}"#)
+    }
+
+    fn route<T>(path: &str, handler: impl crate::handler::Handler<T>) -> Router {
+        Router::new().on(Method::Get, path, handler, ())
+    }
+
+    fn rejected(reply: &Reply) -> (u16, &str) {
+        (reply.status, reply.text())
+    }
+
+    #[tokio::test]
+    async fn path_parses_or_answers_400() {
+        assert_eq!(get(route("/users/{id}", user), "/users/42").await.text(), r#"{"id":42}"#);
+        let reply = get(route("/users/{id}", user), "/users/abc").await;
+        assert_eq!(rejected(&reply), (400, "invalid path parameter `id`"));
+        assert_eq!(reply.header("content-type"), Some("text/plain; charset=utf-8"));
+    }
+
+    #[tokio::test]
+    async fn path_needs_exactly_one_parameter() {
+        assert_eq!(get(route("/users", user), "/users").await.status, 500);
+        assert_eq!(get(route("/{a}/{b}", user), "/1/2").await.status, 500);
+    }
+
+    #[tokio::test]
+    async fn params_query_headers_and_method() {
+        async fn all(method: Method, Params(p): Params, Query(q): Query, Headers(h): Headers) -> String {
+            let agent = h.iter().find(|(k, _)| k == "user-agent").map_or("", |(_, v)| v.as_str());
+            format!("{method} {}/{} {} {agent}", p["org"], p["repo"], q["tab"])
+        }
+        let raw = "GET /astor/web?tab=issues&tab=code HTTP/1.1\r\nuser-agent: test\r\n\r\n";
+        let reply = send(route("/{org}/{repo}", all), raw).await;
+        assert_eq!(reply.text(), "GET astor/web code test");
+    }
+
+    #[tokio::test]
+    async fn state_is_shared_or_answers_500() {
+        async fn hello(State(greeting): State<&'static str>) -> &'static str {
+            *greeting
+        }
+        assert_eq!(get(route("/", hello).with_state("hi"), "/").await.text(), "hi");
+        let reply = get(route("/", hello), "/").await;
+        assert_eq!(rejected(&reply), (500, ""));
+    }
+
+    #[tokio::test]
+    async fn extension_is_cloned_or_answers_500() {
+        #[derive(Clone)]
+        struct UserId(u64);
+        async fn me(Extension(UserId(id)): Extension<UserId>) -> String {
+            id.to_string()
+        }
+        async fn auth(mut req: Request, next: Next) -> Response {
+            req.extensions_mut().insert(UserId(9));
+            next.call(req).await
+        }
+        let router = Router::new().on(Method::Get, "/me", me, auth);
+        assert_eq!(get(router, "/me").await.text(), "9");
+        let router = Router::new().on(Method::Get, "/open", me, ());
+        let reply = get(router, "/open").await;
+        assert_eq!(rejected(&reply), (500, ""));
+    }
+
+    #[tokio::test]
+    async fn body_as_bytes_or_text() {
+        async fn bytes(body: Vec<u8>) -> String {
+            body.len().to_string()
+        }
+        async fn text(body: String) -> String {
+            body.to_uppercase()
+        }
+        let post = |body: &[u8]| {
+            let mut raw = format!("POST / HTTP/1.1\r\ncontent-length: {}\r\n\r\n", body.len()).into_bytes();
+            raw.extend_from_slice(body);
+            raw
+        };
+        let text = || Router::new().on(Method::Post, "/", text, ());
+        let bytes = Router::new().on(Method::Post, "/", bytes, ());
+        assert_eq!(send(bytes, post(b"\xff\xfe")).await.text(), "2");
+        assert_eq!(send(text(), post(b"abc")).await.text(), "ABC");
+        let reply = send(text(), post(b"\xff")).await;
+        assert_eq!(rejected(&reply), (400, "request body is not valid UTF-8"));
+    }
+
+    #[tokio::test]
+    async fn a_rejection_stops_later_extractors_and_the_handler() {
+        struct Probe;
+        impl FromRequestParts for Probe {
+            type Rejection = Rejection;
+            fn from_request_parts(req: &mut Request) -> Result<Self, Rejection> {
+                req.extensions_mut().insert(Probe);
+                Err(Rejection::new(Status::Unauthorized, "no"))
+            }
+        }
+        async fn never(_: Probe, _: Path<u64>) -> &'static str {
+            unreachable!()
+        }
+        let reply = get(route("/{id}", never), "/abc").await;
+        assert_eq!(rejected(&reply), (401, "no"));
+    }
+
+    #[tokio::test]
+    async fn handlers_take_up_to_eight_arguments() {
+        #[allow(clippy::too_many_arguments)]
+        async fn eight(
+            _: Method,
+            Path(id): Path<u32>,
+            _: Params,
+            Query(q): Query,
+            _: Headers,
+            State(n): State<u32>,
+            State(s): State<&'static str>,
+            body: String,
+        ) -> String {
+            format!("{id} {} {n}{s} {body}", q["x"])
+        }
+        let router = Router::new().with_state(3u32).with_state("!").on(Method::Post, "/{id}", eight, ());
+        let reply = send(router, "POST /7?x=y HTTP/1.1\r\ncontent-length: 2\r\n\r\nhi").await;
+        assert_eq!(reply.text(), "7 y 3! hi");
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn form_is_deserialized_or_rejected() {
+        #[derive(serde::Deserialize)]
+        struct Login {
+            user: String,
+        }
+        async fn login(Form(login): Form<Login>) -> String {
+            format!("welcome, {}", login.user)
+        }
+        let post = |content_type: &str, body: &str| {
+            let router = Router::new().on(Method::Post, "/login", login, ());
+            let raw = format!(
+                "POST /login HTTP/1.1\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\n\r\n{body}",
+                body.len(),
+            );
+            send(router, raw)
+        };
+        assert_eq!(post("application/x-www-form-urlencoded", "user=ann").await.text(), "welcome, ann");
+        assert_eq!(post("text/plain", "user=ann").await.status, 415);
+        assert_eq!(post("application/x-www-form-urlencoded", "name=ann").await.status, 422);
+    }
+}
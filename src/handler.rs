@@ -10,24 +10,27 @@
 //! The chain from user code to vtable call is:
 //!
 //! ```text
-//! async fn hello(req: Request) -> Response { … }   ← user writes this
-//!        ↓ router.get("/", hello)
-//! hello.into_boxed_handler()                       ← Handler blanket impl
+//! async fn hello(Path(id): Path<u64>) -> Response { … }   ← user writes this
+//!        ↓ router.on(Method::Get, "/{id}", hello, ())
+//! hello.into_boxed_handler()                              ← macro-generated impl
 //!        ↓
-//! Arc::new(FnHandler(hello))                       ← heap-allocated wrapper
+//! Arc::new(FnHandler(closure over Arc::new(hello)))       ← heap-allocated wrapper
 //!        ↓  stored as BoxedHandler = Arc<dyn ErasedHandler>
-//! handler.call(req)  at request time               ← one vtable dispatch
+//! handler.call(req)  at request time                      ← one vtable dispatch
 //!        ↓
-//! Box::pin(async { hello(req).await.into_response() })  ← BoxFuture
+//! Box::pin(async { extract args; hello(args).await.into_response() })  ← BoxFuture
 //! ```
 //!
-//! The only runtime cost per request is **one Arc clone** (atomic inc) +
-//! **one virtual call** — negligible compared to network I/O.
+//! The only runtime cost per request is **two Arc clones** (atomic incs) +
+//! **one virtual call** + whatever the extractors themselves do — negligible
+//! compared to network I/O.
 
+use std::any::TypeId;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 
+use crate::extract::{FromRequest, FromRequestParts};
 use crate::request::Request;
 use crate::response::{IntoResponse, Response};
 
@@ -63,71 +66,150 @@ pub type BoxedHandler = Arc<dyn ErasedHandler + Send + Sync + 'static>;
 /// Implemented for every valid route handler.
 ///
 /// You never implement this yourself. It is automatically satisfied for any
-/// `async fn` with the signature:
+/// `async fn` taking up to eight [extractors][crate::extract] and returning
+/// something that implements [`IntoResponse`]:
 ///
 /// ```text
 /// async fn name(req: Request) -> impl IntoResponse
+/// async fn name(Path(id): Path<u64>, State(db): State<Db>, body: String) -> impl IntoResponse
 /// ```
 ///
+/// Every argument but the last implements
+/// [`FromRequestParts`]; the last one implements [`FromRequest`] and may
+/// consume the request — the body, or the whole [`Request`]. An extractor
+/// that fails short-circuits the handler and its rejection becomes the
+/// response.
+///
+/// `T` is an inference marker for the argument list. It is never named in
+/// user code.
+///
 /// The trait is **sealed** (via the private `Sealed` supertrait): only the
-/// blanket impl below can satisfy it. This prevents accidental misuse and
+/// blanket impls below can satisfy it. This prevents accidental misuse and
 /// keeps the API surface stable across versions.
-pub trait Handler: private::Sealed + Send + Sync + 'static {
+pub trait Handler<T>: private::Sealed<T> + Send + Sync + 'static {
     #[doc(hidden)]
     fn into_boxed_handler(self) -> BoxedHandler;
+
+    /// Every state type the arguments need — see
+    /// [`Router::build`][crate::Router::build].
+    #[doc(hidden)]
+    fn required_state(&self) -> Vec<(TypeId, &'static str)> {
+        Vec::new()
+    }
 }
 
 /// The sealing module. Because `Sealed` is private, external crates cannot
 /// name it and therefore cannot implement `Handler` on their own types.
 mod private {
-    pub trait Sealed {}
+    pub trait Sealed<T> {}
 }
 
 // ── Blanket implementations ───────────────────────────────────────────────────
 
-/// Implement the sealing trait for any function with the right signature.
-///
-/// `Fn(Request) -> Fut` covers:
-///   - named `async fn` items
-///   - `async` closures (when they stabilise)
-///   - any struct that implements `Fn`
-impl<F, Fut, R> private::Sealed for F
+/// A handler that takes no arguments.
+impl<F, Fut, R> private::Sealed<()> for F
 where
-    F: Fn(Request) -> Fut + Send + Sync + 'static,
+    F: Fn() -> Fut + Send + Sync + 'static,
     Fut: Future<Output = R> + Send + 'static,
     R: IntoResponse + Send + 'static,
 {
 }
 
-/// Implement `Handler` for any function with the right signature.
-impl<F, Fut, R> Handler for F
+impl<F, Fut, R> Handler<()> for F
 where
-    F: Fn(Request) -> Fut + Send + Sync + 'static,
+    F: Fn() -> Fut + Send + Sync + 'static,
     Fut: Future<Output = R> + Send + 'static,
     R: IntoResponse + Send + 'static,
 {
     fn into_boxed_handler(self) -> BoxedHandler {
-        Arc::new(FnHandler(self))
+        Arc::new(FnHandler(move |_req: Request| -> BoxFuture {
+            let fut = self();
+            Box::pin(async move { fut.await.into_response() })
+        }))
     }
 }
 
+/// Implements `Handler` for functions of one or more extractors.
+///
+/// `$parts` are the leading [`FromRequestParts`] arguments, `$last` the final
+/// [`FromRequest`] one. `M` picks between a `FromRequest` type and a
+/// `FromRequestParts` type in last position — see
+/// [`FromRequest`]'s marker parameter.
+///
+/// `F` is wrapped in an `Arc` so the boxed future can own a handle to it:
+/// extraction awaits before the call, so the future outlives `&self`.
+macro_rules! impl_handler {
+    ($($parts:ident),*; $last:ident) => {
+        impl<F, Fut, R, M, $($parts,)* $last> private::Sealed<(M, $($parts,)* $last,)> for F
+        where
+            F: Fn($($parts,)* $last) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = R> + Send + 'static,
+            R: IntoResponse + Send + 'static,
+            $( $parts: FromRequestParts + 'static, )*
+            $last: FromRequest<M> + 'static,
+        {
+        }
+
+        impl<F, Fut, R, M, $($parts,)* $last> Handler<(M, $($parts,)* $last,)> for F
+        where
+            F: Fn($($parts,)* $last) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = R> + Send + 'static,
+            R: IntoResponse + Send + 'static,
+            $( $parts: FromRequestParts + 'static, )*
+            $last: FromRequest<M> + 'static,
+        {
+            #[allow(non_snake_case, unused_mut)]
+            fn into_boxed_handler(self) -> BoxedHandler {
+                let f = Arc::new(self);
+                Arc::new(FnHandler(move |mut req: Request| -> BoxFuture {
+                    let f = Arc::clone(&f);
+                    Box::pin(async move {
+                        $(
+                            let $parts = match $parts::from_request_parts(&mut req) {
+                                Ok(value)     => value,
+                                Err(rejected) => return rejected.into_response(),
+                            };
+                        )*
+                        let $last = match $last::from_request(req).await {
+                            Ok(value)     => value,
+                            Err(rejected) => return rejected.into_response(),
+                        };
+                        f($($parts,)* $last).await.into_response()
+                    })
+                }))
+            }
+
+            fn required_state(&self) -> Vec<(TypeId, &'static str)> {
+                [$(<$parts as FromRequestParts>::required_state(),)* <$last as FromRequest<M>>::required_state()]
+                    .into_iter()
+                    .flatten()
+                    .collect()
+            }
+        }
+    };
+}
+
+impl_handler!(; T1);
+impl_handler!(T1; T2);
+impl_handler!(T1, T2; T3);
+impl_handler!(T1, T2, T3; T4);
+impl_handler!(T1, T2, T3, T4; T5);
+impl_handler!(T1, T2, T3, T4, T5; T6);
+impl_handler!(T1, T2, T3, T4, T5, T6; T7);
+impl_handler!(T1, T2, T3, T4, T5, T6, T7; T8);
+
 // ── Concrete wrapper ──────────────────────────────────────────────────────────
 
-/// Newtype wrapper that holds a concrete handler `F` and implements
-/// [`ErasedHandler`], bridging the typed world to the trait-object world.
-struct FnHandler<F>(F);
+/// Newtype wrapper that holds the request-to-future closure built by
+/// `into_boxed_handler` and implements [`ErasedHandler`], bridging the typed
+/// world to the trait-object world.
+struct FnHandler<G>(G);
 
-impl<F, Fut, R> ErasedHandler for FnHandler<F>
+impl<G> ErasedHandler for FnHandler<G>
 where
-    F: Fn(Request) -> Fut + Send + Sync,
-    Fut: Future<Output = R> + Send + 'static,
-    R: IntoResponse + Send + 'static,
+    G: Fn(Request) -> BoxFuture + Send + Sync,
 {
     fn call(&self, req: Request) -> BoxFuture {
-        // Call the wrapped function — this returns the concrete `Fut`.
-        // We then map it to `Response` via `IntoResponse` and box the whole
-        // thing so the return type matches the trait signature.
-        let fut = (self.0)(req);
-        Box::pin(async move { fut.await.into_response() })
+        (self.0)(req)
     }
 }
@@ -0,0 +1,426 @@
+//! A request's cookies plus the changes to send back — signed or encrypted
+//! with a server secret.
+//!
+//! Register a [`CookieKey`] as state and take a [`CookieJar`] as a handler
+//! argument. Reads see the request's `cookie` header; writes are collected
+//! and sent as `set-cookie` headers with
+//! [`Response::builder`][crate::Response::builder]`.jar(…)`:
+//!
+//! ```rust,no_run
+//! use astor::{Cookie, CookieJar, CookieKey, Method, Response, Router};
+//!
+//! async fn visit(mut jar: CookieJar) -> Response {
+//!     let visits: u64 = jar.get_signed("visits").and_then(|v| v.parse().ok()).unwrap_or(0);
+//!     jar.add_signed(Cookie::new("visits", (visits + 1).to_string()).path("/").http_only());
+//!     Response::builder().jar(jar).text(format!("visit #{}", visits + 1))
+//! }
+//!
+//! # let secret = [0u8; 32];
+//! let app = Router::new()
+//!     .with_state(CookieKey::new(&secret))
+//!     .on(Method::Get, "/", visit, ());
+//! ```
+//!
+//! # Signed and private cookies
+//!
+//! A **signed** cookie carries its value in the clear plus an HMAC-SHA256
+//! tag: the client can read it but not change it. A **private** cookie is
+//! encrypted with ChaCha20-Poly1305: the client can neither read nor change
+//! it. Both bind the cookie name, so a value cannot be replayed under another
+//! name. A value that fails verification reads as absent.
+//!
+//! # Key rotation
+//!
+//! Add the old secret with [`CookieKey::previous`] when switching to a new
+//! one. Cookies made with the old secret keep verifying; every cookie the
+//! jar writes uses the new one. Drop the old secret once the longest-lived
+//! cookie made with it has expired.
+
+use std::any::TypeId;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+use crate::cookie::Cookie;
+use crate::crypto;
+use crate::extract::{self, FromRequestParts, Rejection};
+use crate::request::Request;
+
+// ── CookieKey ─────────────────────────────────────────────────────────────────
+
+/// The server secret behind signed and private cookies, plus any previous
+/// secrets still accepted.
+///
+/// Load the secret from configuration, not source code: anyone who has it
+/// can forge every cookie. `Debug` never prints key material.
+#[derive(Clone)]
+pub struct CookieKey {
+    current: Keys,
+    previous: Vec<Keys>,
+}
+
+impl CookieKey {
+    /// A key from `secret` — at least 32 bytes of random data.
+    ///
+    /// Separate signing and encryption keys are derived from it, so one
+    /// secret serves both.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `secret` is shorter than 32 bytes.
+    pub fn new(secret: &[u8]) -> Self {
+        Self { current: Keys::derive(secret), previous: Vec::new() }
+    }
+
+    /// A key from 32 fresh random bytes.
+    ///
+    /// Cookies do not survive a restart or reach other instances — use
+    /// [`new`][CookieKey::new] with a configured secret in production.
+    ///
+    /// Fails if the operating system's random number generator cannot be
+    /// read.
+    pub fn generate() -> io::Result<Self> {
+        let mut secret = [0u8; 32];
+        crypto::random_bytes(&mut secret)?;
+        Ok(Self::new(&secret))
+    }
+
+    /// Also accepts cookies made with `secret`. Call once per retired
+    /// secret; they are tried in the order added, after the current one.
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use astor::{Cookie, CookieJar, CookieKey};
+    ///
+    /// # let (new_secret, old_secret) = ([1u8; 32], [2u8; 32]);
+    /// let mut old = CookieJar::empty(Arc::new(CookieKey::new(&old_secret)));
+    /// old.add_signed(Cookie::new("user", "42"));
+    /// let issued = old.get("user").unwrap().to_owned();
+    ///
+    /// let key = Arc::new(CookieKey::new(&new_secret).previous(&old_secret));
+    /// let mut jar = CookieJar::empty(key);
+    /// jar.add(Cookie::new("user", issued));
+    /// assert_eq!(jar.get_signed("user").as_deref(), Some("42"));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `secret` is shorter than 32 bytes.
+    pub fn previous(mut self, secret: &[u8]) -> Self {
+        self.previous.push(Keys::derive(secret));
+        self
+    }
+
+    /// The current keys first, then every previous one.
+    fn all(&self) -> impl Iterator<Item = &Keys> {
+        std::iter::once(&self.current).chain(&self.previous)
+    }
+}
+
+impl fmt::Debug for CookieKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CookieKey").finish_non_exhaustive()
+    }
+}
+
+/// The keys derived from one secret.
+#[derive(Clone)]
+struct Keys {
+    encryption: [u8; 32],
+    signing: [u8; 32],
+}
+
+impl Keys {
+    fn derive(secret: &[u8]) -> Self {
+        assert!(secret.len() >= 32, "cookie secret must be at least 32 bytes, got {}", secret.len());
+        Self {
+            encryption: crypto::hmac_sha256(secret, &[b"astor cookie encryption"]),
+            signing: crypto::hmac_sha256(secret, &[b"astor cookie signing"]),
+        }
+    }
+
+    /// The HMAC tag over `name=value`.
+    fn sign(&self, name: &str, value: &str) -> [u8; 32] {
+        crypto::hmac_sha256(&self.signing, &[name.as_bytes(), b"=", value.as_bytes()])
+    }
+
+    fn verify(&self, name: &str, signed: &str) -> Option<String> {
+        let (value, tag) = signed.rsplit_once('.')?;
+        let tag = crypto::base64url_decode(tag)?;
+        crypto::constant_time_eq(&self.sign(name, value), &tag).then(|| value.to_owned())
+    }
+
+    fn encrypt(&self, name: &str, value: &str) -> io::Result<String> {
+        let mut nonce = [0u8; crypto::NONCE_LEN];
+        crypto::random_bytes(&mut nonce)?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(crypto::seal(&self.encryption, &nonce, name.as_bytes(), value.as_bytes()));
+        Ok(crypto::base64url_encode(&sealed))
+    }
+
+    fn decrypt(&self, name: &str, private: &str) -> Option<String> {
+        let sealed = crypto::base64url_decode(private)?;
+        let nonce = sealed.first_chunk::<{ crypto::NONCE_LEN }>()?;
+        let plain = crypto::open(&self.encryption, nonce, name.as_bytes(), &sealed[crypto::NONCE_LEN..])?;
+        String::from_utf8(plain).ok()
+    }
+}
+
+// ── CookieJar ─────────────────────────────────────────────────────────────────
+
+/// The request's cookies, and the `set-cookie` changes to answer with.
+///
+/// Reads see changes made earlier through the same jar: a cookie added is
+/// read back, a cookie removed reads as absent.
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use astor::{Cookie, CookieJar, CookieKey};
+///
+/// let key = Arc::new(CookieKey::new(&[7u8; 32]));
+/// let mut jar = CookieJar::empty(Arc::clone(&key));
+/// jar.add_signed(Cookie::new("user", "42"));
+/// jar.add_private(Cookie::new("token", "s3cret")).unwrap();
+///
+/// assert_eq!(jar.get_signed("user").as_deref(), Some("42"));
+/// assert_eq!(jar.get_private("token").as_deref(), Some("s3cret"));
+///
+/// // The raw values are not what was stored, and do not verify under
+/// // another name or after tampering.
+/// let signed = jar.get("user").unwrap().to_owned();
+/// assert!(signed.starts_with("42."));
+/// assert!(!jar.get("token").unwrap().contains("s3cret"));
+///
+/// jar.add(Cookie::new("other", signed.as_str()));
+/// assert_eq!(jar.get_signed("other"), None);
+///
+/// jar.add(Cookie::new("user", signed.replacen("42", "43", 1)));
+/// assert_eq!(jar.get_signed("user"), None);
+/// ```
+#[derive(Debug)]
+pub struct CookieJar {
+    /// Cookies added or removed through the jar, in order.
+    delta: Vec<Cookie>,
+    key: Arc<CookieKey>,
+    /// The request's cookies, as sent.
+    original: Vec<(String, String)>,
+}
+
+impl CookieJar {
+    /// The cookies sent with `req`. Handlers usually take a `CookieJar`
+    /// argument instead, which reads the [`CookieKey`] from router state.
+    pub fn new(req: &Request, key: Arc<CookieKey>) -> Self {
+        let original = req.cookies().map(|(k, v)| (k.to_owned(), v.to_owned())).collect();
+        Self { delta: Vec::new(), key, original }
+    }
+
+    /// A jar with no cookies — for building `set-cookie` headers outside a
+    /// request.
+    pub fn empty(key: Arc<CookieKey>) -> Self {
+        Self { delta: Vec::new(), key, original: Vec::new() }
+    }
+
+    /// A cookie's raw value — as sent, with no verification.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        match self.delta.iter().rev().find(|c| c.name() == name) {
+            Some(c) if c.is_removal() => None,
+            Some(c)                   => Some(c.value()),
+            None                      => self.original.iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.as_str()),
+        }
+    }
+
+    /// A signed cookie's value. `None` if the cookie is absent or its
+    /// signature does not verify under any key.
+    pub fn get_signed(&self, name: &str) -> Option<String> {
+        let raw = self.get(name)?;
+        self.key.all().find_map(|keys| keys.verify(name, raw))
+    }
+
+    /// A private cookie's value. `None` if the cookie is absent or does not
+    /// decrypt under any key.
+    pub fn get_private(&self, name: &str) -> Option<String> {
+        let raw = self.get(name)?;
+        self.key.all().find_map(|keys| keys.decrypt(name, raw))
+    }
+
+    /// Sets a cookie as-is.
+    pub fn add(&mut self, cookie: Cookie) {
+        self.delta.push(cookie);
+    }
+
+    /// Sets a cookie the client can read but not change. The value gains a
+    /// `.` and a 43-character tag.
+    pub fn add_signed(&mut self, cookie: Cookie) {
+        let tag = self.key.current.sign(cookie.name(), cookie.value());
+        let value = format!("{}.{}", cookie.value(), crypto::base64url_encode(&tag));
+        self.delta.push(cookie.with_value(value));
+    }
+
+    /// Sets a cookie the client can neither read nor change. The value
+    /// becomes base64url ciphertext of about `4/3 × len + 38` characters.
+    ///
+    /// Fails, adding nothing, if no random nonce can be read from the
+    /// operating system.
+    pub fn add_private(&mut self, cookie: Cookie) -> io::Result<()> {
+        let value = self.key.current.encrypt(cookie.name(), cookie.value())?;
+        self.delta.push(cookie.with_value(value));
+        Ok(())
+    }
+
+    /// Deletes a cookie from the browser. Pass it with the `path` and
+    /// `domain` it was set with; its value is ignored.
+    pub fn remove(&mut self, cookie: Cookie) {
+        self.delta.push(cookie.into_removal());
+    }
+
+    /// The cookies added or removed through the jar, in order — what
+    /// `.jar(…)` on [`Response::builder`][crate::Response::builder] sends.
+    pub fn delta(&self) -> impl Iterator<Item = &Cookie> {
+        self.delta.iter()
+    }
+}
+
+/// Reads the [`CookieKey`] registered with
+/// [`Router::with_state`][crate::Router::with_state]. `500 Internal Server
+/// Error` if there is none — [`Router::build`][crate::Router::build] reports
+/// that at startup.
+impl FromRequestParts for CookieJar {
+    type Rejection = Rejection;
+
+    fn from_request_parts(req: &mut Request) -> Result<Self, Rejection> {
+        let key = req.state_arc::<CookieKey>().ok_or_else(extract::missing)?;
+        Ok(Self::new(req, key))
+    }
+
+    fn required_state() -> Option<(TypeId, &'static str)> {
+        Some((TypeId::of::<CookieKey>(), "astor::CookieKey"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::method::Method;
+    use crate::router::Router;
+
+    fn jar() -> CookieJar {
+        CookieJar::empty(Arc::new(CookieKey::new(&[7; 32])))
+    }
+
+    fn signed(value: &str) -> String {
+        let mut jar = jar();
+        jar.add_signed(Cookie::new("user", value));
+        jar.get("user").unwrap().to_owned()
+    }
+
+    #[test]
+    fn signed_cookies_round_trip_and_reject_tampering() {
+        let raw = signed("42");
+        let (value, tag) = raw.split_once('.').unwrap();
+        assert_eq!(value, "42");
+        assert_eq!(tag.len(), 43);
+
+        let mut jar = jar();
+        jar.add(Cookie::new("user", raw.as_str()));
+        assert_eq!(jar.get_signed("user").as_deref(), Some("42"));
+
+        jar.add(Cookie::new("user", format!("43.{tag}")));
+        assert_eq!(jar.get_signed("user"), None);
+        jar.add(Cookie::new("other", raw.as_str()));
+        assert_eq!(jar.get_signed("other"), None);
+        jar.add(Cookie::new("user", "42"));
+        assert_eq!(jar.get_signed("user"), None);
+    }
+
+    #[test]
+    fn signed_tags_have_one_spelling() {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+        // The tag's last character carries two unused bits. Setting one
+        // must not give a second valid spelling of the same tag.
+        let raw = signed("42");
+        let (rest, last) = raw.split_at(raw.len() - 1);
+        let i = ALPHABET.iter().position(|&c| c == last.as_bytes()[0]).unwrap();
+        let mut jar = jar();
+        jar.add(Cookie::new("user", format!("{rest}{}", ALPHABET[i ^ 1] as char)));
+        assert_eq!(jar.get_signed("user"), None);
+    }
+
+    #[test]
+    fn private_cookies_round_trip_and_hide_the_value() {
+        let mut jar = jar();
+        jar.add_private(Cookie::new("token", "s3cret")).unwrap();
+        let raw = jar.get("token").unwrap().to_owned();
+        assert!(!raw.contains("s3cret"));
+        assert_eq!(jar.get_private("token").as_deref(), Some("s3cret"));
+
+        // A fresh nonce every time.
+        jar.add_private(Cookie::new("token", "s3cret")).unwrap();
+        assert_ne!(jar.get("token").unwrap(), raw);
+
+        jar.add(Cookie::new("renamed", raw.as_str()));
+        assert_eq!(jar.get_private("renamed"), None);
+        let mut flipped = raw.into_bytes();
+        flipped[20] = if flipped[20] == b'A' { b'B' } else { b'A' };
+        jar.add(Cookie::new("token", String::from_utf8(flipped).unwrap()));
+        assert_eq!(jar.get_private("token"), None);
+    }
+
+    #[test]
+    fn previous_keys_still_verify() {
+        let old = CookieKey::new(&[1; 32]);
+        let mut issued = CookieJar::empty(Arc::new(old));
+        issued.add_signed(Cookie::new("a", "1"));
+        issued.add_private(Cookie::new("b", "2")).unwrap();
+
+        let rotated = Arc::new(CookieKey::new(&[2; 32]).previous(&[1; 32]));
+        let mut jar = CookieJar::empty(Arc::clone(&rotated));
+        for cookie in issued.delta() {
+            jar.add(cookie.clone());
+        }
+        assert_eq!(jar.get_signed("a").as_deref(), Some("1"));
+        assert_eq!(jar.get_private("b").as_deref(), Some("2"));
+
+        let mut fresh = CookieJar::empty(Arc::new(CookieKey::new(&[2; 32])));
+        for cookie in issued.delta() {
+            fresh.add(cookie.clone());
+        }
+        assert_eq!(fresh.get_signed("a"), None);
+        assert_eq!(fresh.get_private("b"), None);
+    }
+
+    #[test]
+    fn removed_cookies_read_as_absent() {
+        let mut jar = jar();
+        jar.add(Cookie::new("a", "1"));
+        jar.remove(Cookie::new("a", ""));
+        assert_eq!(jar.get("a"), None);
+        assert_eq!(jar.delta().count(), 2);
+    }
+
+    #[test]
+    #[should_panic = "at least 32 bytes"]
+    fn short_secrets_panic() {
+        CookieKey::new(&[0; 31]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn generated_keys_differ() {
+        let mut jar = CookieJar::empty(Arc::new(CookieKey::generate().unwrap()));
+        jar.add_signed(Cookie::new("a", "1"));
+        assert_ne!(jar.get("a"), Some(signed("1").as_str()));
+    }
+
+    #[tokio::test]
+    async fn a_missing_key_answers_500_without_naming_it() {
+        async fn read(jar: CookieJar) -> String {
+            jar.get("user").unwrap_or_default().to_owned()
+        }
+        let reply = crate::testing::get(Router::new().on(Method::Get, "/", read, ()), "/").await;
+        assert_eq!((reply.status, reply.text()), (500, ""));
+    }
+}
@@ -0,0 +1,144 @@
+//! JSON bodies, both ways — requires the `json` feature.
+//!
+//! [`Request::json`][crate::Request::json] reads a JSON body;
+//! [`Json<T>`] is the typed wrapper that works as a handler argument and as
+//! a return type:
+//!
+//! ```rust,no_run
+//! use astor::{Json, Method, Router};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize)]
+//! struct NewUser { name: String }
+//!
+//! #[derive(Serialize)]
+//! struct User { id: u64, name: String }
+//!
+//! // 415 without `content-type: application/json`, 400 on malformed JSON,
+//! // 422 when the JSON does not fit `NewUser` — before the handler runs.
+//! async fn create_user(Json(new): Json<NewUser>) -> Json<User> {
+//!     Json(User { id: 1, name: new.name })
+//! }
+//!
+//! let app = Router::new().on(Method::Post, "/users", create_user, ());
+//! ```
+
+use std::future::{self, Future};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::error::JsonError;
+use crate::extract::FromRequest;
+use crate::request::Request;
+use crate::response::{IntoResponse, Response};
+use crate::status::Status;
+
+/// A JSON body — deserialized as a handler's last argument, serialized as a
+/// return value.
+///
+/// As an extractor, rejects with [`JsonError`]. As a response, answers
+/// `200 OK` with `content-type: application/json`; a value serde cannot
+/// serialize (a map with non-string keys, a failing `Serialize` impl) answers
+/// `500 Internal Server Error`.
+#[derive(Debug)]
+pub struct Json<T>(pub T);
+
+impl<T: DeserializeOwned + Send> FromRequest for Json<T> {
+    type Rejection = JsonError;
+
+    fn from_request(req: Request) -> impl Future<Output = Result<Self, JsonError>> + Send {
+        future::ready(req.json().map(Json))
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        match serde_json::to_vec(&self.0) {
+            Ok(bytes) => Response::json(bytes),
+            Err(_)    => Response::status(Status::InternalServerError),
+        }
+    }
+}
+
+/// `true` for `application/json` and `application/*+json`, parameters and
+/// case ignored.
+pub(crate) fn is_json(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim();
+    let Some((kind, subtype)) = essence.split_once('/') else { return false };
+    kind.eq_ignore_ascii_case("application")
+        && (subtype.eq_ignore_ascii_case("json")
+            || subtype.len() > 5 && subtype.as_bytes()[subtype.len() - 5..].eq_ignore_ascii_case(b"+json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::method::Method;
+    use crate::router::Router;
+    use crate::testing::{Reply, get, send};
+
+    #[derive(Deserialize, Serialize)]
+    struct User {
+        id: u64,
+        name: String,
+    }
+
+    async fn echo(Json(user): Json<User>) -> Json<User> {
+        Json(User { id: user.id + 1, name: user.name })
+    }
+
+    async fn post(content_type: &str, body: &str) -> Reply {
+        let router = Router::new().on(Method::Post, "/users", echo, ());
+        let raw = format!(
+            "POST /users HTTP/1.1\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\n\r\n{body}",
+            body.len(),
+        );
+        send(router, raw).await
+    }
+
+    #[tokio::test]
+    async fn round_trips_a_body() {
+        let reply = post("application/json; charset=utf-8", r#"{"id":1,"name":"ann"}"#).await;
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.header("content-type"), Some("application/json"));
+        assert_eq!(reply.text(), r#"{"id":2,"name":"ann"}"#);
+    }
+
+    #[tokio::test]
+    async fn rejects_with_415_400_and_422() {
+        let reply = post("text/plain", r#"{"id":1,"name":"ann"}"#).await;
+        assert_eq!((reply.status, reply.text()), (415, "expected content-type application/json"));
+
+        let reply = post("application/json", r#"{"id":1,"#).await;
+        assert_eq!(reply.status, 400);
+        assert!(reply.text().starts_with("malformed json body: "), "{}", reply.text());
+
+        let reply = post("application/json", r#"{"id":"one","name":"ann"}"#).await;
+        assert_eq!(reply.status, 422);
+        assert!(reply.text().starts_with("invalid json body: "), "{}", reply.text());
+    }
+
+    #[tokio::test]
+    async fn unserializable_values_answer_500() {
+        async fn bad() -> Json<HashMap<(u8, u8), u8>> {
+            Json(HashMap::from([((1, 2), 3)]))
+        }
+        let router = Router::new().on(Method::Get, "/", bad, ());
+        assert_eq!(get(router, "/").await.status, 500);
+    }
+
+    #[test]
+    fn recognizes_json_content_types() {
+        assert!(is_json("application/json"));
+        assert!(is_json("Application/JSON ; charset=utf-8"));
+        assert!(is_json("application/problem+json"));
+        assert!(!is_json("application/+json"));
+        assert!(!is_json("application/jsonp"));
+        assert!(!is_json("text/json"));
+    }
+}
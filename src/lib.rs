@@ -98,12 +98,12 @@
 //!
 //! | Required nginx setting | What breaks without it |
 //! |---|---|
-//! | `proxy_buffering on` | astor only reads `Content-Length`-framed bodies — chunked bodies are silently dropped |
+//! | `proxy_buffering on` | astor only reads `Content-Length`-framed bodies — chunked bodies are silently dropped (unless the `chunked` feature is on) |
 //! | `proxy_http_version 1.1` + `proxy_set_header Connection ""` | keep-alive pool collapses to one request per TCP connection |
 //! | `client_max_body_size` | clients can stream unlimited body bytes |
 //! | `client_header_buffer_size` / `large_client_header_buffers` | oversized headers are not rejected before reaching astor |
 //! | `client_body_timeout` / `client_header_timeout` | slow clients are not dropped; astor has no timeout logic |
-//! | method whitelist | nginx forwards any method string — `ANYTHING /path HTTP/1.1` reaches your handlers |
+//! | method whitelist | nginx forwards any method string — unknown methods reach astor and get `501 Not Implemented` instead of nginx's `405` |
 //!
 //! Minimal example (two required lines shown — not a full config):
 //!
@@ -121,36 +121,90 @@
 //! Full config, Kubernetes ingress, and all required settings:
 //! **[`docs/nginx.md`](https://github.com/benjaminPla/astor/blob/master/docs/nginx.md)**
 //!
+//! ## Cargo features
+//!
+//! Everything is off by default. Opt in to what your deployment needs.
+//!
+//! | Feature | What it adds |
+//! |---|---|
+//! | `chunked` | Decodes `Transfer-Encoding: chunked` request bodies — for `proxy_request_buffering off`. Cap with `Server::max_chunked_body`. |
+//! | `json` | JSON bodies — `Request::json::<T>()` and the `Json<T>` extractor and response type. Implies `serde`. |
+//! | `serde` | Typed query strings and form bodies — `Request::query_as::<T>()`, `Request::form_as::<T>()` and the `extract::QueryAs<T>` / `extract::Form<T>` extractors. |
+//!
 //! ## Key types
 //!
 //! | Type | Purpose |
 //! |---|---|
 //! | [`Router`] | Register routes — `Router::new().on(method, path, handler, extra_mw)` |
-//! | [`Server`] | Bind a port and serve — `Server::bind(addr).serve(router)` |
+//! | [`Server`] | Bind a port or Unix socket and serve — `Server::bind(addr).serve(router)` |
 //! | [`Request`] | Incoming request — method, path, headers, body, params |
+//! | [`extract`] | Typed handler arguments — `Path<T>`, `Query`, `State<S>`, … |
+//! | [`BodyStream`] | Request body as an `AsyncRead`, unbuffered on `Router::on_stream` routes |
+//! | [`multipart`] | `multipart/form-data` uploads, part by part |
+//! | [`session`] | Server-side sessions — `Sessions` middleware, pluggable `SessionStore` |
 //! | [`Response`] | Outgoing response — shortcuts + typed builder |
+//! | [`Upgraded`] | A connection taken over after `101` — `Response::builder().upgrade(…)`; `into_parts()` gives back the TCP or Unix [`Transport`] |
+//! | [`ws`] | WebSockets — `WebSocketUpgrade` handshake, framed `WebSocket` messages |
+//! | [`Sse`] | Server-sent events — a stream of [`Event`]s with keep-alive |
 //! | [`Status`] | Every IANA status code as a named variant |
+//! | [`Cookie`] | `set-cookie` builder — append with `Response::builder().cookie(…)` |
+//! | [`CookieJar`] | Signed and encrypted cookies, keyed by a rotating [`CookieKey`] |
 //! | [`Method`] | Every HTTP method — RFC 9110 + WebDAV + PURGE |
 //! | [`ContentType`] | Common content-type values for [`Response::builder`] |
 //! | [`IntoResponse`] | Implement on your own types to return them from handlers |
 
+mod body;
+mod cookie;
+mod crypto;
+#[cfg(feature = "serde")]
+mod de;
 mod error;
+mod extensions;
 mod handler;
+mod jar;
+#[cfg(feature = "json")]
+mod json;
 mod method;
 mod request;
 mod response;
 mod router;
 mod server;
+mod sse;
+mod state;
 mod status;
+#[cfg(test)]
+mod testing;
+mod upgrade;
+mod urlencoded;
 
+pub mod extract;
 pub mod middleware;
+pub mod multipart;
+pub mod session;
+pub mod ws;
 
-pub use error::Error;
+pub use body::BodyStream;
+pub use cookie::{Cookie, SameSite};
+pub use error::{
+    CookieError, Error, FormError, MultipartError, ParseError, RouteError, UpgradeError,
+    WebSocketError,
+};
+#[cfg(feature = "json")]
+pub use error::JsonError;
+#[cfg(feature = "serde")]
+pub use error::QueryError;
+pub use extensions::Extensions;
+pub use extract::{FromRequest, FromRequestParts};
 pub use handler::Handler;
+pub use jar::{CookieJar, CookieKey};
+#[cfg(feature = "json")]
+pub use json::Json;
 pub use method::Method;
 pub use middleware::{Middleware, Next};
 pub use request::Request;
 pub use response::{ContentType, IntoResponse, Response};
 pub use router::Router;
 pub use server::Server;
+pub use sse::{Event, Sse};
 pub use status::Status;
+pub use upgrade::{Parts, Transport, Upgraded};
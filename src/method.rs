@@ -13,10 +13,10 @@
 //! # use astor::{Method, Request, Response, Router};
 //! # async fn handler(_: Request) -> Response { Response::text("") }
 //! Router::new()
-//!     .on(Method::Delete, "/users/{id}", handler)
-//!     .on(Method::Get,    "/users/{id}", handler)
-//!     .on(Method::Patch,  "/users/{id}", handler)
-//!     .on(Method::Post,   "/users",      handler);
+//!     .on(Method::Delete, "/users/{id}", handler, ())
+//!     .on(Method::Get,    "/users/{id}", handler, ())
+//!     .on(Method::Patch,  "/users/{id}", handler, ())
+//!     .on(Method::Post,   "/users",      handler, ());
 //! ```
 //!
 //! # Inspecting the method in a handler
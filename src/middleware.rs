@@ -8,13 +8,16 @@
 //! [`Router::middleware`][crate::Router::middleware]; per-route middleware is
 //! the fourth argument to [`Router::on`][crate::Router::on].
 //!
-//! # No built-in middleware
+//! # Almost no built-in middleware
 //!
-//! astor ships no middleware of its own — by design. Everything that typically
-//! lives in a middleware layer (CORS headers, rate limiting, request-ID
-//! injection, timeouts) is already handled by nginx before a request reaches
-//! astor. Duplicating that work here contradicts the whole point of the
-//! framework.
+//! astor ships almost no middleware of its own — by design. Everything that
+//! typically lives in a middleware layer (CORS headers, rate limiting,
+//! request-ID injection, timeouts) is already handled by nginx before a
+//! request reaches astor. Duplicating that work here contradicts the whole
+//! point of the framework.
+//!
+//! The exception is [`Sessions`][crate::session::Sessions]: session state
+//! belongs to the application, and nginx cannot hold it.
 //!
 //! Write your own for anything genuinely application-specific: ownership
 //! checks, feature flags, audit logging. Everything else: configure nginx.
@@ -116,8 +119,9 @@ mod private {
 /// async fn name(req: Request, next: Next) -> Response
 /// ```
 ///
-/// The trait is **sealed** — only the blanket impl can satisfy it. This keeps
-/// the API stable and prevents accidental misuse.
+/// The trait is **sealed** — only the blanket impl and
+/// [`Sessions`][crate::session::Sessions] can satisfy it. This keeps the API
+/// stable and prevents accidental misuse.
 pub trait Middleware: private::Sealed + Send + Sync + 'static {
     #[doc(hidden)]
     fn into_boxed_middleware(self) -> BoxedMiddleware;
@@ -130,6 +134,10 @@ where
 {
 }
 
+/// [`Sessions`][crate::session::Sessions] is a middleware value rather than a
+/// function; its `Middleware` impl lives in `session.rs`.
+impl<S: crate::session::SessionStore> private::Sealed for crate::session::Sessions<S> {}
+
 impl<F, Fut> Middleware for F
 where
     F: Fn(Request, Next) -> Fut + Send + Sync + 'static,
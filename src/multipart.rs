@@ -0,0 +1,537 @@
+//! `multipart/form-data` — file uploads and mixed forms (RFC 7578).
+//!
+//! Take [`Multipart`] as a handler's last argument, or call
+//! [`Request::into_multipart`][crate::Request::into_multipart], then walk the
+//! parts in order:
+//!
+//! ```rust,no_run
+//! use astor::multipart::Multipart;
+//! use astor::{MultipartError, Status};
+//!
+//! async fn upload(multipart: Multipart) -> Result<Status, MultipartError> {
+//!     let mut multipart = multipart.max_part_size(5 * 1024 * 1024);
+//!     while let Some(part) = multipart.next_part().await? {
+//!         match part.file_name() {
+//!             Some(file) => println!("{}: file {file}, {} bytes", part.name(), part.bytes().len()),
+//!             None       => println!("{}: {}", part.name(), String::from_utf8_lossy(part.bytes())),
+//!         }
+//!     }
+//!     Ok(Status::NoContent)
+//! }
+//! ```
+//!
+//! # Limits
+//!
+//! nginx's `client_max_body_size` caps the whole request before it reaches
+//! astor. [`max_part_size`][Multipart::max_part_size] and
+//! [`max_total_size`][Multipart::max_total_size] add per-route limits on top:
+//! a part past either answers `413 Content Too Large` through
+//! [`MultipartError`]. Both are unlimited by default.
+//!
+//! # Streamed uploads
+//!
+//! On routes registered with [`Router::on_stream`][crate::Router::on_stream]
+//! the parser reads the body off the connection as
+//! [`next_part`][Multipart::next_part] asks for it, and checks both limits
+//! while it reads — an oversized part fails as soon as it crosses the limit,
+//! not once it has arrived. Each part is still returned whole, so set
+//! `max_part_size` on those routes to bound what one part can hold. On every
+//! other route the body is already in memory and the parser walks it in
+//! place.
+
+use std::fmt;
+use std::future::{self, Future};
+
+use tokio::io::AsyncReadExt;
+
+use crate::body::BodyStream;
+use crate::error::MultipartError;
+use crate::extract::FromRequest;
+use crate::request::Request;
+
+/// Bytes asked of a streamed body per read.
+const READ_SIZE: usize = 16 * 1024;
+
+/// The most a preamble, or one part's header block, may take.
+const MAX_HEAD: usize = 16 * 1024;
+
+/// A `multipart/form-data` body, read one [`Part`] at a time.
+///
+/// Framing is checked lazily: a body broken halfway through yields its
+/// leading parts before [`next_part`][Multipart::next_part] reports the
+/// error.
+pub struct Multipart {
+    /// The rest of a streamed body; empty for a buffered one.
+    body: BodyStream,
+    /// Read from `body` — or the whole buffered body — from `pos` on unparsed.
+    buf: Vec<u8>,
+    /// `\r\n--` + boundary — what separates one part from the next.
+    delimiter: Vec<u8>,
+    done: bool,
+    /// `body` has nothing more to give.
+    eof: bool,
+    max_part_size: usize,
+    max_total_size: usize,
+    pos: usize,
+    /// Past the first boundary.
+    started: bool,
+    total: usize,
+}
+
+impl Multipart {
+    /// Checks `content-type` and takes the body out of the request.
+    pub(crate) fn new(mut req: Request) -> Result<Self, MultipartError> {
+        let boundary = req.header("content-type")
+            .ok_or(MultipartError::InvalidContentType)
+            .and_then(boundary)?;
+        let mut delimiter = b"\r\n--".to_vec();
+        delimiter.extend_from_slice(boundary.as_bytes());
+        let (buf, body) = match req.stream.take() {
+            Some(stream) => (Vec::new(), stream),
+            None         => (std::mem::take(&mut req.body), BodyStream::buffered(Vec::new())),
+        };
+        Ok(Self {
+            body,
+            buf,
+            delimiter,
+            done: false,
+            eof: false,
+            max_part_size: usize::MAX,
+            max_total_size: usize::MAX,
+            pos: 0,
+            started: false,
+            total: 0,
+        })
+    }
+
+    /// Caps the size of any one part's content, in bytes.
+    pub fn max_part_size(mut self, bytes: usize) -> Self {
+        self.max_part_size = bytes;
+        self
+    }
+
+    /// Caps the combined size of every part's content, in bytes.
+    pub fn max_total_size(mut self, bytes: usize) -> Self {
+        self.max_total_size = bytes;
+        self
+    }
+
+    /// The next part, or `None` after the last one.
+    ///
+    /// # Errors
+    ///
+    /// [`MultipartError`] if the body is not well-formed multipart or a part
+    /// breaks a limit. A streamed body whose connection fails reads as cut
+    /// short: [`MultipartError::UnexpectedEnd`]. Every later call returns
+    /// `Ok(None)`.
+    pub async fn next_part(&mut self) -> Result<Option<Part>, MultipartError> {
+        if self.done {
+            return Ok(None);
+        }
+        let part = self.parse_part().await;
+        if !matches!(part, Ok(Some(_))) {
+            self.done = true;
+        }
+        part
+    }
+
+    async fn parse_part(&mut self) -> Result<Option<Part>, MultipartError> {
+        let delimiter = self.delimiter.clone();
+
+        // The first boundary may open the body, or follow a preamble.
+        if !self.started {
+            let dash_boundary = &delimiter[2..];
+            self.fill_to(dash_boundary.len()).await;
+            self.pos = if self.buf.starts_with(dash_boundary) {
+                dash_boundary.len()
+            } else {
+                self.find(&delimiter, MAX_HEAD, MultipartError::InvalidPart).await? + delimiter.len()
+            };
+            self.started = true;
+        }
+
+        // `--` after a boundary closes the body; the epilogue is ignored.
+        self.fill_to(2).await;
+        if self.buf[self.pos..].starts_with(b"--") {
+            return Ok(None);
+        }
+        // Transport padding, then the line break ending the boundary line.
+        let eol = self.find(b"\r\n", MAX_HEAD, MultipartError::UnexpectedEnd).await?;
+        if !self.buf[self.pos..eol].iter().all(|b| matches!(b, b' ' | b'\t')) {
+            return Err(MultipartError::UnexpectedEnd);
+        }
+        self.pos = eol + 2;
+
+        // Header lines up to an empty one — which may come first.
+        self.fill_to(2).await;
+        let head_end = if self.buf[self.pos..].starts_with(b"\r\n") {
+            self.pos
+        } else {
+            self.find(b"\r\n\r\n", MAX_HEAD, MultipartError::InvalidPart).await? + 2
+        };
+        let mut headers = Vec::new();
+        let mut lines = &self.buf[self.pos..head_end];
+        while let Some(eol) = find(lines, b"\r\n", 0) {
+            let line = &lines[..eol];
+            lines = &lines[eol + 2..];
+            let line = std::str::from_utf8(line).map_err(|_| MultipartError::InvalidPart)?;
+            let (name, value) = line.split_once(':').ok_or(MultipartError::InvalidPart)?;
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+        }
+        self.pos = head_end + 2;
+
+        let disposition = headers.iter()
+            .find(|(k, _)| k == "content-disposition")
+            .map(|(_, v)| v.as_str())
+            .ok_or(MultipartError::InvalidPart)?;
+        let (kind, params) = disposition.split_once(';').unwrap_or((disposition, ""));
+        if !kind.trim().eq_ignore_ascii_case("form-data") {
+            return Err(MultipartError::InvalidPart);
+        }
+        let params = parse_params(params);
+        let name = param(&params, "name").ok_or(MultipartError::InvalidPart)?;
+        let file_name = param(&params, "filename");
+
+        // The tighter of the two limits stops the read.
+        let room = self.max_total_size.saturating_sub(self.total);
+        let (limit, too_large) = if self.max_part_size <= room {
+            (self.max_part_size, MultipartError::PartTooLarge)
+        } else {
+            (room, MultipartError::TooLarge)
+        };
+        let end = self.find(&delimiter, limit, too_large).await?;
+        let size = end - self.pos;
+        if size > limit {
+            return Err(too_large);
+        }
+        self.total += size;
+
+        let part = Part { body: self.buf[self.pos..end].to_vec(), file_name, headers, name };
+        self.pos = end + delimiter.len();
+        Ok(Some(part))
+    }
+
+    /// Where `needle` next starts in `buf`, at or after `pos`, reading more
+    /// of the body until it shows up. Fails with `too_large` once more than
+    /// `limit` bytes past `pos` are known to come before it.
+    async fn find(
+        &mut self,
+        needle: &[u8],
+        limit: usize,
+        too_large: MultipartError,
+    ) -> Result<usize, MultipartError> {
+        // Bytes past `pos` already searched — `fill` keeps them past `pos`.
+        let mut searched = 0;
+        loop {
+            if let Some(at) = find(&self.buf, needle, self.pos + searched) {
+                return Ok(at);
+            }
+            searched = (self.buf.len() - self.pos).saturating_sub(needle.len() - 1);
+            if searched > limit {
+                return Err(too_large);
+            }
+            if !self.fill().await {
+                return Err(MultipartError::UnexpectedEnd);
+            }
+        }
+    }
+
+    /// Reads until `buf` holds `len` bytes past `pos`, or the body ends.
+    async fn fill_to(&mut self, len: usize) {
+        while self.buf.len() - self.pos < len && self.fill().await {}
+    }
+
+    /// Drops the parsed bytes, then appends the next read of the body to
+    /// `buf`. `false` once the body has ended — or failed, which reads the
+    /// same: a body cut short.
+    async fn fill(&mut self) -> bool {
+        if self.eof {
+            return false;
+        }
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+        let len = self.buf.len();
+        self.buf.resize(len + READ_SIZE, 0);
+        let n = self.body.read(&mut self.buf[len..]).await.unwrap_or(0);
+        self.buf.truncate(len + n);
+        self.eof = n == 0;
+        !self.eof
+    }
+}
+
+impl fmt::Debug for Multipart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Multipart")
+            .field("max_part_size", &self.max_part_size)
+            .field("max_total_size", &self.max_total_size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Takes the body — streamed on [`Router::on_stream`][crate::Router::on_stream]
+/// routes, from memory everywhere else. `415` if `content-type` is not
+/// `multipart/form-data`; `400` if it has no usable `boundary`.
+impl FromRequest for Multipart {
+    type Rejection = MultipartError;
+
+    fn from_request(req: Request) -> impl Future<Output = Result<Self, MultipartError>> + Send {
+        future::ready(Multipart::new(req))
+    }
+}
+
+/// One field of a multipart form — a text input or an uploaded file.
+#[derive(Debug)]
+pub struct Part {
+    body: Vec<u8>,
+    file_name: Option<String>,
+    headers: Vec<(String, String)>,
+    name: String,
+}
+
+impl Part {
+    /// The form field name, from `content-disposition`.
+    pub fn name(&self) -> &str { &self.name }
+
+    /// The uploaded file's name, if the part is a file. Untrusted — never use
+    /// it as a filesystem path as-is.
+    pub fn file_name(&self) -> Option<&str> { self.file_name.as_deref() }
+
+    /// The part's `content-type`, if it sent one. RFC 7578 defaults a
+    /// missing one to `text/plain`.
+    pub fn content_type(&self) -> Option<&str> { self.header("content-type") }
+
+    /// Every part header as name-value pairs, names lowercased.
+    pub fn headers(&self) -> &[(String, String)] { &self.headers }
+
+    /// Case-insensitive lookup for a single part header.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The part's content.
+    pub fn bytes(&self) -> &[u8] { &self.body }
+
+    /// The part's content, owned.
+    pub fn into_bytes(self) -> Vec<u8> { self.body }
+}
+
+// ── Internal ──────────────────────────────────────────────────────────────────
+
+/// The `boundary` parameter of a `multipart/form-data` content type.
+fn boundary(content_type: &str) -> Result<String, MultipartError> {
+    let (essence, params) = content_type.split_once(';').unwrap_or((content_type, ""));
+    if !essence.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return Err(MultipartError::InvalidContentType);
+    }
+    // RFC 2046 §5.1.1: 1–70 characters.
+    match param(&parse_params(params), "boundary") {
+        Some(b) if (1..=70).contains(&b.len()) => Ok(b),
+        _                                       => Err(MultipartError::InvalidBoundary),
+    }
+}
+
+/// Splits `; a=b; c="d;e"` into pairs, names lowercased, quotes removed.
+fn parse_params(s: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut rest = s;
+    loop {
+        rest = rest.trim_start_matches([';', ' ', '\t']);
+        let Some((name, after)) = rest.split_once('=') else { return params };
+        let name = name.trim().to_ascii_lowercase();
+        let after = after.trim_start();
+        let (value, next) = match after.strip_prefix('"') {
+            Some(quoted) => unquote(quoted),
+            None         => {
+                let end = after.find(';').unwrap_or(after.len());
+                (after[..end].trim_end().to_owned(), &after[end..])
+            }
+        };
+        params.push((name, value));
+        rest = next;
+    }
+}
+
+/// Reads a quoted string up to its closing `"`. Returns the value and what
+/// follows the closing quote.
+///
+/// No `\` escapes: browsers percent-encode `"` in names (WHATWG HTML
+/// §4.10.21.8), and old ones send Windows paths with literal backslashes.
+fn unquote(s: &str) -> (String, &str) {
+    match s.split_once('"') {
+        Some((value, rest)) => (value.to_owned(), rest),
+        None                => (s.to_owned(), ""),
+    }
+}
+
+fn param(params: &[(String, String)], name: &str) -> Option<String> {
+    params.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone())
+}
+
+/// The first index of `needle` in `haystack` at or after `from`.
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack.get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|at| from + at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::method::Method;
+    use crate::router::Router;
+    use crate::status::Status;
+    use crate::testing::send;
+
+    const TYPE: &str = "multipart/form-data; boundary=XyZ";
+
+    fn request(content_type: &str, body: &[u8]) -> Request {
+        let headers = vec![("content-type".to_owned(), content_type.to_owned())];
+        let (body, path) = (body.to_vec(), "/".to_owned());
+        Request::new(body, headers, Method::Post, Default::default(), path, String::new(), Default::default())
+    }
+
+    async fn parts(multipart: &mut Multipart) -> Result<Vec<Part>, MultipartError> {
+        let mut parts = Vec::new();
+        while let Some(part) = multipart.next_part().await? {
+            parts.push(part);
+        }
+        Ok(parts)
+    }
+
+    async fn parse(body: &[u8]) -> Result<Vec<Part>, MultipartError> {
+        parts(&mut Multipart::new(request(TYPE, body))?).await
+    }
+
+    const FORM: &[u8] = b"--XyZ\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        hello\r\n\
+        --XyZ\r\n\
+        content-disposition: form-data; name=\"file\"; filename=\"a;b.txt\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        line one\r\nline two\r\n\
+        --XyZ--\r\n";
+
+    #[tokio::test]
+    async fn reads_fields_and_files_in_order() {
+        let parts = parse(FORM).await.unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!((parts[0].name(), parts[0].file_name(), parts[0].bytes()), ("title", None, &b"hello"[..]));
+        assert_eq!((parts[1].name(), parts[1].file_name()), ("file", Some("a;b.txt")));
+        assert_eq!(parts[1].content_type(), Some("text/plain"));
+        assert_eq!(parts[1].header("CONTENT-TYPE"), Some("text/plain"));
+        assert_eq!(parts[1].bytes(), b"line one\r\nline two");
+    }
+
+    #[tokio::test]
+    async fn skips_preamble_padding_and_epilogue() {
+        let body = b"preamble\r\n--XyZ \t\r\ncontent-disposition: form-data; name=a\r\n\r\n1\r\n--XyZ--epilogue";
+        let parts = parse(body).await.unwrap();
+        assert_eq!((parts.len(), parts[0].name(), parts[0].bytes()), (1, "a", &b"1"[..]));
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_content_types() {
+        let err = Multipart::new(request("text/plain", FORM)).unwrap_err();
+        assert_eq!(err, MultipartError::InvalidContentType);
+        let err = Multipart::new(request("multipart/form-data", FORM)).unwrap_err();
+        assert_eq!(err, MultipartError::InvalidBoundary);
+        let long = format!("multipart/form-data; boundary={}", "b".repeat(71));
+        assert_eq!(Multipart::new(request(&long, FORM)).unwrap_err(), MultipartError::InvalidBoundary);
+    }
+
+    #[tokio::test]
+    async fn yields_leading_parts_before_a_broken_one() {
+        let mut multipart = Multipart::new(request(TYPE, &FORM[..FORM.len() - 12])).unwrap();
+        assert_eq!(multipart.next_part().await.unwrap().unwrap().name(), "title");
+        assert_eq!(multipart.next_part().await.unwrap_err(), MultipartError::UnexpectedEnd);
+        assert!(multipart.next_part().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_parts() {
+        let no_name = b"--XyZ\r\ncontent-disposition: form-data\r\n\r\nx\r\n--XyZ--";
+        assert_eq!(parse(no_name).await.unwrap_err(), MultipartError::InvalidPart);
+        let attachment = b"--XyZ\r\ncontent-disposition: attachment; name=a\r\n\r\nx\r\n--XyZ--";
+        assert_eq!(parse(attachment).await.unwrap_err(), MultipartError::InvalidPart);
+        let no_colon = b"--XyZ\r\nnonsense\r\n\r\nx\r\n--XyZ--";
+        assert_eq!(parse(no_colon).await.unwrap_err(), MultipartError::InvalidPart);
+        assert_eq!(parse(b"no boundary at all").await.unwrap_err(), MultipartError::UnexpectedEnd);
+    }
+
+    #[tokio::test]
+    async fn enforces_part_and_total_limits() {
+        let mut multipart = Multipart::new(request(TYPE, FORM)).unwrap().max_part_size(5);
+        assert_eq!(parts(&mut multipart).await.unwrap_err(), MultipartError::PartTooLarge);
+        let mut multipart = Multipart::new(request(TYPE, FORM)).unwrap().max_part_size(18);
+        assert_eq!(parts(&mut multipart).await.unwrap().len(), 2);
+        let mut multipart = Multipart::new(request(TYPE, FORM)).unwrap().max_total_size(22);
+        assert_eq!(parts(&mut multipart).await.unwrap_err(), MultipartError::TooLarge);
+        let mut multipart = Multipart::new(request(TYPE, FORM)).unwrap().max_total_size(23);
+        assert_eq!(parts(&mut multipart).await.unwrap().len(), 2);
+    }
+
+    /// Every part as `name=size`, or the error's status.
+    async fn sizes(multipart: Multipart) -> Result<String, MultipartError> {
+        let mut multipart = multipart.max_part_size(100_000);
+        let mut out = Vec::new();
+        while let Some(part) = multipart.next_part().await? {
+            out.push(format!("{}={}", part.name(), part.bytes().len()));
+        }
+        Ok(out.join(" "))
+    }
+
+    fn upload(body: &[u8], content_length: usize) -> Vec<u8> {
+        let mut raw = format!(
+            "POST /upload HTTP/1.1\r\ncontent-type: {TYPE}\r\ncontent-length: {content_length}\r\n\r\n",
+        ).into_bytes();
+        raw.extend_from_slice(body);
+        raw
+    }
+
+    fn big_form() -> Vec<u8> {
+        let mut body = b"--XyZ\r\ncontent-disposition: form-data; name=big\r\n\r\n".to_vec();
+        body.extend(std::iter::repeat_n(b'x', 3 * READ_SIZE + 7));
+        body.extend_from_slice(b"\r\n--XyZ\r\ncontent-disposition: form-data; name=small\r\n\r\nok\r\n--XyZ--\r\n");
+        body
+    }
+
+    #[tokio::test]
+    async fn reads_a_streamed_body_across_many_reads() {
+        let body = big_form();
+        for router in [
+            Router::new().on_stream(Method::Post, "/upload", sizes, ()),
+            Router::new().on(Method::Post, "/upload", sizes, ()),
+        ] {
+            let reply = send(router, upload(&body, body.len())).await;
+            assert_eq!(reply.text(), format!("big={} small=2", 3 * READ_SIZE + 7));
+        }
+    }
+
+    #[tokio::test]
+    async fn streamed_limits_stop_the_read_early() {
+        async fn capped(multipart: Multipart) -> Result<Status, MultipartError> {
+            let mut multipart = multipart.max_part_size(1024);
+            while multipart.next_part().await?.is_some() {}
+            Ok(Status::NoContent)
+        }
+        // Only the first 32 KiB of a 10 MB upload ever arrive: the limit must
+        // trip on what has, not wait for the rest.
+        let body = big_form();
+        let router = Router::new().on_stream(Method::Post, "/upload", capped, ());
+        let reply = send(router, upload(&body[..32 * 1024], 10_000_000)).await;
+        assert_eq!(reply.status, 413);
+    }
+
+    #[tokio::test]
+    async fn a_streamed_body_cut_short_is_unexpected_end() {
+        let body = big_form();
+        let router = Router::new().on_stream(Method::Post, "/upload", sizes, ());
+        let reply = send(router, upload(&body[..body.len() - 20], body.len())).await;
+        assert_eq!((reply.status, reply.text()), (400, "multipart body ends before its closing boundary"));
+    }
+}
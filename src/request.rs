@@ -2,7 +2,8 @@
 //!
 //! Parsed from the raw TCP stream by the server. By the time your handler
 //! receives a [`Request`], the proxy has already validated and buffered the
-//! input. You get a clean struct — no ceremony, no streaming.
+//! input. You get a clean struct — no ceremony, no streaming unless a route
+//! asks for it with [`Router::on_stream`][crate::Router::on_stream].
 //!
 //! # Accessing request data
 //!
@@ -29,21 +30,45 @@
 //! ```
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
+use crate::body::BodyStream;
+use crate::cookie;
+#[cfg(feature = "serde")]
+use crate::de;
+use crate::error::FormError;
+#[cfg(feature = "json")]
+use crate::error::JsonError;
+use crate::error::MultipartError;
+#[cfg(feature = "serde")]
+use crate::error::QueryError;
+use crate::extensions::Extensions;
+#[cfg(feature = "json")]
+use crate::json;
 use crate::method::Method;
+use crate::multipart::Multipart;
+use crate::state::StateMap;
+use crate::urlencoded;
 
 /// An incoming HTTP request, parsed from the raw TCP stream.
 ///
 /// Constructed by the server before dispatch. Fields are read-only — handlers
-/// receive the request and return a [`Response`][crate::Response].
+/// receive the request and return a [`Response`][crate::Response]. The one
+/// exception is [`extensions_mut`][Request::extensions_mut], where middleware
+/// leaves typed values for the handler.
 pub struct Request {
     pub(crate) body: Vec<u8>,
+    pub(crate) extensions: Extensions,
     pub(crate) headers: Vec<(String, String)>,
     pub(crate) method: Method,
     pub(crate) params: HashMap<String, String>,
     pub(crate) path: String,
-    pub(crate) query: HashMap<String, String>,
+    pub(crate) query: Vec<(String, String)>,
     pub(crate) raw_query: String,
+    pub(crate) state: Arc<StateMap>,
+    /// The unread body on [`Router::on_stream`][crate::Router::on_stream]
+    /// routes; `body` is empty there.
+    pub(crate) stream: Option<BodyStream>,
 }
 
 impl Request {
@@ -54,9 +79,11 @@ impl Request {
         params: HashMap<String, String>,
         path: String,
         raw_query: String,
+        state: Arc<StateMap>,
     ) -> Self {
-        let query = parse_query(&raw_query);
-        Self { body, headers, method, params, path, query, raw_query }
+        let query = urlencoded::parse(&raw_query);
+        let extensions = Extensions::new();
+        Self { body, extensions, headers, method, params, path, query, raw_query, state, stream: None }
     }
 
     /// Returns the HTTP method.
@@ -69,9 +96,10 @@ impl Request {
 
     /// Looks up a single query parameter by name.
     ///
-    /// Returns `None` if the key is absent. For duplicate keys (e.g.
-    /// `?tag=a&tag=b`) the last value wins — this is uncommon in REST APIs
-    /// and consistent with most framework behaviour.
+    /// Keys and values are percent-decoded, with `+` read as a space, so
+    /// `?q=hello+w%C3%B6rld` gives `"hello wörld"`. Returns `None` if the key
+    /// is absent. For duplicate keys (e.g. `?tag=a&tag=b`) the last value
+    /// wins — use [`query_all`][Request::query_all] to get every one.
     ///
     /// Unknown parameters from external services or tracing agents are kept
     /// as-is; the handler simply ignores what it does not need.
@@ -86,13 +114,71 @@ impl Request {
     /// }
     /// ```
     pub fn query(&self, key: &str) -> Option<&str> {
-        self.query.get(key).map(String::as_str)
+        self.query.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// Every value of a repeated query parameter, in request order.
+    ///
+    /// ```rust,no_run
+    /// # use astor::{Request, Response};
+    /// async fn handler(req: Request) -> Response {
+    ///     // GET /posts?tag=rust&tag=http
+    ///     let tags: Vec<&str> = req.query_all("tag").collect(); // ["rust", "http"]
+    ///     Response::text(tags.join(","))
+    /// }
+    /// ```
+    pub fn query_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        self.query.iter().filter(move |(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// Every decoded query pair, in request order, repeats included.
+    ///
+    /// Pairs with an empty key are dropped; a key with no `=` has an empty
+    /// value.
+    pub fn query_pairs(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.query.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Deserializes the decoded query string into `T`.
+    ///
+    /// Missing keys are fine for `Option` fields and `#[serde(default)]`;
+    /// repeated keys fill `Vec` fields; the last value wins for everything
+    /// else. Booleans accept `true`/`false`, `1`/`0` and `on`/`off`. An empty
+    /// value (`?page=`) reads as absent for `Option` fields.
+    ///
+    /// Requires the `serde` feature.
+    ///
+    /// ```rust,no_run
+    /// # use astor::{Request, Response, QueryError};
+    /// #[derive(serde::Deserialize)]
+    /// struct List {
+    ///     #[serde(default)]
+    ///     tag:   Vec<String>,
+    ///     limit: Option<u32>,
+    ///     page:  Option<u32>,
+    /// }
+    ///
+    /// // GET /posts?page=2&tag=rust&tag=http
+    /// async fn list(req: Request) -> Result<Response, QueryError> {
+    ///     let q: List = req.query_as()?;
+    ///     let (page, limit) = (q.page.unwrap_or(1), q.limit.unwrap_or(20));
+    ///     Ok(Response::text(format!("page={page} limit={limit} tags={}", q.tag.join(","))))
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// [`QueryError`] — a `400 Bad Request` naming the parameter — if a
+    /// required key is missing or a value does not parse.
+    #[cfg(feature = "serde")]
+    pub fn query_as<'a, T: serde::Deserialize<'a>>(&'a self) -> Result<T, QueryError> {
+        de::from_pairs(&self.query).map_err(QueryError::new)
     }
 
     /// Returns the raw query string, without the leading `?`.
     ///
-    /// Empty string if the request had no query string. Use this when you
-    /// need the original bytes — e.g. HMAC signature verification against
+    /// Empty string if the request had no query string. Not percent-decoded.
+    /// Use this when you need the original bytes — e.g. HMAC signature verification against
     /// an external API that signs the raw query string.
     ///
     /// ```rust,no_run
@@ -125,8 +211,161 @@ impl Request {
     /// Body size is constrained by `client_max_body_size` in your nginx config,
     /// not by astor. Gate on `body.len()` inside the handler if you need
     /// per-route limits tighter than the global nginx setting.
+    ///
+    /// Always empty on routes registered with
+    /// [`Router::on_stream`][crate::Router::on_stream] — read those with
+    /// [`into_body_stream`][Request::into_body_stream].
     pub fn body(&self) -> &[u8] { &self.body }
 
+    /// The body as an `AsyncRead` — see [`BodyStream`].
+    ///
+    /// On routes registered with [`Router::on_stream`][crate::Router::on_stream]
+    /// this reads from the connection as the body arrives; elsewhere it reads
+    /// the buffered [`body`][Request::body].
+    pub fn into_body_stream(self) -> BodyStream {
+        self.stream.unwrap_or_else(|| BodyStream::buffered(self.body))
+    }
+
+    /// Parses an `application/x-www-form-urlencoded` body into decoded
+    /// pairs, in order, repeats included.
+    ///
+    /// Decoding follows the same rules as the query string — see
+    /// [`query`][Request::query].
+    ///
+    /// ```rust,no_run
+    /// # use astor::{FormError, Request};
+    /// // <form method="post"><input name="tag"> <input name="tag"> …
+    /// async fn save(req: Request) -> Result<String, FormError> {
+    ///     let form = req.form()?;
+    ///     let tags: Vec<&str> = form.iter()
+    ///         .filter(|(k, _)| k == "tag")
+    ///         .map(|(_, v)| v.as_str())
+    ///         .collect();
+    ///     Ok(tags.join(","))
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// [`FormError::InvalidContentType`] — `415` — if `content-type` is not
+    /// `application/x-www-form-urlencoded`.
+    pub fn form(&self) -> Result<Vec<(String, String)>, FormError> {
+        if !self.header("content-type").is_some_and(urlencoded::is_form) {
+            return Err(FormError::InvalidContentType);
+        }
+        Ok(urlencoded::parse(&String::from_utf8_lossy(&self.body)))
+    }
+
+    /// Deserializes an `application/x-www-form-urlencoded` body into `T`.
+    ///
+    /// Same field rules as [`query_as`][Request::query_as] — `on` for a
+    /// checked checkbox reads as `true`, a blank input as `None`. Requires the
+    /// `serde` feature; the [`Form`][crate::extract::Form] extractor is the
+    /// argument form.
+    ///
+    /// ```rust,no_run
+    /// # use astor::{FormError, Request, Status};
+    /// #[derive(serde::Deserialize)]
+    /// struct Signup {
+    ///     email:      String,
+    ///     newsletter: Option<bool>,
+    /// }
+    ///
+    /// async fn signup(req: Request) -> Result<Status, FormError> {
+    ///     let form: Signup = req.form_as()?;
+    ///     # let _ = (form.email, form.newsletter);
+    ///     Ok(Status::SeeOther)
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// [`FormError::InvalidContentType`] — `415` — for the wrong
+    /// `content-type`; [`FormError::Data`] — `422` — if the fields do not
+    /// fit `T`.
+    #[cfg(feature = "serde")]
+    pub fn form_as<T: serde::de::DeserializeOwned>(&self) -> Result<T, FormError> {
+        de::from_pairs(&self.form()?).map_err(|e| FormError::Data(e.into_message()))
+    }
+
+    /// Deserializes the body as JSON.
+    ///
+    /// Checks `content-type` first: `application/json` or any
+    /// `application/*+json`. `T` may borrow from the body.
+    ///
+    /// Requires the `json` feature. To take the body as a handler argument,
+    /// use the [`Json`][crate::Json] extractor instead.
+    ///
+    /// ```rust,no_run
+    /// # use astor::{JsonError, Request, Status};
+    /// #[derive(serde::Deserialize)]
+    /// struct Rename<'a> { name: &'a str }
+    ///
+    /// async fn rename(req: Request) -> Result<Status, JsonError> {
+    ///     let body: Rename = req.json()?;
+    ///     # let _ = body.name;
+    ///     Ok(Status::NoContent)
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// [`JsonError`] — `415` for a missing or non-JSON `content-type`, `400`
+    /// for malformed JSON, `422` for JSON that does not fit `T`.
+    #[cfg(feature = "json")]
+    pub fn json<'a, T: serde::Deserialize<'a>>(&'a self) -> Result<T, JsonError> {
+        if !self.header("content-type").is_some_and(json::is_json) {
+            return Err(JsonError::InvalidContentType);
+        }
+        serde_json::from_slice(&self.body).map_err(JsonError::new)
+    }
+
+    /// Reads a `multipart/form-data` body — see [`Multipart`].
+    ///
+    /// Consumes the request so the body moves into the parser without a copy.
+    /// Read anything else you need from the request first.
+    ///
+    /// # Errors
+    ///
+    /// [`MultipartError::InvalidContentType`] — `415` — if `content-type` is
+    /// not `multipart/form-data`; [`MultipartError::InvalidBoundary`] — `400`
+    /// — if it has no usable `boundary`.
+    pub fn into_multipart(self) -> Result<Multipart, MultipartError> {
+        Multipart::new(self)
+    }
+
+    /// Typed values attached by middleware — see [`Extensions`].
+    ///
+    /// ```rust,no_run
+    /// # use astor::{Request, Response, Status};
+    /// struct UserId(u64);
+    ///
+    /// async fn me(req: Request) -> Response {
+    ///     match req.extensions().get::<UserId>() {
+    ///         Some(UserId(id)) => Response::text(id.to_string()),
+    ///         None             => Response::status(Status::Unauthorized),
+    ///     }
+    /// }
+    /// ```
+    pub fn extensions(&self) -> &Extensions { &self.extensions }
+
+    /// Mutable access to the request's [`Extensions`] — insert values in
+    /// middleware before calling [`Next::call`][crate::Next::call].
+    ///
+    /// ```rust,no_run
+    /// # use astor::{Next, Request, Response, Status};
+    /// struct UserId(u64);
+    ///
+    /// async fn require_auth(mut req: Request, next: Next) -> Response {
+    ///     let Some(id) = req.header("authorization").and_then(|t| t.parse().ok()) else {
+    ///         return Response::status(Status::Unauthorized);
+    ///     };
+    ///     req.extensions_mut().insert(UserId(id));
+    ///     next.call(req).await
+    /// }
+    /// ```
+    pub fn extensions_mut(&mut self) -> &mut Extensions { &mut self.extensions }
+
     /// Case-insensitive lookup for a single header by name.
     ///
     /// Returns `None` if the header is absent. Header names are lowercased by
@@ -147,6 +386,34 @@ impl Request {
             .map(|(_, v)| v.as_str())
     }
 
+    /// Looks up a cookie the client sent, by name.
+    ///
+    /// Surrounding double quotes are stripped; nothing is decoded. When the
+    /// browser sends two cookies with the same name — set for different
+    /// paths — the first, most specific one wins.
+    ///
+    /// ```rust,no_run
+    /// # use astor::{Request, Response, Status};
+    /// async fn dashboard(req: Request) -> Response {
+    ///     match req.cookie("session") {
+    ///         Some(id) => Response::text(format!("session {id}")),
+    ///         None     => Response::status(Status::Unauthorized),
+    ///     }
+    /// }
+    /// ```
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.cookies().find(|(k, _)| *k == name).map(|(_, v)| v)
+    }
+
+    /// Every cookie the client sent as name-value pairs, in order, across all
+    /// `cookie` headers. Pairs without `=` are skipped.
+    pub fn cookies(&self) -> impl Iterator<Item = (&str, &str)> {
+        let headers = self.headers.iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case("cookie"))
+            .map(|(_, v)| v.as_str());
+        cookie::parse(headers)
+    }
+
     /// Returns a named path parameter extracted by the router.
     ///
     /// For a route `/users/{id}`, `req.param("id")` on `/users/42` returns
@@ -164,25 +431,121 @@ impl Request {
     pub fn param(&self, key: &str) -> Option<&str> {
         self.params.get(key).map(String::as_str)
     }
+
+    /// Returns the shared state of type `S` registered with
+    /// [`Router::with_state`][crate::Router::with_state].
+    ///
+    /// `None` if no state of that type was registered. Middleware reads state
+    /// the same way.
+    ///
+    /// ```rust,no_run
+    /// # use astor::{Request, Response, Status};
+    /// # struct Pool;
+    /// # impl Pool { async fn count_users(&self) -> u64 { 0 } }
+    /// async fn count(req: Request) -> Response {
+    ///     let Some(pool) = req.state::<Pool>() else {
+    ///         return Response::status(Status::InternalServerError);
+    ///     };
+    ///     Response::text(pool.count_users().await.to_string())
+    /// }
+    /// ```
+    pub fn state<S: Send + Sync + 'static>(&self) -> Option<&S> {
+        self.state.get()
+    }
+
+    /// Like [`state`][Request::state], but returns a shared `Arc<S>` — for
+    /// moving the state into a spawned task that outlives the request.
+    ///
+    /// ```rust,no_run
+    /// # use astor::{Request, Status};
+    /// # struct Mailer;
+    /// # impl Mailer { async fn send_welcome(&self) {} }
+    /// async fn signup(req: Request) -> Status {
+    ///     let Some(mailer) = req.state_arc::<Mailer>() else {
+    ///         return Status::InternalServerError;
+    ///     };
+    ///     tokio::spawn(async move { mailer.send_welcome().await });
+    ///     Status::Accepted
+    /// }
+    /// ```
+    pub fn state_arc<S: Send + Sync + 'static>(&self) -> Option<Arc<S>> {
+        self.state.get_arc()
+    }
 }
 
-// ── Internal ──────────────────────────────────────────────────────────────────
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-/// Parses `key=value&key2=value2` into a map.
-///
-/// - Pairs with no `=` are stored with an empty-string value.
-/// - Duplicate keys: last value wins.
-/// - No percent-decoding: nginx passes query strings to upstream as-is,
-///   so the raw bytes are already what the client sent.
-fn parse_query(raw: &str) -> HashMap<String, String> {
-    if raw.is_empty() {
-        return HashMap::new();
-    }
-    raw.split('&')
-        .filter_map(|pair| {
-            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
-            if k.is_empty() { return None; }
-            Some((k.to_owned(), v.to_owned()))
-        })
-        .collect()
+    fn with_query(raw_query: &str) -> Request {
+        let (path, raw_query) = ("/".to_owned(), raw_query.to_owned());
+        Request::new(Vec::new(), Vec::new(), Method::Get, HashMap::new(), path, raw_query, Arc::default())
+    }
+
+    fn with_form(content_type: Option<&str>, body: &str) -> Request {
+        let headers = content_type.map(|v| ("content-type".to_owned(), v.to_owned())).into_iter().collect();
+        let (path, body) = ("/".to_owned(), body.as_bytes().to_vec());
+        Request::new(body, headers, Method::Post, HashMap::new(), path, String::new(), Arc::default())
+    }
+
+    #[test]
+    fn query_decodes_and_last_value_wins() {
+        let req = with_query("q=hello+w%C3%B6rld&page=1&page=2");
+        assert_eq!(req.query("q"), Some("hello wörld"));
+        assert_eq!(req.query("page"), Some("2"));
+        assert_eq!(req.query("missing"), None);
+        assert_eq!(req.raw_query(), "q=hello+w%C3%B6rld&page=1&page=2");
+    }
+
+    #[test]
+    fn query_all_and_pairs_keep_request_order() {
+        let req = with_query("tag=rust&x&tag=http");
+        assert_eq!(req.query_all("tag").collect::<Vec<_>>(), ["rust", "http"]);
+        assert_eq!(req.query_pairs().collect::<Vec<_>>(), [("tag", "rust"), ("x", ""), ("tag", "http")]);
+    }
+
+    #[test]
+    fn cookies_span_every_cookie_header_and_the_first_wins() {
+        let headers = vec![
+            ("cookie".to_owned(), "theme=dark; sid=1".to_owned()),
+            ("Cookie".to_owned(), "sid=2".to_owned()),
+        ];
+        let req = Request::new(Vec::new(), headers, Method::Get, HashMap::new(), "/".into(), String::new(), Arc::default());
+        assert_eq!(req.cookie("sid"), Some("1"));
+        assert_eq!(req.cookie("missing"), None);
+        assert_eq!(req.cookies().collect::<Vec<_>>(), [("theme", "dark"), ("sid", "1"), ("sid", "2")]);
+    }
+
+    #[test]
+    fn form_decodes_every_pair() {
+        let req = with_form(Some("application/x-www-form-urlencoded; charset=utf-8"), "a=1&b=x+y%21&a=2");
+        let pairs = req.form().unwrap();
+        assert_eq!(pairs, [("a", "1"), ("b", "x y!"), ("a", "2")].map(|(k, v)| (k.to_owned(), v.to_owned())));
+    }
+
+    #[test]
+    fn form_checks_the_content_type() {
+        for content_type in [None, Some("application/json"), Some("multipart/form-data; boundary=x")] {
+            let err = with_form(content_type, "a=1").form().unwrap_err();
+            assert!(matches!(err, FormError::InvalidContentType));
+            assert_eq!(u16::from(err.status()), 415);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn form_as_fills_a_struct_or_answers_422() {
+        #[derive(Debug, serde::Deserialize)]
+        struct Login {
+            user: String,
+            remember: Option<bool>,
+        }
+        let form = |body| with_form(Some("application/x-www-form-urlencoded"), body);
+        let login: Login = form("user=ann&remember=on").form_as().unwrap();
+        assert_eq!((login.user.as_str(), login.remember), ("ann", Some(true)));
+
+        let err = form("remember=").form_as::<Login>().unwrap_err();
+        assert_eq!(u16::from(err.status()), 422);
+        assert_eq!(err.to_string(), "invalid form body: missing field `user`");
+    }
 }
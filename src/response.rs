@@ -40,10 +40,56 @@
 //!     .status(Status::Ok)
 //!     .bytes(ContentType::Xml, b"<users/>".to_vec());
 //! ```
+//!
+//! # Streaming
+//!
+//! [`ResponseBuilder::reader`] and [`ResponseBuilder::channel`] send a body
+//! as it is produced, with `transfer-encoding: chunked` — for exports and
+//! downloads too large to hold in memory:
+//!
+//! ```rust,no_run
+//! use astor::{ContentType, Response};
+//! use tokio::sync::mpsc;
+//!
+//! async fn export() -> Response {
+//!     let (tx, rx) = mpsc::channel(16);
+//!     tokio::spawn(async move {
+//!         for id in 0..1_000_000 {
+//!             // `send` waits while the client is slow, and fails once it
+//!             // has gone — stop producing then.
+//!             if tx.send(format!("{id},user{id}\n").into_bytes()).await.is_err() {
+//!                 return;
+//!             }
+//!         }
+//!     });
+//!     Response::builder().channel(ContentType::Csv, rx)
+//! }
+//! ```
+//!
+//! Each chunk is written before the next is asked for, so a slow client
+//! slows the producer down instead of growing a buffer. A client that
+//! disconnects fails the next write: the body is dropped — the reader
+//! closed, the channel's sender told — and the connection ends.
+//!
+//! nginx buffers upstream responses by default (`proxy_buffering on`), so the
+//! client still receives the whole body — only astor's memory use changes.
+//! Send `x-accel-buffering: no` on responses the client must see chunk by
+//! chunk.
+//!
+//! For server-sent events, return an [`Sse`][crate::Sse] — it frames the
+//! events, keeps the connection alive and sets the headers nginx needs.
 
-use tokio::io::{AsyncWrite, AsyncWriteExt};
+use std::future::Future;
+use std::pin::Pin;
 
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, watch};
+
+use crate::cookie::Cookie;
+use crate::jar::CookieJar;
+use crate::sse::Sse;
 use crate::status::Status;
+use crate::upgrade::{OnUpgrade, Upgraded};
 
 // ── ContentType ───────────────────────────────────────────────────────────────
 
@@ -56,7 +102,9 @@ use crate::status::Status;
 pub enum ContentType {
     /// `text/csv`
     Csv,
-    /// `text/event-stream` — server-sent events (SSE).
+    /// `text/event-stream` — server-sent events (SSE). Prefer
+    /// [`Sse`][crate::Sse], which frames the events and sets this
+    /// content-type automatically.
     EventStream,
     /// `application/x-www-form-urlencoded`
     FormData,
@@ -143,9 +191,11 @@ impl ContentType {
 ///     .bytes(ContentType::Xml, b"<users/>".to_vec());
 /// ```
 pub struct Response {
-    pub(crate) body: Vec<u8>,
+    pub(crate) body: Body,
     pub(crate) headers: Vec<(String, String)>,
     pub(crate) status: u16,
+    /// Takes the connection over once a `101` has been written.
+    pub(crate) upgrade: Option<OnUpgrade>,
 }
 
 impl Response {
@@ -174,7 +224,7 @@ impl Response {
     /// Response::status(Status::ServiceUnavailable); // 503
     /// ```
     pub fn status(code: Status) -> Self {
-        Self { body: Vec::new(), headers: Vec::new(), status: code.into() }
+        Self { body: Body::Full(Vec::new()), headers: Vec::new(), status: code.into(), upgrade: None }
     }
 
     /// Builder for responses that need a custom status code or extra headers.
@@ -188,27 +238,65 @@ impl Response {
 
     fn bytes_raw(content_type: &str, body: Vec<u8>) -> Self {
         Self {
-            body,
+            body: Body::Full(body),
             headers: vec![("content-type".to_owned(), content_type.to_owned())],
             status: Status::Ok.into(),
+            upgrade: None,
         }
     }
 
+    /// `true` when the body is produced while it is sent — its length is
+    /// not known up front.
+    pub(crate) fn is_streamed(&self) -> bool {
+        !matches!(self.body, Body::Full(_))
+    }
+
+    /// Serialises the response onto the wire.
+    ///
+    /// `head_only` answers a `HEAD` request: status and headers go out,
+    /// `content-length` still describes the body, the body itself does not
+    /// (RFC 9110 §9.3.2). A streamed body is never polled for `HEAD`.
+    ///
+    /// A streamed body goes out chunked to an HTTP/1.1 request. An HTTP/1.0
+    /// client knows no chunked coding (RFC 9112 §6.1), so it gets the body
+    /// unframed, ended by closing the connection — the caller's job, along
+    /// with the `connection: close` header.
+    ///
+    /// An event stream ends early once `shutdown` turns `true`.
+    ///
+    /// # Errors
+    ///
+    /// A failed write, or a streamed body whose reader fails. Either way the
+    /// response is cut short and the connection must be dropped.
     pub(crate) async fn write_to<W: AsyncWrite + Unpin>(
         self,
         writer: &mut W,
+        head_only: bool,
+        version: Version,
+        shutdown: &watch::Receiver<bool>,
     ) -> std::io::Result<()> {
         writer.write_all(
             format!("HTTP/1.1 {} {}\r\n", self.status, status_reason(self.status)).as_bytes(),
         ).await?;
-        writer.write_all(
-            format!("content-length: {}\r\n", self.body.len()).as_bytes(),
-        ).await?;
+        // RFC 9110 §8.6 — no content-length on 1xx or 204, and no body.
+        let no_body = matches!(self.status, 100..=199 | 204);
+        match &self.body {
+            _ if no_body      => {}
+            Body::Full(bytes) => {
+                writer.write_all(format!("content-length: {}\r\n", bytes.len()).as_bytes()).await?;
+            }
+            _ if version == Version::Http10 => {} // unframed, ended by the close
+            Body::Channel(_) | Body::Events(_) | Body::Reader(_) => {
+                writer.write_all(b"transfer-encoding: chunked\r\n").await?;
+            }
+        }
         for (name, value) in &self.headers {
             writer.write_all(format!("{name}: {value}\r\n").as_bytes()).await?;
         }
         writer.write_all(b"\r\n").await?;
-        writer.write_all(&self.body).await?;
+        if !head_only && !no_body {
+            self.body.write_to(writer, version, shutdown).await?;
+        }
         writer.flush().await
     }
 }
@@ -266,6 +354,42 @@ impl ResponseBuilder {
         self
     }
 
+    /// Appends a `set-cookie` header. Call once per cookie — each gets its
+    /// own header line, as RFC 6265 requires.
+    ///
+    /// ```rust
+    /// # use astor::{Cookie, Response, Status};
+    /// Response::builder()
+    ///     .status(Status::SeeOther)
+    ///     .header("location", "/")
+    ///     .cookie(Cookie::new("session", "abc123").path("/").http_only())
+    ///     .cookie(Cookie::removal("flash").path("/"))
+    ///     .no_body();
+    /// ```
+    pub fn cookie(mut self, cookie: Cookie) -> Self {
+        self.headers.push(("set-cookie".to_owned(), cookie.to_string()));
+        self
+    }
+
+    /// Appends a `set-cookie` header for every cookie added to or removed
+    /// from `jar`, in order. A jar with no changes adds nothing.
+    ///
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use astor::{Cookie, CookieJar, CookieKey, Response};
+    /// # let mut jar = CookieJar::empty(Arc::new(CookieKey::generate()?));
+    /// jar.add_private(Cookie::new("session", "abc123").path("/").http_only())?;
+    /// jar.remove(Cookie::new("flash", "").path("/"));
+    /// Response::builder().jar(jar).text("signed in");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn jar(mut self, jar: CookieJar) -> Self {
+        for cookie in jar.delta() {
+            self.headers.push(("set-cookie".to_owned(), cookie.to_string()));
+        }
+        self
+    }
+
     /// Terminate with a JSON body (`application/json`).
     ///
     /// astor sends bytes — build them however you like:
@@ -302,16 +426,181 @@ impl ResponseBuilder {
     ///     .no_body();
     /// ```
     pub fn no_body(self) -> Response {
-        Response { body: Vec::new(), headers: self.headers, status: self.status }
+        Response { body: Body::Full(Vec::new()), headers: self.headers, status: self.status, upgrade: None }
+    }
+
+    /// Terminate with a body read from `reader` until EOF, sent in chunks
+    /// as it is read.
+    ///
+    /// A read error after the headers have gone out cuts the body short:
+    /// the connection is closed without the final chunk, so the client sees
+    /// an incomplete response rather than a truncated one passed off as
+    /// whole.
+    ///
+    /// ```rust,no_run
+    /// use astor::{ContentType, Response, Status};
+    ///
+    /// async fn download() -> Response {
+    ///     match tokio::fs::File::open("/var/exports/users.csv").await {
+    ///         Ok(file) => Response::builder().reader(ContentType::Csv, file),
+    ///         Err(_)   => Response::status(Status::NotFound),
+    ///     }
+    /// }
+    /// ```
+    pub fn reader(self, content_type: ContentType, reader: impl AsyncRead + Send + 'static) -> Response {
+        self.finish_body(content_type.as_str(), Body::Reader(Box::pin(reader)))
+    }
+
+    /// Terminate with a body made of the chunks sent on `rx`, ending when
+    /// every sender is dropped. Empty chunks are skipped.
+    ///
+    /// The channel's capacity bounds how far the producer runs ahead of the
+    /// client. Once the client is gone, `send` fails.
+    pub fn channel(self, content_type: ContentType, rx: mpsc::Receiver<Vec<u8>>) -> Response {
+        self.finish_body(content_type.as_str(), Body::Channel(rx))
+    }
+
+    /// Terminate with a server-sent event stream. Adds
+    /// `cache-control: no-cache` and `x-accel-buffering: no`.
+    ///
+    /// Returning the [`Sse`][crate::Sse] itself does the same with `200 OK`
+    /// and no other headers.
+    ///
+    /// ```rust,no_run
+    /// use astor::{Event, Request, Response, Sse};
+    /// use tokio::sync::mpsc;
+    ///
+    /// async fn feed(req: Request) -> Response {
+    ///     let resume_after = req.header("last-event-id").map(str::to_owned);
+    ///     let (tx, rx) = mpsc::channel::<Event>(16);
+    ///     // … hand `tx` and `resume_after` to the producer …
+    ///     # drop((tx, resume_after));
+    ///     Response::builder()
+    ///         .header("x-feed-version", "2")
+    ///         .sse(Sse::new(rx))
+    /// }
+    /// ```
+    pub fn sse(self, sse: Sse) -> Response {
+        self.header("cache-control", "no-cache")
+            .header("x-accel-buffering", "no")
+            .finish_body(ContentType::EventStream.as_str(), Body::Events(sse))
+    }
+
+    /// Terminate with `101 Switching Protocols` to `protocol`, whatever the
+    /// status set so far. Adds `upgrade: {protocol}` and
+    /// `connection: upgrade`.
+    ///
+    /// Once the response is written astor stops speaking HTTP on the
+    /// connection and runs `callback` with it; the connection closes when
+    /// the callback returns. Only switch when the request asked to — check
+    /// its `upgrade` header first. For WebSockets, use [`crate::ws`].
+    ///
+    /// Middleware may still replace the response — then there is no
+    /// upgrade and `callback` never runs. See [`Upgraded`][crate::Upgraded]
+    /// for an example.
+    pub fn upgrade<F, Fut>(self, protocol: &str, callback: F) -> Response
+    where
+        F: FnOnce(Upgraded) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut res = self
+            .status(Status::SwitchingProtocols)
+            .header("upgrade", protocol)
+            .header("connection", "upgrade")
+            .no_body();
+        res.upgrade = Some(Box::new(move |upgraded: Upgraded| Box::pin(callback(upgraded))));
+        res
     }
 
     fn finish(self, content_type: &str, body: Vec<u8>) -> Response {
+        self.finish_body(content_type, Body::Full(body))
+    }
+
+    fn finish_body(self, content_type: &str, body: Body) -> Response {
         let mut headers = vec![("content-type".to_owned(), content_type.to_owned())];
         headers.extend(self.headers);
-        Response { body, headers, status: self.status }
+        Response { body, headers, status: self.status, upgrade: None }
     }
 }
 
+// ── Body ──────────────────────────────────────────────────────────────────────
+
+/// A response body — in memory, or produced while it is sent.
+pub(crate) enum Body {
+    /// Chunks from a channel, until every sender is dropped.
+    Channel(mpsc::Receiver<Vec<u8>>),
+    /// Server-sent events, until the channel closes or the server shuts down.
+    Events(Sse),
+    /// Sent with `content-length`.
+    Full(Vec<u8>),
+    /// Read until EOF.
+    Reader(Pin<Box<dyn AsyncRead + Send>>),
+}
+
+impl Body {
+    /// Writes the body: as-is when full, chunked (RFC 9112 §7.1) otherwise —
+    /// unless the request was HTTP/1.0, which gets it unframed.
+    async fn write_to<W: AsyncWrite + Unpin>(
+        self,
+        writer: &mut W,
+        version: Version,
+        shutdown: &watch::Receiver<bool>,
+    ) -> std::io::Result<()> {
+        match self {
+            Body::Full(bytes) => return writer.write_all(&bytes).await,
+            Body::Channel(mut rx) => {
+                while let Some(chunk) = rx.recv().await {
+                    write_chunk(writer, &chunk, version).await?;
+                }
+            }
+            Body::Events(sse) => sse.write_to(writer, version, shutdown).await?,
+            Body::Reader(mut reader) => {
+                let mut buf = vec![0; 16 * 1024];
+                loop {
+                    let n = reader.read(&mut buf).await?;
+                    if n == 0 {
+                        break;
+                    }
+                    write_chunk(writer, &buf[..n], version).await?;
+                }
+            }
+        }
+        match version {
+            Version::Http10 => Ok(()),
+            Version::Http11 => writer.write_all(b"0\r\n\r\n").await,
+        }
+    }
+}
+
+/// The HTTP version of the request a response answers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Version {
+    Http10,
+    Http11,
+}
+
+/// One chunk of a streamed body — framed for HTTP/1.1, bare for 1.0 —
+/// flushed so it leaves now rather than with the next one.
+pub(crate) async fn write_chunk<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    data: &[u8],
+    version: Version,
+) -> std::io::Result<()> {
+    if data.is_empty() {
+        return Ok(()); // a zero-size chunk would end the body
+    }
+    if version == Version::Http10 {
+        writer.write_all(data).await?;
+        return writer.flush().await;
+    }
+    let mut chunk = format!("{:x}\r\n", data.len()).into_bytes();
+    chunk.reserve(data.len() + 2);
+    chunk.extend_from_slice(data);
+    chunk.extend_from_slice(b"\r\n");
+    writer.write_all(&chunk).await?;
+    writer.flush().await
+}
+
 // ── IntoResponse ──────────────────────────────────────────────────────────────
 
 /// Conversion into an HTTP [`Response`].
@@ -319,26 +608,29 @@ impl ResponseBuilder {
 /// Implement on your own types to return them directly from handlers instead
 /// of constructing a [`Response`] at every call site.
 ///
-/// # Example — typed `Json<T>` wrapper with serde
+/// # Example — an application error type
 ///
-/// ```rust,ignore
+/// ```rust
 /// use astor::{IntoResponse, Request, Response, Status};
-/// use serde::Serialize;
 ///
-/// struct Json<T: Serialize>(T);
+/// enum ApiError { Forbidden, NotFound }
 ///
-/// impl<T: Serialize> IntoResponse for Json<T> {
+/// impl IntoResponse for ApiError {
 ///     fn into_response(self) -> Response {
-///         match serde_json::to_vec(&self.0) {
-///             Ok(bytes) => Response::json(bytes),
-///             Err(_)    => Response::status(Status::InternalServerError),
-///         }
+///         let (status, code) = match self {
+///             Self::Forbidden => (Status::Forbidden, "forbidden"),
+///             Self::NotFound  => (Status::NotFound,  "not_found"),
+///         };
+///         Response::builder()
+///             .status(status)
+///             .json(format!(r#"{{"error":"{code}"}}"#).into_bytes())
 ///     }
 /// }
 ///
 /// // Handler return type is inferred — no Response construction at the call site.
-/// async fn get_user(_req: Request) -> Json<User> {
-///     Json(User { id: 1, name: "alice".into() })
+/// async fn get_user(req: Request) -> Result<String, ApiError> {
+///     let id = req.param("id").ok_or(ApiError::NotFound)?;
+///     Ok(format!("user {id}"))
 /// }
 /// ```
 ///
@@ -350,6 +642,9 @@ impl ResponseBuilder {
 /// | `&'static str` | `200 OK`, `text/plain; charset=utf-8`. |
 /// | [`String`] | `200 OK`, `text/plain; charset=utf-8`. |
 /// | [`Status`] | No body — status code only. |
+/// | `Result<T, E>` | Whichever arm it holds. |
+/// | [`Sse`][crate::Sse] | `200 OK`, `text/event-stream`, streamed. |
+/// | `Json<T>` | `200 OK`, `application/json` — requires the `json` feature. |
 pub trait IntoResponse {
     fn into_response(self) -> Response;
 }
@@ -366,6 +661,30 @@ impl IntoResponse for String {
     fn into_response(self) -> Response { Response::text(self) }
 }
 
+/// Return `Result` from a handler and use `?` — both arms are responses.
+///
+/// ```rust
+/// use astor::{Request, Status};
+///
+/// async fn handler(req: Request) -> Result<String, Status> {
+///     let id = req.param("id").ok_or(Status::BadRequest)?;
+///     Ok(format!("user {id}"))
+/// }
+/// ```
+impl<T: IntoResponse, E: IntoResponse> IntoResponse for Result<T, E> {
+    fn into_response(self) -> Response {
+        match self {
+            Ok(ok)   => ok.into_response(),
+            Err(err) => err.into_response(),
+        }
+    }
+}
+
+/// The rejection of extractors that cannot fail.
+impl IntoResponse for std::convert::Infallible {
+    fn into_response(self) -> Response { match self {} }
+}
+
 /// Return a [`Status`] directly from a handler — astor wraps it into a
 /// body-less response.
 ///
@@ -446,3 +765,152 @@ fn status_reason(code: u16) -> &'static str {
         _   => "",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use tokio::io::ReadBuf;
+
+    use super::*;
+    use crate::method::Method;
+    use crate::router::Router;
+    use crate::testing::{exchange, get, parse};
+
+    /// Everything `res` writes, and whether writing succeeded.
+    async fn write(res: Response, head_only: bool) -> (String, io::Result<()>) {
+        let (_tx, shutdown) = watch::channel(false);
+        let mut out = Vec::new();
+        let result = res.write_to(&mut out, head_only, Version::Http11, &shutdown).await;
+        (String::from_utf8(out).unwrap(), result)
+    }
+
+    /// A reader that fails on its first read.
+    struct Failing;
+
+    impl AsyncRead for Failing {
+        fn poll_read(self: Pin<&mut Self>, _: &mut Context<'_>, _: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Err(io::Error::other("disk on fire")))
+        }
+    }
+
+    #[tokio::test]
+    async fn readers_are_sent_chunked() {
+        let res = Response::builder().reader(ContentType::Csv, &b"id,name\n1,ada\n"[..]);
+        let (out, result) = write(res, false).await;
+        result.unwrap();
+        assert_eq!(
+            out,
+            "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\ncontent-type: text/csv\r\n\r\n\
+             e\r\nid,name\n1,ada\n\r\n0\r\n\r\n",
+        );
+    }
+
+    #[tokio::test]
+    async fn channels_skip_empty_chunks_and_end_with_the_senders() {
+        let (tx, rx) = mpsc::channel(4);
+        for chunk in ["a", "", "bc"] {
+            tx.send(chunk.as_bytes().to_vec()).await.unwrap();
+        }
+        drop(tx);
+        let (out, result) = write(Response::builder().channel(ContentType::Text, rx), false).await;
+        result.unwrap();
+        assert!(out.ends_with("\r\n\r\n1\r\na\r\n2\r\nbc\r\n0\r\n\r\n"), "{out:?}");
+        assert!(!out.contains("content-length"));
+    }
+
+    #[tokio::test]
+    async fn reader_errors_cut_the_body_short() {
+        let res = Response::builder().reader(ContentType::Csv, (&b"id\n"[..]).chain(Failing));
+        let (out, result) = write(res, false).await;
+        assert_eq!(result.unwrap_err().to_string(), "disk on fire");
+        assert!(out.ends_with("3\r\nid\n\r\n"), "{out:?}");
+    }
+
+    #[tokio::test]
+    async fn head_and_no_content_never_poll_the_body() {
+        let res = Response::builder().reader(ContentType::Csv, Failing);
+        let (out, result) = write(res, true).await;
+        result.unwrap();
+        assert!(out.ends_with("transfer-encoding: chunked\r\ncontent-type: text/csv\r\n\r\n"), "{out:?}");
+
+        let res = Response::builder().status(Status::NoContent).reader(ContentType::Csv, Failing);
+        let (out, result) = write(res, false).await;
+        result.unwrap();
+        assert_eq!(out, "HTTP/1.1 204 No Content\r\ncontent-type: text/csv\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn a_slow_client_holds_the_producer_back() {
+        let (tx, rx) = mpsc::channel(1);
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let (_shutdown_tx, shutdown) = watch::channel(false);
+        let writer = tokio::spawn(async move {
+            Response::builder().channel(ContentType::Csv, rx).write_to(&mut server, false, Version::Http11, &shutdown).await
+        });
+
+        // Nothing reads `client`: the pipe fills, then the channel does.
+        let mut sent = 0;
+        while tokio::time::timeout(Duration::from_millis(50), tx.send(vec![b'x'; 32])).await.is_ok() {
+            sent += 1;
+            assert!(sent < 16, "the producer was never held back");
+        }
+
+        // A client that hangs up fails the write and, with it, the producer.
+        drop(client.read(&mut [0; 8]).await);
+        drop(client);
+        assert!(writer.await.unwrap().is_err());
+        assert!(tx.send(vec![b'x']).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn streamed_responses_keep_the_connection_alive() {
+        async fn export() -> Response {
+            let (tx, rx) = mpsc::channel(2);
+            tokio::spawn(async move {
+                for row in 0..100 {
+                    if tx.send(format!("{row},row {row}\n").into_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+            });
+            Response::builder().channel(ContentType::Csv, rx)
+        }
+        let app = || Router::new().on(Method::Get, "/export", export, ());
+
+        let reply = get(app(), "/export").await;
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.header("transfer-encoding"), Some("chunked"));
+        let text = reply.text();
+        assert_eq!(text.lines().count(), 100);
+        assert!(text.starts_with("0,row 0\n") && text.ends_with("99,row 99\n"));
+
+        let out = exchange(app(), "HEAD /export HTTP/1.1\r\nhost: a\r\n\r\n").await;
+        assert!(out.starts_with("HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n"), "{out:?}");
+        assert!(out.ends_with("content-type: text/csv\r\n\r\n"), "{out:?}");
+
+        let out = exchange(app(), "GET /export HTTP/1.1\r\nhost: a\r\n\r\nGET /export HTTP/1.1\r\nhost: a\r\n\r\n").await;
+        assert_eq!(out.matches("HTTP/1.1 200 OK").count(), 2);
+        let second = out.rfind("HTTP/1.1").unwrap();
+        assert_eq!(parse(&out.as_bytes()[second..]).text(), text);
+    }
+
+    #[tokio::test]
+    async fn http10_clients_get_the_body_unframed_then_a_close() {
+        let app = Router::new().on(Method::Get, "/x", || async {
+            Response::builder().reader(ContentType::Csv, &b"a,b\n"[..])
+        }, ());
+        let out = exchange(app, "GET /x HTTP/1.0\r\n\r\nGET /x HTTP/1.0\r\n\r\n").await;
+        assert_eq!(out, "HTTP/1.1 200 OK\r\ncontent-type: text/csv\r\nconnection: close\r\n\r\na,b\n");
+    }
+
+    #[tokio::test]
+    async fn http10_clients_keep_content_length_for_full_bodies() {
+        let app = Router::new().on(Method::Get, "/x", || async { "ok" }, ());
+        let out = exchange(app, "GET /x HTTP/1.0\r\n\r\n").await;
+        assert!(out.contains("content-length: 2\r\n") && out.ends_with("\r\n\r\nok"), "{out:?}");
+        assert!(!out.contains("connection: close"), "{out:?}");
+    }
+}
@@ -16,14 +16,33 @@
 //!     .on(Method::Post,   "/users",      create_user, ());
 //! ```
 
+use std::any::TypeId;
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use matchit::Router as MatchitRouter;
+use matchit::{InsertError, Router as MatchitRouter};
 
+use crate::body::BodyMode;
+use crate::error::RouteError;
 use crate::handler::{BoxedHandler, Handler};
 use crate::method::Method;
 use crate::middleware::{BoxedMiddleware, IntoMiddlewares, Middleware};
+use crate::request::Request;
+use crate::response::Response;
+use crate::state::StateMap;
+use crate::status::Status;
+
+/// A route's pre-built middleware chain, shared with every request it serves.
+type Chain = Arc<[BoxedMiddleware]>;
+
+/// What a route resolves to.
+type Endpoint = (BoxedHandler, Chain, BodyMode);
+
+/// The state types a handler's extractors need, with their names.
+type Requires = Vec<(TypeId, &'static str)>;
+
+/// One radix tree per method. Shared with the built-in `OPTIONS` handler.
+type Routes = HashMap<Method, MatchitRouter<Endpoint>>;
 
 /// The application router.
 ///
@@ -33,21 +52,172 @@ use crate::middleware::{BoxedMiddleware, IntoMiddlewares, Middleware};
 ///
 /// Each [`Router::on`] call bakes the current global middleware chain plus any
 /// per-route extras into an `Arc<[BoxedMiddleware]>` stored alongside the
-/// handler. Sub-routers can be composed with [`Router::merge`].
+/// handler. Sub-routers can be composed with [`Router::merge`], or mounted
+/// under a path prefix with [`Router::nest`].
 ///
-/// Unmatched routes return `404 Not Found` automatically.
+/// Unmatched routes return `404 Not Found` automatically, or run the handler
+/// registered with [`Router::fallback`]. A path that matches
+/// under other methods returns `405 Method Not Allowed` with an `Allow` header
+/// instead — see [`Router::method_not_allowed`].
+///
+/// `HEAD` needs no route of its own: a `HEAD` request with no explicit `HEAD`
+/// route runs the `GET` handler for the same path, and the server sends the
+/// status and headers — including the `content-length` the body would have
+/// had — without the body.
+///
+/// `OPTIONS` needs none either: a path with routes but no explicit `OPTIONS`
+/// route answers `204 No Content` with the same `Allow` header. That answer
+/// runs through this router's global middleware — register a CORS middleware
+/// with [`Router::middleware`] and it decorates preflight responses too.
 pub struct Router {
-    routes: HashMap<Method, MatchitRouter<(BoxedHandler, Arc<[BoxedMiddleware]>)>>,
-    /// Kept solely for [`merge`][Router::merge] — matchit 0.8 has no iteration API.
-    raw: Vec<(Method, Box<str>, BoxedHandler, Arc<[BoxedMiddleware]>)>,
+    /// Set by [`fallback`][Router::fallback]; answers paths no route matches.
+    fallback: Option<(BoxedHandler, Chain, Requires)>,
+    /// The built-in `OPTIONS` handler and its chain, built once by
+    /// [`finish`][Router::finish] and shared with every request it answers.
+    options: Option<(BoxedHandler, Chain)>,
+    routes: Arc<Routes>,
+    /// Kept for [`merge`][Router::merge], [`nest`][Router::nest] and
+    /// [`RouteError`] reporting — matchit 0.8 has no iteration API.
+    raw: Vec<RawRoute>,
     /// Accumulated by [`middleware`][Router::middleware], consumed at each [`on`][Router::on] call.
     middleware: Vec<BoxedMiddleware>,
+    /// Set by [`method_not_allowed`][Router::method_not_allowed].
+    method_not_allowed: bool,
+    /// Set by [`name`][Router::name].
+    name: Option<Arc<str>>,
+    /// Filled by [`with_state`][Router::with_state], handed to every request.
+    state: Arc<StateMap>,
+}
+
+/// One registered route, as handed to matchit.
+struct RawRoute {
+    body: BodyMode,
+    chain: Chain,
+    handler: BoxedHandler,
+    method: Method,
+    /// Name of the router the route was first registered on.
+    origin: Option<Arc<str>>,
+    path: Box<str>,
+    /// Checked by [`build`][Router::build].
+    requires: Requires,
+}
+
+/// Outcome of [`Router::lookup`].
+pub(crate) enum Lookup {
+    Found(BoxedHandler, Chain, HashMap<String, String>, BodyMode),
+    /// The path exists under other methods. Holds the `Allow` header value.
+    MethodNotAllowed(String),
+    NotFound,
 }
 
 impl Router {
     /// Creates an empty router with no registered routes or middleware.
     pub fn new() -> Self {
-        Self { routes: HashMap::new(), raw: Vec::new(), middleware: Vec::new() }
+        Self {
+            fallback: None,
+            options: None,
+            routes: Arc::default(),
+            raw: Vec::new(),
+            middleware: Vec::new(),
+            method_not_allowed: true,
+            name: None,
+            state: Arc::default(),
+        }
+    }
+
+    /// Share `state` with every handler and middleware.
+    ///
+    /// Database pools, configuration, clients — anything built once at
+    /// startup. Read it back by type with
+    /// [`Request::state`][crate::Request::state] (a `&S`) or
+    /// [`Request::state_arc`][crate::Request::state_arc] (an `Arc<S>` to move
+    /// into a spawned task).
+    ///
+    /// State is keyed by type: call `with_state` once per type, and a second
+    /// value of the same type replaces the first. Lookups happen at request
+    /// time — a handler asking for a type nobody registered gets `None`, not
+    /// a compile error, so keep the two next to each other.
+    ///
+    /// State is application-wide and may be registered at any point in the
+    /// chain. [`merge`][Router::merge] and [`nest`][Router::nest] carry
+    /// `other`'s state over for every type `self` does not already hold.
+    ///
+    /// ```rust,no_run
+    /// use astor::{Method, Request, Response, Router, Status};
+    ///
+    /// struct Config { greeting: String }
+    ///
+    /// async fn hello(req: Request) -> Response {
+    ///     let Some(config) = req.state::<Config>() else {
+    ///         return Response::status(Status::InternalServerError);
+    ///     };
+    ///     Response::text(config.greeting.clone())
+    /// }
+    ///
+    /// let app = Router::new()
+    ///     .with_state(Config { greeting: "hello".into() })
+    ///     .on(Method::Get, "/", hello, ());
+    /// ```
+    pub fn with_state<S: Send + Sync + 'static>(mut self, state: S) -> Self {
+        Arc::make_mut(&mut self.state).insert(state);
+        self
+    }
+
+    /// Names this router in [`RouteError`] messages.
+    ///
+    /// When two merged sub-routers collide, the error — and the panic message
+    /// of [`on`][Router::on], [`merge`][Router::merge] and
+    /// [`nest`][Router::nest] — names the router each route came from. Routes
+    /// keep the name of the router they were registered on through any number
+    /// of merges.
+    ///
+    /// ```rust
+    /// # use astor::{Method, Request, Response, Router};
+    /// # async fn list_users(_: Request) -> Response { Response::text("") }
+    /// # async fn list_admins(_: Request) -> Response { Response::text("") }
+    /// let users = Router::new().name("users").on(Method::Get, "/users", list_users, ());
+    /// let admin = Router::new().name("admin").on(Method::Get, "/users", list_admins, ());
+    ///
+    /// let err = Router::new().merge(users).try_merge(admin).err().unwrap();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "route `GET /users` (router `admin`) conflicts with `GET /users` (router `users`)",
+    /// );
+    /// ```
+    pub fn name(mut self, name: &str) -> Self {
+        let name: Arc<str> = name.into();
+        for route in &mut self.raw {
+            route.origin.get_or_insert_with(|| Arc::clone(&name));
+        }
+        self.name = Some(name);
+        self
+    }
+
+    /// Whether a path registered under other methods answers
+    /// `405 Method Not Allowed` instead of `404 Not Found`. On by default.
+    ///
+    /// The `405` carries an `Allow` header listing every method registered for
+    /// the path, in alphabetical order (RFC 9110 §15.5.6). Pass `false` to keep
+    /// answering `404` for every miss.
+    ///
+    /// The setting belongs to the router passed to
+    /// [`Server::serve`][crate::Server::serve] — [`merge`][Router::merge] and
+    /// [`nest`][Router::nest] keep `self`'s setting and ignore `other`'s.
+    ///
+    /// ```rust,no_run
+    /// # use astor::{Method, Request, Response, Router};
+    /// # async fn list_users(_: Request) -> Response { Response::text("") }
+    /// // DELETE /users → 405, allow: GET
+    /// let app = Router::new().on(Method::Get, "/users", list_users, ());
+    ///
+    /// // DELETE /users → 404
+    /// let app = Router::new()
+    ///     .method_not_allowed(false)
+    ///     .on(Method::Get, "/users", list_users, ());
+    /// ```
+    pub fn method_not_allowed(mut self, enabled: bool) -> Self {
+        self.method_not_allowed = enabled;
+        self
     }
 
     /// Append a global middleware that applies to every route registered on
@@ -63,6 +233,7 @@ impl Router {
     /// ```
     pub fn middleware(mut self, mw: impl Middleware) -> Self {
         self.middleware.push(mw.into_boxed_middleware());
+        self.options = None;
         self
     }
 
@@ -87,18 +258,168 @@ impl Router {
     /// # Panics
     ///
     /// Panics at startup if two routes with the same method and path are
-    /// registered, or if the path is not a valid matchit pattern.
-    pub fn on(
+    /// registered, or if the path is not a valid matchit pattern. See
+    /// [`try_on`][Router::try_on] for the non-panicking version.
+    pub fn on<T>(
         self,
         method: Method,
         path: &str,
-        handler: impl Handler,
+        handler: impl Handler<T>,
         extra: impl IntoMiddlewares,
     ) -> Self {
+        self.try_on(method, path, handler, extra).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// [`on`][Router::on], returning a [`RouteError`] instead of panicking.
+    ///
+    /// ```rust
+    /// # use astor::{Method, Request, Response, RouteError, Router};
+    /// # async fn get_user(_: Request) -> Response { Response::text("") }
+    /// let result = Router::new()
+    ///     .on(Method::Get, "/users/{id}", get_user, ())
+    ///     .try_on(Method::Get, "/users/{name}", get_user, ());
+    ///
+    /// assert!(matches!(result, Err(RouteError::Conflict { .. })));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// [`RouteError::Conflict`] if the route overlaps one already registered
+    /// for `method`; [`RouteError::InvalidPath`] if `path` is not a valid
+    /// matchit pattern.
+    pub fn try_on<T>(
+        self,
+        method: Method,
+        path: &str,
+        handler: impl Handler<T>,
+        extra: impl IntoMiddlewares,
+    ) -> Result<Self, RouteError> {
+        self.route(method, path, handler, extra, BodyMode::Buffered)
+    }
+
+    /// Like [`on`][Router::on], but the request body is not read before the
+    /// handler runs: the handler reads it off the connection as it arrives,
+    /// through a [`BodyStream`][crate::BodyStream] argument or
+    /// [`Request::into_body_stream`][crate::Request::into_body_stream].
+    ///
+    /// For uploads too large to hold in memory. [`Request::body`][crate::Request::body]
+    /// is empty on these routes, and so are the extractors built on it.
+    ///
+    /// ```rust,no_run
+    /// # use astor::{BodyStream, Method, Router, Status};
+    /// # async fn upload(_body: BodyStream) -> Status { Status::Created }
+    /// Router::new().on_stream(Method::Put, "/files/{name}", upload, ());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// As [`on`][Router::on]. See [`try_on_stream`][Router::try_on_stream]
+    /// for the non-panicking version.
+    pub fn on_stream<T>(
+        self,
+        method: Method,
+        path: &str,
+        handler: impl Handler<T>,
+        extra: impl IntoMiddlewares,
+    ) -> Self {
+        self.try_on_stream(method, path, handler, extra).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// [`on_stream`][Router::on_stream], returning a [`RouteError`] instead
+    /// of panicking.
+    ///
+    /// # Errors
+    ///
+    /// As [`try_on`][Router::try_on].
+    pub fn try_on_stream<T>(
+        self,
+        method: Method,
+        path: &str,
+        handler: impl Handler<T>,
+        extra: impl IntoMiddlewares,
+    ) -> Result<Self, RouteError> {
+        self.route(method, path, handler, extra, BodyMode::Streamed)
+    }
+
+    fn route<T>(
+        mut self,
+        method: Method,
+        path: &str,
+        handler: impl Handler<T>,
+        extra: impl IntoMiddlewares,
+        body: BodyMode,
+    ) -> Result<Self, RouteError> {
+        let mut chain = self.middleware.clone();
+        chain.extend(extra.into_middlewares());
+        self.insert(RawRoute {
+            body,
+            chain: chain.into(),
+            method,
+            origin: self.name.clone(),
+            path: path.into(),
+            requires: handler.required_state(),
+            handler: handler.into_boxed_handler(),
+        })?;
+        Ok(self)
+    }
+
+    /// Register the handler for requests no route matches, in place of the
+    /// built-in `404 Not Found`.
+    ///
+    /// Like [`on`][Router::on], the handler runs behind the global middleware
+    /// registered so far plus `extra` — so request-id and access-log
+    /// middleware see unmatched requests too. Register it last to put every
+    /// global middleware in front of it.
+    ///
+    /// Paths that exist under other methods still answer `405` (see
+    /// [`method_not_allowed`][Router::method_not_allowed]); the fallback only
+    /// runs for paths no route matches at all. Path parameters are empty.
+    ///
+    /// ```rust,no_run
+    /// # use astor::{Method, Next, Request, Response, Router, Status};
+    /// # async fn list_users(_: Request) -> Response { Response::text("") }
+    /// # async fn request_id(req: Request, next: Next) -> Response { next.call(req).await }
+    /// async fn not_found(_req: Request) -> Response {
+    ///     Response::builder()
+    ///         .status(Status::NotFound)
+    ///         .json(br#"{"error":"not found"}"#.to_vec())
+    /// }
+    ///
+    /// let app = Router::new()
+    ///     .middleware(request_id)
+    ///     .on(Method::Get, "/users", list_users, ())
+    ///     .fallback(not_found, ());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a fallback is already registered — an application has one.
+    /// See [`try_fallback`][Router::try_fallback] for the non-panicking
+    /// version.
+    pub fn fallback<T>(self, handler: impl Handler<T>, extra: impl IntoMiddlewares) -> Self {
+        self.try_fallback(handler, extra).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// [`fallback`][Router::fallback], returning a [`RouteError`] instead of
+    /// panicking.
+    ///
+    /// # Errors
+    ///
+    /// [`RouteError::DuplicateFallback`] if a fallback is already registered.
+    pub fn try_fallback<T>(
+        mut self,
+        handler: impl Handler<T>,
+        extra: impl IntoMiddlewares,
+    ) -> Result<Self, RouteError> {
+        if self.fallback.is_some() {
+            let origin = self.name.as_deref().map(str::to_owned);
+            return Err(RouteError::DuplicateFallback { origin });
+        }
         let mut chain = self.middleware.clone();
         chain.extend(extra.into_middlewares());
-        let chain: Arc<[BoxedMiddleware]> = chain.into();
-        self.add_route(method, path, handler.into_boxed_handler(), chain)
+        let requires = handler.required_state();
+        self.fallback = Some((handler.into_boxed_handler(), chain.into(), requires));
+        Ok(self)
     }
 
     /// Merge all routes from `other` into this router.
@@ -107,6 +428,10 @@ impl Router {
     /// global middleware does **not** retroactively apply to merged routes.
     /// Each sub-router owns its own chain.
     ///
+    /// A [`fallback`][Router::fallback] registered on `other` carries over,
+    /// chain included. The merged application still has exactly one: merging
+    /// two routers that both have a fallback panics.
+    ///
     /// ```rust,no_run
     /// # use astor::{Method, Next, Request, Response, Router, Status};
     /// # async fn health(_: Request) -> Response { Response::text("ok") }
@@ -119,45 +444,264 @@ impl Router {
     ///
     /// let app = Router::new().merge(public).merge(users);
     /// ```
-    pub fn merge(mut self, other: Router) -> Self {
-        for (method, path, handler, chain) in other.raw {
-            self = self.add_route(method, &path, handler, chain);
+    ///
+    /// # Panics
+    ///
+    /// Panics if a route in `other` conflicts with one in `self`, or if both
+    /// routers have a fallback. See [`try_merge`][Router::try_merge] for the
+    /// non-panicking version.
+    pub fn merge(self, other: Router) -> Self {
+        self.nest("/", other)
+    }
+
+    /// [`merge`][Router::merge], returning a [`RouteError`] instead of
+    /// panicking.
+    ///
+    /// # Errors
+    ///
+    /// [`RouteError::Conflict`] if a route in `other` overlaps one in `self`;
+    /// [`RouteError::DuplicateFallback`] if both routers have a fallback.
+    pub fn try_merge(self, other: Router) -> Result<Self, RouteError> {
+        self.try_nest("/", other)
+    }
+
+    /// Mount all routes from `other` under `prefix`.
+    ///
+    /// [`merge`][Router::merge] with a path prefix: sub-routers declare their
+    /// routes relative to wherever they are mounted. Middleware chains and
+    /// fallbacks carry over exactly as with `merge`.
+    ///
+    /// Paths join with a single `/` — a trailing `/` on `prefix` is ignored.
+    /// `other`'s `/` route is mounted at `prefix` itself; any other trailing
+    /// `/` is kept, so `/users/` stays distinct from `/users`. `prefix` may
+    /// contain `{param}` segments. Nesting composes: a router that already
+    /// nests others can itself be nested.
+    ///
+    /// ```rust,no_run
+    /// # use astor::{Method, Request, Response, Router};
+    /// # async fn index(_: Request) -> Response { Response::text("") }
+    /// # async fn list_users(_: Request) -> Response { Response::text("") }
+    /// # async fn get_user(_: Request) -> Response { Response::text("") }
+    /// let users = Router::new()
+    ///     .on(Method::Get, "/",     list_users, ())  // GET /api/v1/users
+    ///     .on(Method::Get, "/{id}", get_user,   ()); // GET /api/v1/users/{id}
+    ///
+    /// let v1 = Router::new()
+    ///     .on(Method::Get, "/", index, ())           // GET /api/v1
+    ///     .nest("/users", users);
+    ///
+    /// let app = Router::new().nest("/api/v1", v1);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix` does not start with `/`, if a prefixed route
+    /// conflicts with one in `self`, or if both routers have a fallback. See
+    /// [`try_nest`][Router::try_nest] for the non-panicking version.
+    pub fn nest(self, prefix: &str, other: Router) -> Self {
+        self.try_nest(prefix, other).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// [`nest`][Router::nest], returning a [`RouteError`] instead of
+    /// panicking.
+    ///
+    /// # Errors
+    ///
+    /// [`RouteError::InvalidPrefix`] if `prefix` does not start with `/`,
+    /// plus everything [`try_merge`][Router::try_merge] returns.
+    pub fn try_nest(mut self, prefix: &str, other: Router) -> Result<Self, RouteError> {
+        if !prefix.starts_with('/') {
+            return Err(RouteError::InvalidPrefix { prefix: prefix.to_owned() });
         }
-        self
+        let prefix = prefix.trim_end_matches('/');
+        Arc::make_mut(&mut self.state).merge(&other.state);
+        if let Some(fallback) = other.fallback {
+            if self.fallback.is_some() {
+                let origin = other.name.as_deref().map(str::to_owned);
+                return Err(RouteError::DuplicateFallback { origin });
+            }
+            self.fallback = Some(fallback);
+        }
+        for route in other.raw {
+            let path = match (prefix, &*route.path) {
+                ("", path) => path.into(),
+                (prefix, "/") => prefix.into(),
+                (prefix, path) => format!("{prefix}{path}").into(),
+            };
+            self.insert(RawRoute { path, ..route })?;
+        }
+        Ok(self)
     }
 
-    fn add_route(
-        mut self,
-        method: Method,
-        path: &str,
-        handler: BoxedHandler,
-        chain: Arc<[BoxedMiddleware]>,
-    ) -> Self {
-        self.routes
-            .entry(method)
+    fn insert(&mut self, route: RawRoute) -> Result<(), RouteError> {
+        self.options = None;
+        let inserted = Arc::make_mut(&mut self.routes)
+            .entry(route.method)
             .or_default()
-            .insert(path, (Arc::clone(&handler), Arc::clone(&chain)))
-            .unwrap_or_else(|e| panic!("invalid route `{path}`: {e}"));
-        self.raw.push((method, path.into(), handler, chain));
-        self
+            .insert(&*route.path, (Arc::clone(&route.handler), Arc::clone(&route.chain), route.body));
+        if let Err(e) = inserted {
+            return Err(self.route_error(&route, e));
+        }
+        self.raw.push(route);
+        Ok(())
     }
 
-    pub(crate) fn lookup(
-        &self,
-        method: Method,
-        path: &str,
-    ) -> Option<(BoxedHandler, Arc<[BoxedMiddleware]>, HashMap<String, String>)> {
-        let tree = self.routes.get(&method)?;
-        let matched = tree.at(path).ok()?;
-        let handler = Arc::clone(&matched.value.0);
-        let chain = Arc::clone(&matched.value.1);
+    fn route_error(&self, route: &RawRoute, e: InsertError) -> RouteError {
+        let method = route.method;
+        let origin = route.origin.as_deref().map(str::to_owned);
+        let path = route.path.to_string();
+        match e {
+            InsertError::Conflict { with } => {
+                let existing_origin = self.raw
+                    .iter()
+                    .find(|r| r.method == method && *r.path == with)
+                    .and_then(|r| r.origin.as_deref())
+                    .map(str::to_owned);
+                RouteError::Conflict { existing: with, existing_origin, method, origin, path }
+            }
+            e => RouteError::InvalidPath { method, origin, path, reason: e.to_string() },
+        }
+    }
+
+    pub(crate) fn lookup(&self, method: Method, path: &str) -> Lookup {
+        let matched = match self.routes.get(&method).and_then(|tree| tree.at(path).ok()) {
+            // RFC 9110 §9.3.2 — HEAD is GET without the body. An explicit HEAD
+            // route wins; otherwise the GET handler answers and the server
+            // drops the body on the way out.
+            None if method == Method::Head => {
+                self.routes.get(&Method::Get).and_then(|tree| tree.at(path).ok())
+            }
+            matched => matched,
+        };
+        let Some(matched) = matched else {
+            return self.miss(method, path);
+        };
+        let (handler, chain, body) = matched.value;
+        let (handler, chain) = (Arc::clone(handler), Arc::clone(chain));
         let params = matched
             .params
             .iter()
             .map(|(k, v)| (k.to_owned(), v.to_owned()))
             .collect();
-        Some((handler, chain, params))
+        Lookup::Found(handler, chain, params, *body)
+    }
+
+    /// Checks the finished router before it is served.
+    ///
+    /// Registration errors already surface from each `try_` call. What only
+    /// the whole router can answer is whether every handler's state is
+    /// there: a [`State`][crate::extract::State] or
+    /// [`CookieJar`][crate::CookieJar] argument whose type no router
+    /// registered with [`with_state`][Router::with_state] would otherwise
+    /// answer `500` on the first request that reaches it. `build` reports it
+    /// at startup instead, after every [`merge`][Router::merge] and
+    /// [`nest`][Router::nest] has contributed its state.
+    ///
+    /// ```rust
+    /// use astor::extract::State;
+    /// use astor::{Method, RouteError, Router};
+    ///
+    /// struct Config;
+    /// async fn hello(_: State<Config>) -> &'static str { "hello" }
+    ///
+    /// let result = Router::new().on(Method::Get, "/", hello, ()).build();
+    /// assert!(matches!(result, Err(RouteError::MissingState { .. })));
+    ///
+    /// let app = Router::new()
+    ///     .with_state(Config)
+    ///     .on(Method::Get, "/", hello, ())
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// [`RouteError::MissingState`] naming the first route — or the
+    /// fallback — whose state is not registered.
+    pub fn build(mut self) -> Result<Self, RouteError> {
+        let routes = self.raw.iter().map(|r| {
+            (format!("{} {}", r.method, r.path), r.origin.as_deref(), &r.requires)
+        });
+        // A merged fallback does not remember its router; report none.
+        let fallback = self.fallback.iter().map(|(.., requires)| ("fallback".to_owned(), None, requires));
+        for (handler, origin, requires) in routes.chain(fallback) {
+            if let Some((_, state)) = requires.iter().find(|(id, _)| !self.state.contains(*id)) {
+                let origin = origin.map(str::to_owned);
+                return Err(RouteError::MissingState { handler, origin, state: (*state).to_owned() });
+            }
+        }
+        self.finish();
+        Ok(self)
     }
+
+    /// Builds the parts shared by every request that misses into the
+    /// built-in `OPTIONS` handler. Called once the router is handed to the
+    /// server; registering anything afterwards discards them again.
+    pub(crate) fn finish(&mut self) {
+        if self.options.is_none() {
+            let chain: Chain = self.middleware.clone().into();
+            self.options = Some((options_handler(Arc::clone(&self.routes)), chain));
+        }
+    }
+
+    pub(crate) fn state(&self) -> Arc<StateMap> {
+        Arc::clone(&self.state)
+    }
+
+    /// No route for `method + path` — answer `OPTIONS`, `405`, the fallback,
+    /// or `404`.
+    fn miss(&self, method: Method, path: &str) -> Lookup {
+        match (allow(&self.routes, path), &self.options) {
+            (Some(_), Some((handler, chain))) if method == Method::Options => {
+                Lookup::Found(Arc::clone(handler), Arc::clone(chain), HashMap::new(), BodyMode::Buffered)
+            }
+            (Some(allow), _) if self.method_not_allowed => Lookup::MethodNotAllowed(allow),
+            _ => match &self.fallback {
+                Some((handler, chain, _)) => {
+                    Lookup::Found(Arc::clone(handler), Arc::clone(chain), HashMap::new(), BodyMode::Buffered)
+                }
+                None => Lookup::NotFound,
+            },
+        }
+    }
+}
+
+/// Every method astor answers for `path`, as an `Allow` header value —
+/// registered methods plus the implied `HEAD` and `OPTIONS`. `path` `*`
+/// (server-wide `OPTIONS *`) matches every route. `None` if no route
+/// matches. Only runs on a miss — never on the hot path.
+fn allow(routes: &Routes, path: &str) -> Option<String> {
+    let mut methods: Vec<&str> = routes
+        .iter()
+        .filter(|(_, tree)| path == "*" || tree.at(path).is_ok())
+        .map(|(method, _)| method.as_str())
+        .collect();
+    if methods.is_empty() {
+        return None;
+    }
+    if methods.contains(&"GET") && !methods.contains(&"HEAD") {
+        methods.push("HEAD");
+    }
+    if !methods.contains(&"OPTIONS") {
+        methods.push("OPTIONS");
+    }
+    methods.sort_unstable();
+    Some(methods.join(", "))
+}
+
+/// The built-in `OPTIONS` handler — `204 No Content` with `allow` for the
+/// request path.
+fn options_handler(routes: Arc<Routes>) -> BoxedHandler {
+    let handler = move |req: Request| {
+        let allow = allow(&routes, req.path()).unwrap_or_default();
+        async move {
+            Response::builder()
+                .status(Status::NoContent)
+                .header("allow", &allow)
+                .no_body()
+        }
+    };
+    handler.into_boxed_handler()
 }
 
 impl Default for Router {
@@ -165,3 +709,294 @@ impl Default for Router {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::State;
+    use crate::middleware::Next;
+    use crate::response::ContentType;
+    use crate::testing::{exchange, get, request};
+
+    async fn ok() -> &'static str {
+        "ok"
+    }
+
+    fn users() -> Router {
+        Router::new()
+            .on(Method::Delete, "/users/me", ok, ())
+            .on(Method::Get, "/users/{id}", ok, ())
+            .on(Method::Post, "/users/{id}", ok, ())
+    }
+
+    #[tokio::test]
+    async fn answers_other_methods_with_405_and_allow() {
+        let reply = request(users(), "PUT", "/users/5").await;
+        assert_eq!(reply.status, 405);
+        assert_eq!(reply.header("allow"), Some("GET, HEAD, OPTIONS, POST"));
+        assert!(reply.body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn allow_lists_the_methods_matching_the_concrete_path() {
+        let reply = request(users(), "PUT", "/users/me").await;
+        assert_eq!(reply.header("allow"), Some("DELETE, GET, HEAD, OPTIONS, POST"));
+
+        let post_only = Router::new().on(Method::Post, "/form", ok, ());
+        let reply = request(post_only, "HEAD", "/form").await;
+        assert_eq!((reply.status, reply.header("allow")), (405, Some("OPTIONS, POST")));
+    }
+
+    #[tokio::test]
+    async fn keeps_the_connection_after_a_405() {
+        let raw = "DELETE /users/5 HTTP/1.1\r\n\r\nGET /users/5 HTTP/1.1\r\n\r\n";
+        let out = exchange(users(), raw).await;
+        assert!(out.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(!out.contains("connection: close"));
+        assert!(out.ends_with("\r\n\r\nok"), "{out}");
+    }
+
+    #[tokio::test]
+    async fn answers_404_for_unknown_paths() {
+        let reply = get(users(), "/nothing").await;
+        assert_eq!((reply.status, reply.header("allow")), (404, None));
+    }
+
+    #[tokio::test]
+    async fn head_runs_the_get_route_without_a_body() {
+        let out = exchange(users(), "HEAD /users/5 HTTP/1.1\r\n\r\n").await;
+        assert!(out.starts_with("HTTP/1.1 200 OK\r\n"), "{out}");
+        assert!(out.contains("content-length: 2\r\n"));
+        assert!(out.ends_with("\r\n\r\n"), "{out}");
+    }
+
+    #[tokio::test]
+    async fn head_keeps_pipelined_responses_framed() {
+        let raw = "HEAD /users/5 HTTP/1.1\r\n\r\nGET /users/5 HTTP/1.1\r\n\r\n";
+        let out = exchange(users(), raw).await;
+        let (first, second) = out.split_once("\r\n\r\n").unwrap();
+        assert!(first.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(second.starts_with("HTTP/1.1 200 OK\r\n"), "{out}");
+        assert!(second.ends_with("\r\n\r\nok"), "{out}");
+    }
+
+    #[tokio::test]
+    async fn an_explicit_head_route_wins() {
+        async fn head() -> Response {
+            Response::builder().header("x-route", "head").text("")
+        }
+        let router = users().on(Method::Head, "/users/{id}", head, ());
+        let reply = request(router, "HEAD", "/users/5").await;
+        assert_eq!((reply.status, reply.header("x-route")), (200, Some("head")));
+    }
+
+    #[tokio::test]
+    async fn head_never_polls_a_streamed_body() {
+        async fn stream() -> Response {
+            let (tx, rx) = tokio::sync::mpsc::channel(1);
+            tx.try_send(b"never sent".to_vec()).unwrap();
+            std::mem::forget(tx);
+            Response::builder().channel(ContentType::Text, rx)
+        }
+        let router = Router::new().on(Method::Get, "/stream", stream, ());
+        let out = exchange(router, "HEAD /stream HTTP/1.1\r\n\r\n").await;
+        assert!(out.contains("transfer-encoding: chunked\r\n"));
+        assert!(out.ends_with("\r\n\r\n"), "{out}");
+    }
+
+    #[tokio::test]
+    async fn options_answers_204_with_allow() {
+        let reply = request(users(), "OPTIONS", "/users/me").await;
+        assert_eq!(reply.status, 204);
+        assert_eq!(reply.header("allow"), Some("DELETE, GET, HEAD, OPTIONS, POST"));
+        assert_eq!(reply.header("content-length"), None);
+
+        let reply = request(users(), "OPTIONS", "*").await;
+        assert_eq!((reply.status, reply.header("allow")), (204, Some("DELETE, GET, HEAD, OPTIONS, POST")));
+
+        let reply = request(users(), "OPTIONS", "/nothing").await;
+        assert_eq!(reply.status, 404);
+    }
+
+    #[tokio::test]
+    async fn an_explicit_options_route_wins() {
+        async fn preflight() -> Status {
+            Status::Ok
+        }
+        let router = users().on(Method::Options, "/users/{id}", preflight, ());
+        assert_eq!(request(router, "OPTIONS", "/users/5").await.status, 200);
+    }
+
+    #[tokio::test]
+    async fn options_runs_through_global_middleware() {
+        async fn cors(req: Request, next: Next) -> Response {
+            let mut res = next.call(req).await;
+            res.headers.push(("access-control-allow-origin".into(), "*".into()));
+            res
+        }
+        let router = Router::new().on(Method::Get, "/a", ok, ()).middleware(cors);
+        let reply = request(router, "OPTIONS", "/a").await;
+        assert_eq!(reply.header("access-control-allow-origin"), Some("*"));
+    }
+
+    #[test]
+    fn options_endpoint_is_built_once_and_shared() {
+        let mut router = users();
+        router.finish();
+        let shared = |router: &Router| match router.lookup(Method::Options, "/users/5") {
+            Lookup::Found(handler, chain, ..) => (handler, chain),
+            _ => panic!("OPTIONS did not resolve"),
+        };
+        let (h1, c1) = shared(&router);
+        let (h2, c2) = shared(&router);
+        assert!(Arc::ptr_eq(&h1, &h2) && Arc::ptr_eq(&c1, &c2));
+
+        async fn noop(req: Request, next: Next) -> Response {
+            next.call(req).await
+        }
+        let mut router = router.middleware(noop);
+        router.finish();
+        let (h3, c3) = shared(&router);
+        assert!(!Arc::ptr_eq(&h1, &h3));
+        assert_eq!(c3.len(), 1);
+    }
+
+    async fn not_found(req: Request) -> Response {
+        Response::builder()
+            .status(Status::NotFound)
+            .json(format!(r#"{{"missing":"{}"}}"#, req.path()).into_bytes())
+    }
+
+    async fn tag(req: Request, next: Next) -> Response {
+        let mut res = next.call(req).await;
+        res.headers.push(("x-tag".into(), "global".into()));
+        res
+    }
+
+    #[tokio::test]
+    async fn fallback_answers_unmatched_paths_behind_global_middleware() {
+        let router = users().middleware(tag).fallback(not_found, ());
+        let reply = get(router, "/nothing").await;
+        assert_eq!(reply.status, 404);
+        assert_eq!(reply.header("content-type"), Some("application/json"));
+        assert_eq!(reply.header("x-tag"), Some("global"));
+        assert_eq!(reply.text(), r#"{"missing":"/nothing"}"#);
+    }
+
+    #[tokio::test]
+    async fn fallback_leaves_405_alone() {
+        let router = users().fallback(not_found, ());
+        assert_eq!(request(router, "PUT", "/users/5").await.status, 405);
+
+        let router = users().method_not_allowed(false).fallback(not_found, ());
+        assert_eq!(request(router, "PUT", "/users/5").await.text(), r#"{"missing":"/users/5"}"#);
+    }
+
+    #[tokio::test]
+    async fn a_merged_fallback_carries_over_with_its_chain() {
+        let api = Router::new().middleware(tag).fallback(not_found, ());
+        let reply = get(users().merge(api), "/nothing").await;
+        assert_eq!((reply.status, reply.header("x-tag")), (404, Some("global")));
+    }
+
+    #[test]
+    fn merging_two_fallbacks_is_an_error() {
+        let a = Router::new().fallback(not_found, ());
+        let b = Router::new().name("b").fallback(not_found, ());
+        let err = a.try_merge(b).err().unwrap();
+        assert!(matches!(err, RouteError::DuplicateFallback { origin: Some(ref o) } if o == "b"));
+    }
+
+    async fn describe(req: Request) -> String {
+        format!("{} v={} id={}", req.path(), req.param("v").unwrap_or("-"), req.param("id").unwrap_or("-"))
+    }
+
+    fn nested() -> Router {
+        let users = Router::new()
+            .on(Method::Get, "/", describe, ())
+            .on(Method::Get, "/{id}", describe, ())
+            .on(Method::Get, "/{id}/", describe, ());
+        let v1 = Router::new().on(Method::Get, "/", describe, ()).nest("/users/", users);
+        Router::new().nest("/api/{v}", v1)
+    }
+
+    #[tokio::test]
+    async fn nest_mounts_routes_under_a_prefix() {
+        assert_eq!(get(nested(), "/api/1").await.text(), "/api/1 v=1 id=-");
+        assert_eq!(get(nested(), "/api/1/users").await.text(), "/api/1/users v=1 id=-");
+        assert_eq!(get(nested(), "/api/2/users/7").await.text(), "/api/2/users/7 v=2 id=7");
+        assert_eq!(get(nested(), "/api/2/users/7/").await.text(), "/api/2/users/7/ v=2 id=7");
+        assert_eq!(get(nested(), "/api/1/").await.status, 404);
+        assert_eq!(get(nested(), "/users").await.status, 404);
+    }
+
+    #[tokio::test]
+    async fn nest_keeps_each_chain_and_state() {
+        let admin = Router::new()
+            .with_state(7u32)
+            .middleware(tag)
+            .on(Method::Get, "/", |req: Request| async move { req.state::<u32>().unwrap().to_string() }, ());
+        let router = users().nest("/admin", admin);
+        let reply = get(router, "/admin").await;
+        assert_eq!((reply.text(), reply.header("x-tag")), ("7", Some("global")));
+
+        // `self`'s global middleware does not reach nested routes.
+        let plain = Router::new().on(Method::Get, "/", ok, ());
+        let reply = get(users().middleware(tag).nest("/admin", plain), "/admin").await;
+        assert_eq!(reply.header("x-tag"), None);
+    }
+
+    #[test]
+    fn nest_rejects_bad_prefixes_and_conflicts() {
+        let err = Router::new().try_nest("api", users()).err().unwrap();
+        assert!(matches!(err, RouteError::InvalidPrefix { ref prefix } if prefix == "api"));
+
+        let inner = Router::new().name("inner").on(Method::Get, "/{name}", ok, ());
+        let err = Router::new().on(Method::Get, "/users/{id}", ok, ()).try_nest("/users", inner).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "route `GET /users/{name}` (router `inner`) conflicts with `GET /users/{id}`",
+        );
+    }
+
+    #[test]
+    fn a_second_fallback_is_an_error() {
+        let router = Router::new().name("app").fallback(not_found, ());
+        let err = router.try_fallback(not_found, ()).err().unwrap();
+        assert_eq!(err.to_string(), "fallback (router `app`) rejected: a fallback is already registered");
+    }
+
+    struct Config(&'static str);
+
+    async fn greet(State(config): State<Config>) -> &'static str {
+        config.0
+    }
+
+    #[test]
+    fn build_reports_missing_state() {
+        let err = Router::new().name("api").on(Method::Get, "/hi", greet, ()).build().err().unwrap();
+        let config = std::any::type_name::<Config>();
+        assert_eq!(err.to_string(), format!("`GET /hi` (router `api`) needs state `{config}`, which is not registered"));
+
+        let err = Router::new().fallback(greet, ()).build().err().unwrap();
+        assert!(matches!(err, RouteError::MissingState { ref handler, .. } if handler == "fallback"));
+
+        let jar = |_: crate::CookieJar| async { "" };
+        let err = Router::new().on(Method::Get, "/", jar, ()).build().err().unwrap();
+        assert!(matches!(err, RouteError::MissingState { ref state, .. } if state == "astor::CookieKey"));
+    }
+
+    #[tokio::test]
+    async fn build_accepts_state_from_any_merged_router() {
+        let api = Router::new().on(Method::Get, "/hi", greet, ());
+        let config = Router::new().with_state(Config("hello"));
+        let router = Router::new().nest("/api", api).merge(config).build().unwrap();
+        assert_eq!(get(router, "/api/hi").await.text(), "hello");
+    }
+
+    #[tokio::test]
+    async fn method_not_allowed_false_answers_404() {
+        let reply = request(users().method_not_allowed(false), "PUT", "/users/5").await;
+        assert_eq!((reply.status, reply.header("allow")), (404, None));
+    }
+}
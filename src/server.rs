@@ -14,10 +14,18 @@
 //!
 //! ## `proxy_buffering on`
 //!
-//! astor reads `Content-Length`-framed bodies only. `proxy_buffering on`
+//! By default astor reads `Content-Length`-framed bodies only. `proxy_buffering on`
 //! (the nginx default) ensures the full body arrives with a `Content-Length`
-//! header. Set it to `off` and you get chunked bodies astor cannot parse.
-//! Don't do it.
+//! header. Set it to `off` and you get chunked bodies, which astor answers
+//! with `501 Not Implemented`. Don't do it.
+//!
+//! Unless you enable the `chunked` feature. Then astor decodes
+//! `Transfer-Encoding: chunked` bodies too — chunk extensions are ignored,
+//! trailers are discarded — up to `Server::max_chunked_body` decoded bytes.
+//! A request carrying both `Transfer-Encoding` and `Content-Length` is
+//! rejected and the connection closed: that combination is a request-smuggling
+//! vector, not a framing choice (RFC 9112 §6.3). So is one whose
+//! `Content-Length` fields disagree.
 //!
 //! ## Method filtering
 //!
@@ -34,6 +42,22 @@
 //! case-sensitive per RFC 9110 §9.1 and assumes nginx already enforces
 //! uppercase — it does not normalise case itself.
 //!
+//! # Malformed requests
+//!
+//! Anything that slips past nginx anyway is answered, not dropped: a broken
+//! request line or header gets `400 Bad Request`, an unknown method
+//! `501 Not Implemented`, anything but `HTTP/1.1` or `HTTP/1.0`
+//! `505 HTTP Version Not Supported`. The response carries
+//! `connection: close` and astor closes the socket after it — see
+//! [`ParseError`][crate::ParseError] for the full list.
+//!
+//! # Unix domain sockets
+//!
+//! When nginx runs next to astor — same pod, same host — skip TCP:
+//! [`Server::bind_unix`] listens on a socket file and nginx connects with
+//! `proxy_pass http://unix:/run/app.sock;`. No port to allocate, no loopback
+//! TCP stack in between. Everything else behaves the same.
+//!
 //! # Keep-alive — nginx's business, not ours
 //!
 //! nginx reuses connections to astor. astor loops until nginx closes them (EOF).
@@ -42,27 +66,39 @@
 //! # Graceful shutdown
 //!
 //! On SIGTERM / Ctrl-C: accept loop stops, in-flight tasks drain, then exit.
+//! Server-sent event streams end at once; every connection closes after the
+//! response it is writing. WebSockets waiting for a message are closed with
+//! code `1001`. Any other upgraded connection counts as in flight until its
+//! callback returns — watch [`Upgraded::shutdown`][crate::Upgraded::shutdown].
 //! Set `terminationGracePeriodSeconds` longer than your slowest request or k8s
 //! SIGKILLs the pod before drain finishes. That is not graceful shutdown.
 
 use std::net::SocketAddr;
-use std::sync::Arc;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
-use tokio::net::{TcpListener, TcpStream};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWrite, BufReader};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::sync::watch;
 
-use crate::error::Error;
+use crate::body::{BodyMode, BodyStream, Reader, Slot};
+use crate::error::{Error, ParseError};
 use crate::method::Method;
 use crate::middleware::Next;
 use crate::request::Request;
-use crate::response::Response;
-use crate::router::Router;
-use crate::status::Status; // used in dispatch fallback (404)
+use crate::response::{Response, Version};
+use crate::router::{Lookup, Router};
+use crate::status::Status; // used in dispatch fallbacks (404, 405)
+use crate::upgrade::{Upgraded, Writer};
 
 /// The HTTP server.
 ///
-/// Binds a TCP port, accepts connections, and dispatches requests through a
-/// [`Router`]. Handles graceful shutdown automatically — no setup required.
+/// Binds a TCP port or a Unix socket, accepts connections, and dispatches
+/// requests through a [`Router`]. Handles graceful shutdown automatically —
+/// no setup required.
 ///
 /// ```rust,no_run
 /// use astor::{Method, Request, Response, Router, Server};
@@ -70,13 +106,36 @@ use crate::status::Status; // used in dispatch fallback (404)
 /// #[tokio::main]
 /// async fn main() {
 ///     let app = Router::new()
-///         .on(Method::Get, "/", |_req: Request| async { Response::text("ok") });
+///         .on(Method::Get, "/", |_req: Request| async { Response::text("ok") }, ());
 ///
 ///     Server::bind("0.0.0.0:3000").serve(app).await.unwrap();
 /// }
 /// ```
 pub struct Server {
-    addr: SocketAddr,
+    config: Config,
+    listen: Listen,
+}
+
+/// Where [`Server::serve`] listens.
+enum Listen {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(UnixSocket),
+}
+
+/// A Unix socket file and what to do with it.
+#[cfg(unix)]
+struct UnixSocket {
+    cleanup: bool,
+    mode: Option<u32>,
+    path: PathBuf,
+}
+
+/// Per-connection settings, copied out of [`Server`] for every accepted stream.
+#[derive(Clone, Copy)]
+struct Config {
+    #[cfg(feature = "chunked")]
+    max_chunked_body: usize,
 }
 
 impl Server {
@@ -93,16 +152,103 @@ impl Server {
     /// Panics if `addr` is not a valid socket address string.
     pub fn bind(addr: &str) -> Self {
         let addr: SocketAddr = addr.parse().expect("invalid socket address");
-        Self { addr }
+        Self { config: Config::new(), listen: Listen::Tcp(addr) }
     }
 
-    /// Binds the port, starts accepting connections, and dispatches requests
-    /// through `router`.
+    /// Configures the server to listen on a Unix domain socket at `path`,
+    /// for nginx on the same host or pod:
+    ///
+    /// ```nginx
+    /// upstream astor {
+    ///     server unix:/run/app/astor.sock;
+    ///     keepalive 64;
+    /// }
+    /// ```
+    ///
+    /// The socket is not created until [`serve`][Server::serve] is called.
+    /// By default a stale socket file left at `path` by a previous run is
+    /// replaced, and the file is removed once `serve` returns — see
+    /// [`socket_cleanup`][Server::socket_cleanup]. nginx's worker user needs
+    /// write permission on the socket — see
+    /// [`socket_mode`][Server::socket_mode].
+    ///
+    /// ```rust,no_run
+    /// # use astor::{Router, Server};
+    /// # async fn run(app: Router) {
+    /// Server::bind_unix("/run/app/astor.sock")
+    ///     .socket_mode(0o660)
+    ///     .serve(app)
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn bind_unix(path: impl Into<PathBuf>) -> Self {
+        let socket = UnixSocket { cleanup: true, mode: None, path: path.into() };
+        Self { config: Config::new(), listen: Listen::Unix(socket) }
+    }
+
+    /// Sets the socket file's permission bits once it is created — `0o660`
+    /// to let a group nginx belongs to connect, `0o666` for everyone.
+    /// Defaults to what the process umask leaves.
+    ///
+    /// Only applies to [`bind_unix`][Server::bind_unix]; a TCP server
+    /// ignores it.
+    #[cfg(unix)]
+    pub fn socket_mode(mut self, mode: u32) -> Self {
+        if let Listen::Unix(socket) = &mut self.listen {
+            socket.mode = Some(mode);
+        }
+        self
+    }
+
+    /// Whether [`serve`][Server::serve] replaces a socket file left at the
+    /// path by a previous run, and removes its own once it returns. On by
+    /// default. Only ever removes sockets — any other file at the path fails
+    /// the bind instead.
+    ///
+    /// Turn it off when something else manages the file, such as a process
+    /// supervisor.
+    ///
+    /// Only applies to [`bind_unix`][Server::bind_unix]; a TCP server
+    /// ignores it.
+    #[cfg(unix)]
+    pub fn socket_cleanup(mut self, cleanup: bool) -> Self {
+        if let Listen::Unix(socket) = &mut self.listen {
+            socket.cleanup = cleanup;
+        }
+        self
+    }
+
+    /// Caps the decoded size of `Transfer-Encoding: chunked` request bodies.
+    ///
+    /// Defaults to 1 MiB — the same as nginx's `client_max_body_size` default.
+    /// With `proxy_request_buffering off` nginx streams the body through as it
+    /// arrives, so this is the only limit that applies once the bytes reach
+    /// astor. A body that decodes past `bytes` closes the connection.
+    ///
+    /// Requires the `chunked` feature.
+    ///
+    /// ```rust,no_run
+    /// # use astor::Server;
+    /// Server::bind("0.0.0.0:3000").max_chunked_body(100 * 1024 * 1024); // 100 MiB
+    /// ```
+    #[cfg(feature = "chunked")]
+    pub fn max_chunked_body(mut self, bytes: usize) -> Self {
+        self.config.max_chunked_body = bytes;
+        self
+    }
+
+    /// Binds the port or socket, starts accepting connections, and dispatches
+    /// requests through `router`.
     ///
     /// Blocks until the process receives `SIGTERM` or `Ctrl-C`. On shutdown:
     /// 1. The accept loop stops — no new connections are accepted.
-    /// 2. In-flight requests are allowed to finish.
-    /// 3. The function returns.
+    /// 2. In-flight requests are allowed to finish. [`Sse`][crate::Sse]
+    ///    streams end right away, and no connection takes another request.
+    /// 3. A Unix socket file is removed, unless
+    ///    [`socket_cleanup`][Server::socket_cleanup] is off.
+    /// 4. The function returns.
     ///
     /// For Kubernetes: set `terminationGracePeriodSeconds` in your pod spec to
     /// a value longer than your slowest request. If k8s sends `SIGKILL` before
@@ -111,13 +257,16 @@ impl Server {
     ///
     /// # Errors
     ///
-    /// Returns [`Error`] if the TCP listener cannot bind to the address, or if
-    /// a fatal accept error occurs.
-    pub async fn serve(self, router: Router) -> Result<(), Error> {
-        let listener = TcpListener::bind(self.addr).await?;
+    /// Returns [`Error`] if the listener cannot bind to the address or
+    /// socket path, or if a fatal accept error occurs.
+    pub async fn serve(self, mut router: Router) -> Result<(), Error> {
+        let listener = Listener::bind(self.listen).await?;
+        router.finish();
         let router = Arc::new(router);
+        let config = self.config;
 
         let mut tasks = tokio::task::JoinSet::new();
+        let (stop, stopped) = watch::channel(false);
         let shutdown = shutdown_signal();
         tokio::pin!(shutdown);
 
@@ -126,17 +275,19 @@ impl Server {
                 biased;
 
                 () = &mut shutdown => {
+                    stop.send_replace(true);
                     break;
                 }
 
                 res = listener.accept() => {
-                    let (stream, _remote_addr) = match res {
+                    let (reader, writer) = match res {
                         Ok(v) => v,
                         Err(_) => continue,
                     };
                     let router = Arc::clone(&router);
+                    let stopped = stopped.clone();
                     tasks.spawn(async move {
-                        let _ = serve_connection(stream, router).await;
+                        let _ = serve_connection(reader, writer, router, config, stopped).await;
                     });
                 }
 
@@ -145,86 +296,503 @@ impl Server {
         }
 
         while tasks.join_next().await.is_some() {}
+        listener.close();
         Ok(())
     }
 }
 
+impl Config {
+    fn new() -> Self {
+        Self {
+            #[cfg(feature = "chunked")]
+            max_chunked_body: DEFAULT_MAX_CHUNKED_BODY,
+        }
+    }
+}
+
+// ── Listener ──────────────────────────────────────────────────────────────────
+
+/// A bound listener of either transport. Connections come out as boxed
+/// halves, so everything past `accept` is transport-agnostic.
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, UnixSocket),
+}
+
+impl Listener {
+    async fn bind(listen: Listen) -> std::io::Result<Self> {
+        match listen {
+            Listen::Tcp(addr) => Ok(Self::Tcp(TcpListener::bind(addr).await?)),
+            #[cfg(unix)]
+            Listen::Unix(socket) => {
+                use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+                if socket.cleanup {
+                    match std::fs::symlink_metadata(&socket.path) {
+                        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(&socket.path)?,
+                        _ => {} // nothing there, or not ours to remove — bind reports it
+                    }
+                }
+                let listener = UnixListener::bind(&socket.path)?;
+                if let Some(mode) = socket.mode {
+                    std::fs::set_permissions(&socket.path, std::fs::Permissions::from_mode(mode))?;
+                }
+                Ok(Self::Unix(listener, socket))
+            }
+        }
+    }
+
+    async fn accept(&self) -> std::io::Result<(Reader, Writer)> {
+        match self {
+            Self::Tcp(listener) => {
+                let (read_half, write_half) = listener.accept().await?.0.into_split();
+                Ok((Box::new(BufReader::new(read_half)), Box::new(write_half)))
+            }
+            #[cfg(unix)]
+            Self::Unix(listener, _) => {
+                let (read_half, write_half) = listener.accept().await?.0.into_split();
+                Ok((Box::new(BufReader::new(read_half)), Box::new(write_half)))
+            }
+        }
+    }
+
+    /// Stops listening and, for a Unix socket with cleanup on, removes the
+    /// socket file.
+    fn close(self) {
+        #[cfg(unix)]
+        if let Self::Unix(listener, socket) = self {
+            drop(listener);
+            if socket.cleanup {
+                let _ = std::fs::remove_file(&socket.path);
+            }
+        }
+    }
+}
+
 // ── Connection handler ────────────────────────────────────────────────────────
 
-/// Serves all requests on one TCP connection.
+/// Serves all requests on one connection, TCP or Unix.
 ///
 /// Loops until nginx closes the connection (EOF). nginx controls connection
 /// lifetime via `keepalive_timeout` and `keepalive_requests` in the upstream
 /// block — astor never inspects the `Connection` header.
-async fn serve_connection(stream: TcpStream, router: Arc<Router>) -> Result<(), Error> {
-    let (read_half, mut write_half) = stream.into_split();
-    let mut reader = BufReader::new(read_half);
-
+///
+/// A request that fails to parse is answered with [`ParseError::status`] and
+/// `connection: close`, then the connection is dropped — the byte stream can
+/// no longer be trusted to be aligned on a request boundary.
+///
+/// Once `stopped` turns `true` the connection closes after the response in
+/// progress. A `101 Switching Protocols` response with an upgrade callback
+/// hands the connection to that callback for good.
+async fn serve_connection(
+    mut reader: Reader,
+    mut write_half: Writer,
+    router: Arc<Router>,
+    config: Config,
+    stopped: watch::Receiver<bool>,
+) -> Result<(), Error> {
     loop {
-        // ── Request line ──────────────────────────────────────────────────────
-        let mut line = String::new();
-        if reader.read_line(&mut line).await? == 0 {
-            break; // peer closed connection
-        }
-        let line = line.trim_end();
-        let mut parts = line.splitn(3, ' ');
-        let method_str = parts.next().unwrap_or("");
-        let raw = parts.next().unwrap_or("/");
-        let (path, query) = match raw.find('?') {
-            Some(i) => (&raw[..i], &raw[i + 1..]),
-            None    => (raw, ""),
+        let parsed = match read_head(&mut reader).await {
+            Ok(Some(head)) => framing(&head.headers).map(|framing| (head, framing)),
+            Ok(None) => break, // peer closed connection
+            Err(e) => Err(e),
         };
-        let path = path.to_owned();
-        let query = query.to_owned();
-        let Ok(method) = method_str.parse::<Method>() else { break };
-        // HTTP version field ignored — nginx guarantees HTTP/1.1
-
-        // ── Headers ───────────────────────────────────────────────────────────
-        let mut headers: Vec<(String, String)> = Vec::new();
-        loop {
-            let mut hline = String::new();
-            reader.read_line(&mut hline).await?;
-            let hline = hline.trim_end();
-            if hline.is_empty() { break; }
-            if let Some((name, value)) = hline.split_once(": ") {
-                headers.push((name.to_owned(), value.to_owned()));
-            }
-        }
+        let (head, framing) = match parsed {
+            Ok(v) => v,
+            Err(e) => return fail(e, &mut write_half, &stopped).await,
+        };
+        let Head { headers, method, path, query, version } = head;
 
-        // ── Body ──────────────────────────────────────────────────────────────
-        let body = read_body(&mut reader, &headers).await?;
+        // The route decides whether the body is read now or by the handler,
+        // so look it up first.
+        let lookup = router.lookup(method, &path);
+        let streamed = matches!(lookup, Lookup::Found(.., BodyMode::Streamed));
+        let body = match framing {
+            Framing::Length(len) if streamed => Incoming::Streamed(len),
+            framing => match read_body(&mut reader, framing, config).await {
+                Ok(body) => Incoming::Buffered(body),
+                Err(e)   => return fail(e, &mut write_half, &stopped).await,
+            },
+        };
 
         // ── Dispatch ──────────────────────────────────────────────────────────
-        let response = match router.lookup(method, &path) {
-            Some((handler, middleware, params)) => {
-                let req = Request::new(body, headers, method, params, path, query);
+        let mut lent = None;
+        let mut response = match lookup {
+            Lookup::Found(handler, middleware, params, mode) => {
+                let state = router.state();
+                let (body, stream) = match body {
+                    Incoming::Buffered(body) if mode == BodyMode::Streamed => {
+                        (Vec::new(), Some(BodyStream::buffered(body)))
+                    }
+                    Incoming::Buffered(body) => (body, None),
+                    Incoming::Streamed(remaining) => {
+                        let reader = std::mem::replace(&mut reader, Box::new(tokio::io::empty()));
+                        let slot = Arc::new(Mutex::new(Slot { reader: Some(reader), remaining }));
+                        lent = Some(Arc::clone(&slot));
+                        (Vec::new(), Some(BodyStream::connection(slot)))
+                    }
+                };
+                let mut req = Request::new(body, headers, method, params, path, query, state);
+                req.stream = stream;
                 Next::new(middleware, handler).call(req).await
             }
-            None => Response::status(Status::NotFound),
+            Lookup::MethodNotAllowed(allow) => Response::builder()
+                .status(Status::MethodNotAllowed)
+                .header("allow", &allow)
+                .no_body(),
+            Lookup::NotFound => Response::status(Status::NotFound),
         };
 
-        response.write_to(&mut write_half).await?;
+        // Take the connection back from a streaming handler and skip what it
+        // left of the body — before responding, so a peer still sending the
+        // body cannot block on a full socket while astor blocks on the
+        // response.
+        let mut close = false;
+        if let Some(slot) = lent {
+            let (back, remaining) = {
+                let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+                (slot.reader.take(), slot.remaining)
+            };
+            match back {
+                Some(back) => {
+                    reader = back;
+                    close = !drain(&mut reader, remaining).await;
+                }
+                // Nothing else takes the reader, but without it the rest of
+                // the body cannot be skipped: answer 500 and close rather
+                // than drop the request unanswered.
+                None => {
+                    response = Response::status(Status::InternalServerError);
+                    close = true;
+                }
+            }
+        }
+        // An HTTP/1.0 client learns where a streamed body ends only from
+        // the connection closing.
+        close |= version == Version::Http10 && response.is_streamed();
+        if close {
+            response.headers.push(("connection".to_owned(), "close".to_owned()));
+        }
+
+        // A `101` with a callback ends HTTP on this connection: the callback
+        // owns it from here, still inside this task.
+        let upgrade = response.upgrade.take().filter(|_| response.status == 101 && !close);
+        response.write_to(&mut write_half, method == Method::Head, version, &stopped).await?;
+        if let Some(upgrade) = upgrade {
+            upgrade(Upgraded { reader, stopped, writer: write_half }).await;
+            return Ok(());
+        }
+        if close || *stopped.borrow() {
+            break;
+        }
     }
 
     Ok(())
 }
 
+/// Serves `raw` as everything one connection sends before hanging up, and
+/// returns what astor wrote back.
+#[cfg(test)]
+pub(crate) async fn serve_bytes(router: Router, raw: Vec<u8>) -> Vec<u8> {
+    serve_bytes_with(router, Config::new(), raw).await
+}
+
+#[cfg(test)]
+async fn serve_bytes_with(mut router: Router, config: Config, raw: Vec<u8>) -> Vec<u8> {
+    router.finish();
+    let (writer, mut written) = tokio::io::duplex(64 * 1024);
+    let (_stop, stopped) = watch::channel(false);
+    let reader: Reader = Box::new(std::io::Cursor::new(raw));
+    let conn = tokio::spawn(serve_connection(reader, Box::new(writer), Arc::new(router), config, stopped));
+    let mut out = Vec::new();
+    written.read_to_end(&mut out).await.unwrap();
+    let _ = conn.await.unwrap();
+    out
+}
+
+/// Answers a request that could not be parsed, then fails so the caller
+/// drops the connection — the byte stream is no longer aligned on a request
+/// boundary.
+async fn fail<W: AsyncWrite + Unpin>(
+    e: Error,
+    writer: &mut W,
+    stopped: &watch::Receiver<bool>,
+) -> Result<(), Error> {
+    if let Error::Parse(parse) = e {
+        reject(parse).write_to(writer, false, Version::Http11, stopped).await?;
+    }
+    Err(e)
+}
+
+/// The final response on a connection whose request could not be parsed.
+fn reject(e: ParseError) -> Response {
+    Response::builder()
+        .status(e.status())
+        .header("connection", "close")
+        .no_body()
+}
+
+// ── Head parser ───────────────────────────────────────────────────────────────
+
+/// Request line and header section of one request.
+struct Head {
+    headers: Vec<(String, String)>,
+    method: Method,
+    path: String,
+    query: String,
+    version: Version,
+}
+
+/// Reads the request line and headers. `Ok(None)` on a clean EOF between
+/// requests.
+async fn read_head<R: AsyncBufReadExt + Unpin>(reader: &mut R) -> Result<Option<Head>, Error> {
+    // ── Request line ──────────────────────────────────────────────────────────
+    // RFC 9112 §2.2 — ignore empty lines received before the request line.
+    let line = loop {
+        match read_line(reader, ParseError::InvalidRequestLine).await? {
+            None => return Ok(None),
+            Some(line) if line.is_empty() => continue,
+            Some(line) => break line,
+        }
+    };
+    let mut parts = line.split(' ');
+    let (Some(method_str), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(ParseError::InvalidRequestLine.into());
+    };
+    let version = match version {
+        "HTTP/1.1" => Version::Http11,
+        "HTTP/1.0" => Version::Http10,
+        v if is_http_version(v) => return Err(ParseError::UnsupportedVersion.into()),
+        _ => return Err(ParseError::InvalidRequestLine.into()),
+    };
+    if method_str.is_empty() || !method_str.bytes().all(is_tchar) {
+        return Err(ParseError::InvalidRequestLine.into());
+    }
+    let method = method_str.parse::<Method>().map_err(|()| ParseError::UnsupportedMethod)?;
+    // origin-form, or `*` for server-wide OPTIONS. nginx never forwards
+    // absolute-form or authority-form targets upstream.
+    let server_wide = target == "*" && method == Method::Options;
+    if !target.starts_with('/') && !server_wide {
+        return Err(ParseError::InvalidRequestLine.into());
+    }
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = path.to_owned();
+    let query = query.to_owned();
+
+    // ── Headers ───────────────────────────────────────────────────────────────
+    let mut headers: Vec<(String, String)> = Vec::new();
+    loop {
+        let Some(hline) = read_line(reader, ParseError::InvalidHeader).await? else {
+            return Err(unexpected_eof());
+        };
+        if hline.is_empty() {
+            break;
+        }
+        // obs-fold (a line starting with whitespace) is rejected — RFC 9112 §5.2.
+        let Some((name, value)) = hline.split_once(':') else {
+            return Err(ParseError::InvalidHeader.into());
+        };
+        if name.is_empty() || !name.bytes().all(is_tchar) {
+            return Err(ParseError::InvalidHeader.into());
+        }
+        headers.push((name.to_owned(), value.trim().to_owned()));
+    }
+
+    Ok(Some(Head { headers, method, path, query, version }))
+}
+
+/// Reads one CRLF- (or bare LF-) terminated line without the terminator.
+/// `Ok(None)` on EOF; `invalid` if the line is not UTF-8.
+async fn read_line<R: AsyncBufReadExt + Unpin>(
+    reader: &mut R,
+    invalid: ParseError,
+) -> Result<Option<String>, Error> {
+    let mut buf = Vec::new();
+    if reader.read_until(b'\n', &mut buf).await? == 0 {
+        return Ok(None);
+    }
+    if buf.last() != Some(&b'\n') {
+        return Err(unexpected_eof());
+    }
+    buf.pop();
+    if buf.last() == Some(&b'\r') {
+        buf.pop();
+    }
+    String::from_utf8(buf).map(Some).map_err(|_| invalid.into())
+}
+
+/// `HTTP/<digit>.<digit>` — a well-formed version astor does not speak.
+fn is_http_version(v: &str) -> bool {
+    matches!(v.strip_prefix("HTTP/").map(str::as_bytes), Some([major, b'.', minor])
+        if major.is_ascii_digit() && minor.is_ascii_digit())
+}
+
+/// `tchar` from RFC 9110 §5.6.2 — the characters allowed in methods and
+/// header names.
+fn is_tchar(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+fn unexpected_eof() -> Error {
+    std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()
+}
+
 // ── Body readers ─────────────────────────────────────────────────────────────
 
+/// Matches nginx's `client_max_body_size` default.
+#[cfg(feature = "chunked")]
+const DEFAULT_MAX_CHUNKED_BODY: usize = 1024 * 1024;
+
+/// The most a streaming handler may leave unread and still keep the
+/// connection.
+const MAX_DRAIN: u64 = 256 * 1024;
+
+/// How the request body is delimited (RFC 9112 §6.3).
+enum Framing {
+    #[cfg(feature = "chunked")]
+    Chunked,
+    Length(u64),
+    None,
+}
+
+/// The request body as the handler will see it.
+enum Incoming {
+    Buffered(Vec<u8>),
+    /// Still on the connection — this many bytes.
+    Streamed(u64),
+}
+
+fn framing(headers: &[(String, String)]) -> Result<Framing, Error> {
+    let content_length = content_length(headers)?;
+
+    // Every field and list element counts: `gzip` in one field and `chunked`
+    // in the next is `gzip, chunked`, not `chunked`.
+    let mut codings = values(headers, "transfer-encoding").flat_map(|v| v.split(',')).map(str::trim).peekable();
+    if codings.peek().is_some() {
+        if content_length.is_some() {
+            return Err(ParseError::AmbiguousFraming.into());
+        }
+        // Only `chunked` alone is accepted — astor decodes no content codings.
+        #[cfg(feature = "chunked")]
+        if codings.next().is_some_and(|c| c.eq_ignore_ascii_case("chunked")) && codings.next().is_none() {
+            return Ok(Framing::Chunked);
+        }
+        return Err(ParseError::UnsupportedTransferEncoding.into());
+    }
+
+    Ok(content_length.map_or(Framing::None, Framing::Length))
+}
+
+/// The body length every `content-length` value agrees on, across repeated
+/// fields and comma-separated lists (RFC 9110 §8.6). Values that differ
+/// would let the proxy and astor frame the body differently — request
+/// smuggling — so they are rejected.
+fn content_length(headers: &[(String, String)]) -> Result<Option<u64>, ParseError> {
+    let mut length = None;
+    for value in values(headers, "content-length").flat_map(|v| v.split(',')) {
+        let value = value.trim();
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseError::InvalidContentLength);
+        }
+        let value = value.parse::<u64>().map_err(|_| ParseError::InvalidContentLength)?;
+        if length.is_some_and(|length| length != value) {
+            return Err(ParseError::InvalidContentLength);
+        }
+        length = Some(value);
+    }
+    Ok(length)
+}
+
+#[cfg_attr(not(feature = "chunked"), allow(unused_variables))]
 async fn read_body<R: AsyncBufReadExt + Unpin>(
     reader: &mut R,
-    headers: &[(String, String)],
+    framing: Framing,
+    config: Config,
+) -> Result<Vec<u8>, Error> {
+    match framing {
+        #[cfg(feature = "chunked")]
+        Framing::Chunked => read_chunked(reader, config.max_chunked_body).await,
+        Framing::Length(len) => {
+            let len = usize::try_from(len).map_err(|_| ParseError::InvalidContentLength)?;
+            let mut buf = vec![0u8; len];
+            reader.read_exact(&mut buf).await?;
+            Ok(buf)
+        }
+        Framing::None => Ok(Vec::new()),
+    }
+}
+
+/// Reads and discards what a streaming handler left of its body. `false`
+/// when the connection must close instead: more than [`MAX_DRAIN`] left, or
+/// the peer gone.
+async fn drain(reader: &mut Reader, remaining: u64) -> bool {
+    if remaining > MAX_DRAIN {
+        return false;
+    }
+    let drained = tokio::io::copy(&mut (&mut *reader).take(remaining), &mut tokio::io::sink()).await;
+    matches!(drained, Ok(n) if n == remaining)
+}
+
+/// Decodes a `Transfer-Encoding: chunked` body (RFC 9112 §7.1).
+///
+/// Chunk extensions are parsed past and ignored. Trailer fields are read and
+/// discarded — nothing in astor consumes them, and merging them into the
+/// request headers after the fact would surprise middleware that already ran.
+#[cfg(feature = "chunked")]
+async fn read_chunked<R: AsyncBufReadExt + Unpin>(
+    reader: &mut R,
+    limit: usize,
 ) -> Result<Vec<u8>, Error> {
-    if let Some(len) = headers.iter()
-        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
-        .and_then(|(_, v)| v.trim().parse::<usize>().ok())
-    {
-        let mut buf = vec![0u8; len];
-        reader.read_exact(&mut buf).await?;
-        return Ok(buf);
+    let mut body = Vec::new();
+
+    loop {
+        let Some(line) = read_line(reader, ParseError::InvalidChunk).await? else {
+            return Err(unexpected_eof());
+        };
+        // chunk-size [ ";" chunk-ext ]
+        let size = line.split(';').next().unwrap_or("").trim();
+        if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ParseError::InvalidChunk.into());
+        }
+        let size = usize::from_str_radix(size, 16).map_err(|_| ParseError::InvalidChunk)?;
+        if size == 0 {
+            break;
+        }
+        if size > limit - body.len() {
+            return Err(ParseError::BodyTooLarge.into());
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..]).await?;
+
+        let mut crlf = [0u8; 2];
+        reader.read_exact(&mut crlf).await?;
+        if &crlf != b"\r\n" {
+            return Err(ParseError::InvalidChunk.into());
+        }
     }
 
-    Ok(Vec::new())
+    // trailer-section — zero or more field lines, then an empty line.
+    loop {
+        match read_line(reader, ParseError::InvalidChunk).await? {
+            None => return Err(unexpected_eof()),
+            Some(line) if line.is_empty() => break,
+            Some(_) => {}
+        }
+    }
+
+    Ok(body)
+}
+
+/// Every value of header `name`, in request order.
+fn values<'a>(headers: &'a [(String, String)], name: &'a str) -> impl Iterator<Item = &'a str> {
+    headers.iter()
+        .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
 }
 
 // ── Shutdown signal ───────────────────────────────────────────────────────────
@@ -252,3 +820,367 @@ async fn shutdown_signal() {
         () = sigterm => {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+    use crate::testing::{exchange, send};
+    use crate::upgrade::{Parts, Transport};
+
+    /// `POST /echo` answers with the body it read.
+    fn echo() -> Router {
+        Router::new().on(Method::Post, "/echo", |req: Request| async move { Response::text(String::from_utf8_lossy(req.body())) }, ())
+    }
+
+    fn post(headers: &str, body: &str) -> String {
+        format!("POST /echo HTTP/1.1\r\nhost: test\r\n{headers}\r\n{body}")
+    }
+
+    #[tokio::test]
+    async fn serves_pipelined_requests_on_one_connection() {
+        let raw = format!("{}{}", post("content-length: 2\r\n", "ab"), post("content-length: 1\r\n", "c"));
+        let out = exchange(echo(), raw).await;
+        assert_eq!(out.matches("HTTP/1.1 200 OK").count(), 2);
+        assert!(out.contains("\r\n\r\nab") && out.ends_with("\r\n\r\nc"));
+    }
+
+    #[tokio::test]
+    async fn skips_empty_lines_before_the_request_line() {
+        let reply = send(echo(), format!("\r\n\r\n{}", post("content-length: 2\r\n", "ok"))).await;
+        assert_eq!((reply.status, reply.text()), (200, "ok"));
+    }
+
+    #[tokio::test]
+    async fn accepts_repeated_content_length_that_agrees() {
+        for headers in ["content-length: 3\r\ncontent-length: 3\r\n", "content-length: 3, 3\r\n"] {
+            let reply = send(echo(), post(headers, "abc")).await;
+            assert_eq!((reply.status, reply.text()), (200, "abc"));
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_content_length_that_disagrees() {
+        // Framed as 5, the rest would be read as a second request.
+        let smuggled = "hello".to_owned() + &post("content-length: 0\r\n", "");
+        for headers in ["content-length: 5\r\ncontent-length: 50\r\n", "content-length: 5, 50\r\n"] {
+            let out = exchange(echo(), post(headers, &smuggled)).await;
+            assert!(out.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{out}");
+            assert!(out.contains("connection: close\r\n"));
+            assert_eq!(out.matches("HTTP/1.1").count(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_content_length() {
+        for value in ["", "abc", "-1", "+5", "1 2", "99999999999999999999999"] {
+            let reply = send(echo(), post(&format!("content-length: {value}\r\n"), "")).await;
+            assert_eq!(reply.status, 400, "content-length: {value}");
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_both_transfer_encoding_and_content_length() {
+        let reply = send(echo(), post("transfer-encoding: chunked\r\ncontent-length: 3\r\n", "abc")).await;
+        assert_eq!(reply.status, 400);
+        assert_eq!(reply.header("connection"), Some("close"));
+    }
+
+    #[tokio::test]
+    async fn answers_malformed_request_lines_with_400() {
+        for line in [
+            "GET /echo",
+            "GET  /echo HTTP/1.1",
+            "GET /echo HTTP/1.1 extra",
+            "GET echo HTTP/1.1",
+            "GET /echo FOO/1.1",
+            "G(T /echo HTTP/1.1",
+        ] {
+            let reply = send(echo(), format!("{line}\r\n\r\n")).await;
+            assert_eq!(reply.status, 400, "{line}");
+            assert_eq!(reply.header("connection"), Some("close"));
+        }
+    }
+
+    #[tokio::test]
+    async fn answers_malformed_headers_with_400() {
+        for header in ["no-colon", ": empty-name", "bad name: x", " folded: x", "name\u{0}: x"] {
+            let reply = send(echo(), format!("GET /echo HTTP/1.1\r\n{header}\r\n\r\n")).await;
+            assert_eq!(reply.status, 400, "{header:?}");
+        }
+        let mut raw = b"GET /echo HTTP/1.1\r\nx-bytes: ".to_vec();
+        raw.extend_from_slice(b"\xff\xfe\r\n\r\n");
+        assert_eq!(send(echo(), raw).await.status, 400);
+    }
+
+    #[tokio::test]
+    async fn answers_unknown_methods_with_501() {
+        for method in ["BREW", "get"] {
+            let reply = send(echo(), format!("{method} /echo HTTP/1.1\r\n\r\n")).await;
+            assert_eq!(reply.status, 501, "{method}");
+        }
+    }
+
+    #[tokio::test]
+    async fn answers_other_http_versions_with_505() {
+        for version in ["HTTP/2.0", "HTTP/1.2", "HTTP/0.9"] {
+            let reply = send(echo(), format!("GET /echo {version}\r\n\r\n")).await;
+            assert_eq!(reply.status, 505, "{version}");
+            assert_eq!(reply.header("connection"), Some("close"));
+        }
+        assert_eq!(send(echo(), "GET /echo HTTP/1.0\r\n\r\n").await.status, 405);
+    }
+
+    #[tokio::test]
+    async fn closes_on_a_truncated_request_without_answering() {
+        assert_eq!(exchange(echo(), "GET /echo HTTP/1.1\r\nhost: te").await, "");
+        assert_eq!(exchange(echo(), post("content-length: 10\r\n", "short")).await, "");
+    }
+
+    #[cfg(feature = "chunked")]
+    #[tokio::test]
+    async fn answers_chunked_bodies_over_the_limit_with_413() {
+        let config = Config { max_chunked_body: 4 };
+        let raw = post("transfer-encoding: chunked\r\n", "3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n");
+        let out = serve_bytes_with(echo(), config, raw.into_bytes()).await;
+        let reply = crate::testing::parse(&out);
+        assert_eq!(reply.status, 413);
+        assert_eq!(reply.header("connection"), Some("close"));
+
+        let raw = post("transfer-encoding: chunked\r\n", "3\r\nabc\r\n1\r\nd\r\n0\r\n\r\n");
+        let reply = crate::testing::parse(&serve_bytes_with(echo(), config, raw.into_bytes()).await);
+        assert_eq!((reply.status, reply.text()), (200, "abcd"));
+    }
+
+    #[tokio::test]
+    async fn answers_other_transfer_codings_with_501() {
+        for headers in [
+            "transfer-encoding: gzip\r\n",
+            "transfer-encoding: gzip, chunked\r\n",
+            "transfer-encoding: gzip\r\ntransfer-encoding: chunked\r\n",
+            "transfer-encoding: chunked\r\ntransfer-encoding: chunked\r\n",
+            "transfer-encoding: chunked, \r\n",
+        ] {
+            let reply = send(echo(), post(headers, "0\r\n\r\n")).await;
+            assert_eq!(reply.status, 501, "{headers:?}");
+            assert_eq!(reply.header("connection"), Some("close"));
+        }
+    }
+
+    #[cfg(not(feature = "chunked"))]
+    #[tokio::test]
+    async fn answers_chunked_with_501_without_the_feature() {
+        let reply = send(echo(), post("transfer-encoding: chunked\r\n", "0\r\n\r\n")).await;
+        assert_eq!(reply.status, 501);
+    }
+
+    #[cfg(feature = "chunked")]
+    #[tokio::test]
+    async fn decodes_chunked_bodies() {
+        let body = "3\r\nabc\r\nA;ext=1;other\r\n0123456789\r\n1\r\n!\r\n0\r\n\r\n";
+        for coding in ["chunked", "Chunked", " chunked "] {
+            let reply = send(echo(), post(&format!("transfer-encoding:{coding}\r\n"), body)).await;
+            assert_eq!((reply.status, reply.text()), (200, "abc0123456789!"), "{coding:?}");
+        }
+    }
+
+    #[cfg(feature = "chunked")]
+    #[tokio::test]
+    async fn discards_trailers_and_keeps_the_connection() {
+        let app = Router::new().on(Method::Post, "/echo", |req: Request| async move {
+            Response::text(format!("{} {:?}", String::from_utf8_lossy(req.body()), req.header("x-trailer")))
+        }, ());
+        let chunked = post("transfer-encoding: chunked\r\n", "2\r\nhi\r\n0\r\nx-trailer: 1\r\nx-other: 2\r\n\r\n");
+        let out = exchange(app, chunked + &post("content-length: 0\r\n", "")).await;
+        assert_eq!(out.matches("HTTP/1.1 200 OK").count(), 2, "{out}");
+        assert!(out.contains("\r\n\r\nhi None"));
+    }
+
+    #[cfg(feature = "chunked")]
+    #[tokio::test]
+    async fn answers_malformed_chunks_with_400() {
+        for body in ["x\r\nabc\r\n0\r\n\r\n", "3\r\nabcX\r\n0\r\n\r\n", "\r\n", "fffffffffffffffffffff\r\n"] {
+            let reply = send(echo(), post("transfer-encoding: chunked\r\n", body)).await;
+            assert_eq!(reply.status, 400, "{body:?}");
+        }
+    }
+
+    #[cfg(feature = "chunked")]
+    #[tokio::test]
+    async fn accepts_chunked_bodies_up_to_the_limit() {
+        let config = Config { max_chunked_body: 4 };
+        let raw = post("transfer-encoding: chunked\r\n", "4\r\nabcd\r\n0\r\n\r\n");
+        let reply = crate::testing::parse(&serve_bytes_with(echo(), config, raw.into_bytes()).await);
+        assert_eq!((reply.status, reply.text()), (200, "abcd"));
+
+        let raw = post("transfer-encoding: chunked\r\n", "5\r\nabcde\r\n0\r\n\r\n");
+        let reply = crate::testing::parse(&serve_bytes_with(echo(), config, raw.into_bytes()).await);
+        assert_eq!(reply.status, 413);
+    }
+
+    /// `POST /peek` reads at most `n` bytes of its body off the connection,
+    /// then answers with them.
+    fn peek(n: u64) -> Router {
+        Router::new().on_stream(Method::Post, "/peek", move |body: BodyStream| async move {
+            let mut read = Vec::new();
+            match body.take(n).read_to_end(&mut read).await {
+                Ok(_)  => Response::text(String::from_utf8_lossy(&read)),
+                Err(_) => Response::status(Status::BadRequest),
+            }
+        }, ())
+    }
+
+    /// A streamed `POST /peek` with `len` bytes of body, then a buffered
+    /// `POST /echo` the connection has to reach intact.
+    fn peek_then_echo(len: usize) -> String {
+        let first = format!("POST /peek HTTP/1.1\r\nhost: test\r\ncontent-length: {len}\r\n\r\n{}", "x".repeat(len));
+        first + &post("content-length: 4\r\n", "next")
+    }
+
+    fn both() -> Router {
+        peek(3).merge(echo())
+    }
+
+    #[tokio::test]
+    async fn keeps_the_connection_after_a_partly_read_stream() {
+        let out = exchange(both(), peek_then_echo(100)).await;
+        assert_eq!(out.matches("HTTP/1.1 200 OK").count(), 2, "{out}");
+        assert!(!out.contains("connection: close"));
+        let first = crate::testing::parse(out.as_bytes());
+        assert_eq!(first.text(), "xxx");
+        assert!(out.ends_with("\r\n\r\nnext"));
+    }
+
+    #[tokio::test]
+    async fn drains_up_to_max_drain() {
+        let len = usize::try_from(MAX_DRAIN).unwrap() + 3; // `peek` reads 3
+        let out = exchange(both(), peek_then_echo(len)).await;
+        assert_eq!(out.matches("HTTP/1.1 200 OK").count(), 2);
+        assert!(!out.contains("connection: close"));
+        assert!(out.ends_with("\r\n\r\nnext"));
+    }
+
+    #[tokio::test]
+    async fn closes_past_max_drain() {
+        let len = usize::try_from(MAX_DRAIN).unwrap() + 4;
+        let out = exchange(both(), peek_then_echo(len)).await;
+        assert_eq!(out.matches("HTTP/1.1 200 OK").count(), 1);
+        let reply = crate::testing::parse(out.as_bytes());
+        assert_eq!((reply.header("connection"), reply.text()), (Some("close"), "xxx"));
+    }
+
+    #[tokio::test]
+    async fn closes_when_the_peer_hangs_up_mid_body() {
+        let raw = "POST /peek HTTP/1.1\r\nhost: test\r\ncontent-length: 100\r\n\r\nshort";
+        let reply = send(peek(3), raw).await;
+        assert_eq!((reply.status, reply.header("connection")), (200, Some("close")));
+
+        let reply = send(peek(10), raw).await;
+        assert_eq!((reply.status, reply.header("connection")), (400, Some("close")));
+    }
+
+    #[tokio::test]
+    async fn streamed_routes_read_the_whole_body_when_asked() {
+        let raw = "POST /peek HTTP/1.1\r\nhost: test\r\ncontent-length: 5\r\n\r\nhello";
+        let reply = send(peek(u64::MAX), raw).await;
+        assert_eq!((reply.status, reply.text(), reply.header("connection")), (200, "hello", None));
+    }
+
+    #[tokio::test]
+    async fn upgrades_hand_over_the_socket_after_the_response() {
+        let mut router = Router::new().on(Method::Get, "/raw", || async {
+            Response::builder().upgrade("raw", |conn: Upgraded| async move {
+                let Ok(Parts { buffered, transport }) = conn.into_parts() else { return };
+                let Transport::Tcp(_, mut write) = transport else { return };
+                let _ = write.write_all(&[b"early: ", &buffered[..]].concat()).await;
+            })
+        }, ());
+        let listener = Listener::bind(Listen::Tcp("127.0.0.1:0".parse().unwrap())).await.unwrap();
+        let Listener::Tcp(tcp) = &listener else { unreachable!() };
+        let mut client = tokio::net::TcpStream::connect(tcp.local_addr().unwrap()).await.unwrap();
+        client.write_all(b"GET /raw HTTP/1.1\r\nhost: a\r\nupgrade: raw\r\nconnection: upgrade\r\n\r\nhi").await.unwrap();
+
+        let (reader, writer) = listener.accept().await.unwrap();
+        let (_stop, stopped) = watch::channel(false);
+        router.finish();
+        serve_connection(reader, writer, Arc::new(router), Config::new(), stopped).await.unwrap();
+
+        let mut out = String::new();
+        client.read_to_string(&mut out).await.unwrap();
+        assert!(out.starts_with("HTTP/1.1 101 Switching Protocols\r\n"), "{out}");
+        assert!(out.ends_with("\r\n\r\nearly: hi"), "{out}");
+    }
+
+    /// A fresh socket path for `test`, in the system temp directory.
+    #[cfg(unix)]
+    fn socket_path(test: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("astor-{}-{test}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn serves_requests_over_a_unix_socket() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = socket_path("serve");
+        let listener = Listener::bind(Server::bind_unix(&path).socket_mode(0o600).listen).await.unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        let mut client = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let raw = format!("{}{}", post("content-length: 2\r\n", "ab"), post("content-length: 1\r\n", "c"));
+        client.write_all(raw.as_bytes()).await.unwrap();
+        client.shutdown().await.unwrap();
+
+        let (reader, writer) = listener.accept().await.unwrap();
+        let (_stop, stopped) = watch::channel(false);
+        let mut router = echo();
+        router.finish();
+        serve_connection(reader, writer, Arc::new(router), Config::new(), stopped).await.unwrap();
+        let mut out = String::new();
+        client.read_to_string(&mut out).await.unwrap();
+        assert_eq!(out.matches("HTTP/1.1 200 OK").count(), 2);
+        assert!(out.contains("\r\n\r\nab") && out.ends_with("\r\n\r\nc"));
+
+        listener.close();
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn replaces_a_stale_socket_only_with_cleanup_on() {
+        let path = socket_path("stale");
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let kept = Listener::bind(Server::bind_unix(&path).socket_cleanup(false).listen).await;
+        assert_eq!(kept.err().map(|e| e.kind()), Some(std::io::ErrorKind::AddrInUse));
+
+        let listener = Listener::bind(Server::bind_unix(&path).listen).await.unwrap();
+        listener.close();
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn leaves_the_socket_with_cleanup_off() {
+        let path = socket_path("kept");
+        let listener = Listener::bind(Server::bind_unix(&path).socket_cleanup(false).listen).await.unwrap();
+        listener.close();
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn never_removes_a_file_that_is_not_a_socket() {
+        let path = socket_path("file");
+        std::fs::write(&path, "data").unwrap();
+        let bound = Listener::bind(Server::bind_unix(&path).listen).await;
+        assert_eq!(bound.err().map(|e| e.kind()), Some(std::io::ErrorKind::AddrInUse));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "data");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,623 @@
+//! Server-side sessions — a cookie holds the id, a [`SessionStore`] holds the
+//! data.
+//!
+//! Register [`Sessions`] as middleware and take a [`Session`] as a handler
+//! argument. The middleware loads the session before the handler runs and
+//! saves it after, only if something changed:
+//!
+//! ```rust,no_run
+//! use astor::session::{MemoryStore, Session, Sessions};
+//! use astor::{CookieKey, Method, Router, Status};
+//!
+//! async fn login(session: Session) -> Status {
+//!     // … check credentials …
+//!     session.regenerate();
+//!     session.insert("user_id", "42");
+//!     Status::NoContent
+//! }
+//!
+//! async fn me(session: Session) -> Result<String, Status> {
+//!     session.get("user_id").ok_or(Status::Unauthorized)
+//! }
+//!
+//! async fn logout(session: Session) -> Status {
+//!     session.destroy();
+//!     Status::NoContent
+//! }
+//!
+//! # let secret = [0u8; 32];
+//! let app = Router::new()
+//!     .with_state(CookieKey::new(&secret))
+//!     .middleware(Sessions::new(MemoryStore::new()))
+//!     .on(Method::Get,  "/me",     me,     ())
+//!     .on(Method::Post, "/login",  login,  ())
+//!     .on(Method::Post, "/logout", logout, ());
+//! ```
+//!
+//! # The cookie
+//!
+//! The session id is 32 random bytes, sent as a [signed cookie][crate::CookieJar]
+//! under the router's [`CookieKey`][crate::CookieKey] — register one with
+//! [`Router::with_state`][crate::Router::with_state], or every request
+//! through the middleware answers `500 Internal Server Error`. A visitor
+//! whose session holds nothing gets no cookie and no store entry.
+//!
+//! # Expiry
+//!
+//! A session lives at most [`expiry`][Sessions::expiry] after it was
+//! created (one day by default). With an
+//! [`idle_timeout`][Sessions::idle_timeout] it also ends after that long
+//! without a request. An expired session reads as a fresh, empty one.
+//!
+//! # Stores
+//!
+//! [`MemoryStore`] keeps sessions in the process — they are lost on restart
+//! and not shared between instances. Implement [`SessionStore`] over Redis, a
+//! database or anything else to share them.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use crate::cookie::{Cookie, SameSite};
+use crate::crypto;
+use crate::extract::{self, FromRequestParts, Rejection};
+use crate::jar::CookieJar;
+use crate::middleware::{BoxedMiddleware, ErasedMiddleware, Middleware, Next};
+use crate::request::Request;
+use crate::response::{IntoResponse, Response};
+use crate::status::Status;
+
+// ── Store ─────────────────────────────────────────────────────────────────────
+
+/// Where session data lives between requests.
+///
+/// Ids are opaque, cookie-safe strings. A store need not check
+/// [`Record::expires`] itself — the middleware discards expired records it
+/// loads — but should use it to evict them, as a Redis `EXPIREAT` would.
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use std::io;
+/// use std::sync::Mutex;
+///
+/// use astor::session::{Record, SessionStore};
+///
+/// #[derive(Default)]
+/// struct MyStore(Mutex<HashMap<String, Record>>);
+///
+/// impl SessionStore for MyStore {
+///     async fn load(&self, id: &str) -> io::Result<Option<Record>> {
+///         Ok(self.0.lock().unwrap().get(id).cloned())
+///     }
+///
+///     async fn save(&self, id: &str, record: &Record) -> io::Result<()> {
+///         self.0.lock().unwrap().insert(id.to_owned(), record.clone());
+///         Ok(())
+///     }
+///
+///     async fn delete(&self, id: &str) -> io::Result<()> {
+///         self.0.lock().unwrap().remove(id);
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait SessionStore: Send + Sync + 'static {
+    /// The record stored under `id`, or `None` if there is none.
+    fn load(&self, id: &str) -> impl Future<Output = io::Result<Option<Record>>> + Send;
+
+    /// Stores `record` under `id`, replacing any earlier record.
+    fn save(&self, id: &str, record: &Record) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// Removes the record stored under `id`, if any.
+    fn delete(&self, id: &str) -> impl Future<Output = io::Result<()>> + Send;
+}
+
+/// One session as a store keeps it.
+#[derive(Clone, Debug)]
+pub struct Record {
+    /// When the session was created.
+    pub created: SystemTime,
+    /// The session's values.
+    pub data: HashMap<String, String>,
+    /// When the session ends unless a request extends it — the earlier of
+    /// the absolute and the idle deadline.
+    pub expires: SystemTime,
+}
+
+/// Sessions in a `HashMap` behind a mutex.
+///
+/// Expired records are dropped when looked up, and swept every 1024 saves.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    records: Mutex<HashMap<String, Record>>,
+    saves: AtomicU32,
+}
+
+impl MemoryStore {
+    /// An empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn records(&self) -> MutexGuard<'_, HashMap<String, Record>> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl SessionStore for MemoryStore {
+    async fn load(&self, id: &str) -> io::Result<Option<Record>> {
+        let mut records = self.records();
+        match records.get(id) {
+            Some(r) if r.expires <= SystemTime::now() => {
+                records.remove(id);
+                Ok(None)
+            }
+            record => Ok(record.cloned()),
+        }
+    }
+
+    async fn save(&self, id: &str, record: &Record) -> io::Result<()> {
+        let mut records = self.records();
+        if self.saves.fetch_add(1, Ordering::Relaxed) % 1024 == 1023 {
+            let now = SystemTime::now();
+            records.retain(|_, r| r.expires > now);
+        }
+        records.insert(id.to_owned(), record.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> io::Result<()> {
+        self.records().remove(id);
+        Ok(())
+    }
+}
+
+// ── Session ───────────────────────────────────────────────────────────────────
+
+/// The current request's session — a shared handle; clones see the same
+/// data.
+///
+/// Changes are saved after the handler returns. Values are strings: encode
+/// anything richer (JSON, a number) yourself.
+///
+/// As a handler argument, answers `500 Internal Server Error` if the route
+/// does not run behind [`Sessions`].
+#[derive(Clone)]
+pub struct Session(Arc<Mutex<Inner>>);
+
+struct Inner {
+    changed: bool,
+    created: SystemTime,
+    data: HashMap<String, String>,
+    destroyed: bool,
+    /// `None` for a session not in the store yet.
+    id: Option<String>,
+    regenerate: bool,
+}
+
+impl Session {
+    fn new(id: Option<String>, record: Option<Record>) -> Self {
+        let (created, data) = match record {
+            Some(r) => (r.created, r.data),
+            None    => (SystemTime::now(), HashMap::new()),
+        };
+        Self(Arc::new(Mutex::new(Inner {
+            changed: false,
+            created,
+            data,
+            destroyed: false,
+            id,
+            regenerate: false,
+        })))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// A value, cloned.
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.lock().data.get(key).cloned()
+    }
+
+    /// Sets a value, returning the one it replaces.
+    pub fn insert(&self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        let mut inner = self.lock();
+        inner.changed = true;
+        inner.data.insert(key.into(), value.into())
+    }
+
+    /// Removes a value, returning it.
+    pub fn remove(&self, key: &str) -> Option<String> {
+        let mut inner = self.lock();
+        let old = inner.data.remove(key);
+        inner.changed |= old.is_some();
+        old
+    }
+
+    /// Removes every value. The session — and its id — stay.
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.changed |= !inner.data.is_empty();
+        inner.data.clear();
+    }
+
+    /// Moves the session to a fresh id, keeping its data; the old id stops
+    /// working. Call on login and on any privilege change, so an id an
+    /// attacker planted before login is worthless after it.
+    pub fn regenerate(&self) {
+        let mut inner = self.lock();
+        inner.regenerate = true;
+        inner.changed = true;
+    }
+
+    /// Ends the session: its record is deleted and the browser told to drop
+    /// the cookie. Changes made after this in the same request are
+    /// discarded.
+    pub fn destroy(&self) {
+        self.lock().destroyed = true;
+    }
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.lock();
+        f.debug_struct("Session")
+            .field("created", &inner.created)
+            .field("data", &inner.data)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FromRequestParts for Session {
+    type Rejection = Rejection;
+
+    fn from_request_parts(req: &mut Request) -> Result<Self, Rejection> {
+        req.extensions().get::<Session>().cloned().ok_or_else(extract::missing)
+    }
+}
+
+// ── Middleware ────────────────────────────────────────────────────────────────
+
+/// The session middleware: where sessions are stored, and how long they and
+/// their cookie live.
+///
+/// Pass it to [`Router::middleware`][crate::Router::middleware], or as
+/// per-route middleware.
+pub struct Sessions<S> {
+    cookie: Cookie,
+    expiry: Duration,
+    idle_timeout: Option<Duration>,
+    store: S,
+}
+
+impl<S: SessionStore> Sessions<S> {
+    /// Sessions kept in `store`, in a cookie named `session` with
+    /// `Path=/; HttpOnly; SameSite=Lax`, expiring a day after creation.
+    pub fn new(store: S) -> Self {
+        Self {
+            cookie: Cookie::new("session", "").path("/").http_only().same_site(SameSite::Lax),
+            expiry: Duration::from_secs(24 * 60 * 60),
+            idle_timeout: None,
+            store,
+        }
+    }
+
+    /// The name and attributes of the session cookie; its value is ignored
+    /// and `Max-Age` is set from the session's expiry.
+    ///
+    /// ```rust
+    /// use astor::session::{MemoryStore, Sessions};
+    /// use astor::{Cookie, SameSite};
+    ///
+    /// let sessions = Sessions::new(MemoryStore::new())
+    ///     .cookie(Cookie::new("sid", "").path("/").secure().http_only().same_site(SameSite::Strict));
+    /// ```
+    pub fn cookie(mut self, cookie: Cookie) -> Self {
+        self.cookie = cookie;
+        self
+    }
+
+    /// How long a session lives after it is created, however active.
+    pub fn expiry(mut self, expiry: Duration) -> Self {
+        self.expiry = expiry;
+        self
+    }
+
+    /// Ends a session after this long without a request. Every request then
+    /// writes the session back to extend it.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    async fn handle(&self, mut req: Request, next: Next) -> Response {
+        let mut jar = match CookieJar::from_request_parts(&mut req) {
+            Ok(jar)  => jar,
+            Err(rej) => return rej.into_response(),
+        };
+
+        let name = self.cookie.name();
+        let (id, record) = match jar.get_signed(name) {
+            Some(id) => match self.store.load(&id).await {
+                Ok(Some(r)) if r.expires > SystemTime::now() => (Some(id), Some(r)),
+                Ok(_)  => (None, None),
+                Err(_) => return Response::status(Status::InternalServerError),
+            },
+            None => (None, None),
+        };
+        let session = Session::new(id, record);
+        req.extensions_mut().insert(session.clone());
+
+        let mut res = next.call(req).await;
+
+        if self.persist(&session, &mut jar).await.is_err() {
+            return Response::status(Status::InternalServerError);
+        }
+        for cookie in jar.delta() {
+            res.headers.push(("set-cookie".to_owned(), cookie.to_string()));
+        }
+        res
+    }
+
+    /// Writes the session's changes to the store and the jar.
+    async fn persist(&self, session: &Session, jar: &mut CookieJar) -> io::Result<()> {
+        // A snapshot — the guard cannot be held across the store's awaits.
+        let (id, regenerate, destroyed, changed, created, data) = {
+            let inner = session.lock();
+            (inner.id.clone(), inner.regenerate, inner.destroyed, inner.changed, inner.created, inner.data.clone())
+        };
+
+        if destroyed {
+            if let Some(id) = id {
+                self.store.delete(&id).await?;
+                jar.remove(self.cookie.clone());
+            }
+            return Ok(());
+        }
+        // Unchanged sessions are written back only to extend an idle
+        // timeout; empty new ones are never written.
+        let touch = id.is_some() && self.idle_timeout.is_some();
+        if !changed && !touch || id.is_none() && data.is_empty() {
+            return Ok(());
+        }
+
+        let now = SystemTime::now();
+        let deadline = created + self.expiry;
+        let expires = self.idle_timeout.map_or(deadline, |idle| deadline.min(now + idle));
+        let id = match id {
+            Some(id) if !regenerate => id,
+            old => {
+                if let Some(old) = old {
+                    self.store.delete(&old).await?;
+                }
+                let id = new_id()?;
+                let max_age = deadline.duration_since(now).unwrap_or_default();
+                jar.add_signed(self.cookie.clone().with_value(id.clone()).max_age(max_age));
+                id
+            }
+        };
+        self.store.save(&id, &Record { created, data, expires }).await
+    }
+}
+
+impl<S> fmt::Debug for Sessions<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sessions")
+            .field("cookie", &self.cookie)
+            .field("expiry", &self.expiry)
+            .field("idle_timeout", &self.idle_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: SessionStore> Middleware for Sessions<S> {
+    fn into_boxed_middleware(self) -> BoxedMiddleware {
+        Arc::new(Layer(Arc::new(self)))
+    }
+}
+
+struct Layer<S>(Arc<Sessions<S>>);
+
+impl<S: SessionStore> ErasedMiddleware for Layer<S> {
+    fn call(&self, req: Request, next: Next) -> Pin<Box<dyn Future<Output = Response> + Send + 'static>> {
+        let sessions = Arc::clone(&self.0);
+        Box::pin(async move { sessions.handle(req, next).await })
+    }
+}
+
+/// A fresh session id: 256 random bits as base64url.
+fn new_id() -> io::Result<String> {
+    let mut bytes = [0u8; 32];
+    crypto::random_bytes(&mut bytes)?;
+    Ok(crypto::base64url_encode(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jar::CookieKey;
+    use crate::method::Method;
+    use crate::router::Router;
+    use crate::testing::{send, Reply};
+
+    /// A [`MemoryStore`] the test can inspect after each router is gone.
+    struct Shared(Arc<MemoryStore>);
+
+    impl SessionStore for Shared {
+        async fn load(&self, id: &str) -> io::Result<Option<Record>> {
+            self.0.load(id).await
+        }
+
+        async fn save(&self, id: &str, record: &Record) -> io::Result<()> {
+            self.0.save(id, record).await
+        }
+
+        async fn delete(&self, id: &str) -> io::Result<()> {
+            self.0.delete(id).await
+        }
+    }
+
+    async fn login(session: Session) -> Status {
+        session.regenerate();
+        session.insert("user_id", "42");
+        Status::NoContent
+    }
+
+    async fn logout(session: Session) -> Status {
+        session.destroy();
+        Status::NoContent
+    }
+
+    async fn me(session: Session) -> Result<String, Status> {
+        session.get("user_id").ok_or(Status::Unauthorized)
+    }
+
+    /// A fresh router over `store`; routers are not `Clone`, so each
+    /// request builds its own.
+    fn app(store: &Arc<MemoryStore>, idle: Option<Duration>) -> Router {
+        let mut sessions = Sessions::new(Shared(Arc::clone(store)));
+        if let Some(idle) = idle {
+            sessions = sessions.idle_timeout(idle);
+        }
+        Router::new()
+            .with_state(CookieKey::new(&[7; 32]))
+            .middleware(sessions)
+            .on(Method::Get,  "/me",     me,     ())
+            .on(Method::Post, "/login",  login,  ())
+            .on(Method::Post, "/logout", logout, ())
+    }
+
+    async fn call(store: &Arc<MemoryStore>, method: &str, path: &str, cookie: Option<&str>) -> Reply {
+        call_with(app(store, None), method, path, cookie).await
+    }
+
+    async fn call_with(router: Router, method: &str, path: &str, cookie: Option<&str>) -> Reply {
+        let cookie = cookie.map(|c| format!("cookie: {c}\r\n")).unwrap_or_default();
+        send(router, format!("{method} {path} HTTP/1.1\r\nhost: test\r\n{cookie}content-length: 0\r\n\r\n")).await
+    }
+
+    /// The `name=value` pair of the response's `set-cookie`.
+    fn pair(reply: &Reply) -> String {
+        let set = reply.header("set-cookie").expect("no set-cookie");
+        set.split(';').next().unwrap().to_owned()
+    }
+
+    fn ids(store: &MemoryStore) -> Vec<String> {
+        store.records().keys().cloned().collect()
+    }
+
+    #[tokio::test]
+    async fn cookie_round_trips_the_session() {
+        let store = Arc::new(MemoryStore::new());
+        let reply = call(&store, "POST", "/login", None).await;
+        assert_eq!(reply.status, 204);
+        let set = reply.header("set-cookie").unwrap();
+        assert!(set.starts_with("session="), "{set}");
+        // A day, less the moments since the session was created.
+        assert!(set.contains("; Path=/; Max-Age=8639"), "{set}");
+        assert!(set.ends_with("; HttpOnly; SameSite=Lax"), "{set}");
+        let cookie = pair(&reply);
+        assert_eq!(ids(&store).len(), 1);
+        assert!(cookie.contains(&ids(&store)[0]));
+
+        let reply = call(&store, "GET", "/me", Some(&cookie)).await;
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.text(), "42");
+        assert_eq!(reply.header("set-cookie"), None);
+
+        let forged = format!("session={}", ids(&store)[0]);
+        assert_eq!(call(&store, "GET", "/me", Some(&forged)).await.status, 401);
+    }
+
+    #[tokio::test]
+    async fn empty_sessions_are_not_stored() {
+        let store = Arc::new(MemoryStore::new());
+        let reply = call(&store, "GET", "/me", None).await;
+        assert_eq!(reply.status, 401);
+        assert_eq!(reply.header("set-cookie"), None);
+        assert!(ids(&store).is_empty());
+    }
+
+    #[tokio::test]
+    async fn regenerate_moves_the_data_to_a_new_id() {
+        let store = Arc::new(MemoryStore::new());
+        let first = pair(&call(&store, "POST", "/login", None).await);
+        let old = ids(&store);
+
+        let reply = call(&store, "POST", "/login", Some(&first)).await;
+        let second = pair(&reply);
+        assert_ne!(first, second);
+        assert_eq!(ids(&store).len(), 1);
+        assert_ne!(ids(&store), old);
+
+        assert_eq!(call(&store, "GET", "/me", Some(&first)).await.status, 401);
+        assert_eq!(call(&store, "GET", "/me", Some(&second)).await.text(), "42");
+    }
+
+    #[tokio::test]
+    async fn destroy_deletes_the_record_and_the_cookie() {
+        let store = Arc::new(MemoryStore::new());
+        let cookie = pair(&call(&store, "POST", "/login", None).await);
+
+        let reply = call(&store, "POST", "/logout", Some(&cookie)).await;
+        assert_eq!(reply.status, 204);
+        assert_eq!(
+            reply.header("set-cookie"),
+            Some("session=; Path=/; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT; HttpOnly; SameSite=Lax"),
+        );
+        assert!(ids(&store).is_empty());
+        assert_eq!(call(&store, "GET", "/me", Some(&cookie)).await.status, 401);
+    }
+
+    #[tokio::test]
+    async fn idle_sessions_expire() {
+        let store = Arc::new(MemoryStore::new());
+        let idle = Some(Duration::from_millis(200));
+        let cookie = pair(&call_with(app(&store, idle), "POST", "/login", None).await);
+
+        // Each request pushes the idle deadline back.
+        for _ in 0..3 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            assert_eq!(call_with(app(&store, idle), "GET", "/me", Some(&cookie)).await.text(), "42");
+        }
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(call_with(app(&store, idle), "GET", "/me", Some(&cookie)).await.status, 401);
+        assert!(ids(&store).is_empty());
+    }
+
+    #[tokio::test]
+    async fn absolute_expiry_ends_active_sessions() {
+        let store = Arc::new(MemoryStore::new());
+        let app = || {
+            Router::new()
+                .with_state(CookieKey::new(&[7; 32]))
+                .middleware(Sessions::new(Shared(Arc::clone(&store))).expiry(Duration::from_millis(200)))
+                .on(Method::Get,  "/me",    me,    ())
+                .on(Method::Post, "/login", login, ())
+        };
+        let cookie = pair(&call_with(app(), "POST", "/login", None).await);
+        assert_eq!(call_with(app(), "GET", "/me", Some(&cookie)).await.text(), "42");
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(call_with(app(), "GET", "/me", Some(&cookie)).await.status, 401);
+    }
+
+    #[tokio::test]
+    async fn missing_key_or_middleware_is_a_server_error() {
+        let store = Arc::new(MemoryStore::new());
+        let router = Router::new().middleware(Sessions::new(Shared(Arc::clone(&store)))).on(Method::Get, "/me", me, ());
+        assert_eq!(call_with(router, "GET", "/me", None).await.status, 500);
+
+        let router = Router::new().on(Method::Get, "/me", me, ());
+        let reply = call_with(router, "GET", "/me", None).await;
+        assert_eq!((reply.status, reply.text()), (500, ""));
+    }
+}
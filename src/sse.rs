@@ -0,0 +1,348 @@
+//! Server-sent events — `text/event-stream` responses.
+//!
+//! Build an [`Sse`] from the receiving end of a channel and return it from a
+//! handler. Every [`Event`] sent on the channel goes out as it arrives; the
+//! stream ends when every sender is dropped:
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//!
+//! use astor::{Event, Sse};
+//! use tokio::sync::mpsc;
+//!
+//! async fn ticks() -> Sse {
+//!     let (tx, rx) = mpsc::channel(16);
+//!     tokio::spawn(async move {
+//!         for n in 0u64.. {
+//!             let event = Event::new(n.to_string()).event("tick").id(n.to_string());
+//!             // Fails once the client has gone — stop producing then.
+//!             if tx.send(event).await.is_err() {
+//!                 return;
+//!             }
+//!             tokio::time::sleep(Duration::from_secs(1)).await;
+//!         }
+//!     });
+//!     Sse::new(rx)
+//! }
+//! ```
+//!
+//! # Keep-alive
+//!
+//! nginx drops an upstream connection that stays silent for
+//! `proxy_read_timeout` (60 s by default). A stream with nothing to say sends
+//! a comment line instead — every 15 s unless set with
+//! [`Sse::keep_alive`]. Browsers ignore comments. A client that has gone away
+//! fails the next write, so the keep-alive is also what notices it.
+//!
+//! # Shutdown
+//!
+//! Event streams rarely end on their own, and graceful shutdown waits for
+//! every in-flight response. On `SIGTERM` / `Ctrl-C` astor ends each stream
+//! cleanly and closes its connection; the browser's `EventSource`
+//! reconnects — to another replica — after its retry delay, sending the last
+//! [`id`][Event::id] it saw as `last-event-id`.
+//!
+//! # nginx
+//!
+//! [`Sse`] responses carry `cache-control: no-cache` and
+//! `x-accel-buffering: no`, so nginx passes each event through as it is
+//! written instead of buffering the stream. Nothing to configure.
+//!
+//! The stream goes out chunked. To an HTTP/1.0 request — nginx's default
+//! `proxy_http_version` — it goes out unframed instead, with
+//! `connection: close`, and ends when astor closes the connection.
+
+use std::fmt;
+use std::time::Duration;
+
+use tokio::io::AsyncWrite;
+use tokio::sync::{mpsc, watch};
+
+use crate::response::{IntoResponse, Response, Version, write_chunk};
+
+/// The keep-alive comment and how often it goes out by default.
+const KEEP_ALIVE: &[u8] = b":\n\n";
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// A `text/event-stream` response, fed by a channel of [`Event`]s.
+///
+/// Return it from a handler as-is, or end a [`Response::builder`] chain with
+/// `.sse(…)` for a custom status or extra headers.
+pub struct Sse {
+    events: mpsc::Receiver<Event>,
+    keep_alive: Duration,
+}
+
+impl Sse {
+    /// A stream of the events sent on `events`, ending when every sender is
+    /// dropped.
+    ///
+    /// The channel's capacity bounds how far the producer runs ahead of the
+    /// client. Once the client is gone, `send` fails.
+    pub fn new(events: mpsc::Receiver<Event>) -> Self {
+        Self { events, keep_alive: KEEP_ALIVE_INTERVAL }
+    }
+
+    /// Sends a keep-alive comment after `interval` without an event.
+    /// Defaults to 15 s — keep it under nginx's `proxy_read_timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn keep_alive(mut self, interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "SSE keep-alive interval must be non-zero");
+        self.keep_alive = interval;
+        self
+    }
+
+    /// Writes the events as chunks — bare for HTTP/1.0 — until the channel
+    /// closes or `shutdown` turns `true`, with a keep-alive after every
+    /// silent interval.
+    pub(crate) async fn write_to<W: AsyncWrite + Unpin>(
+        mut self,
+        writer: &mut W,
+        version: Version,
+        shutdown: &watch::Receiver<bool>,
+    ) -> std::io::Result<()> {
+        let mut shutdown = shutdown.clone();
+        loop {
+            let chunk = tokio::select! {
+                biased;
+
+                Ok(_) = shutdown.wait_for(|&stop| stop) => return Ok(()),
+
+                event = self.events.recv() => match event {
+                    Some(event) => event.to_string().into_bytes(),
+                    None        => return Ok(()),
+                },
+
+                () = tokio::time::sleep(self.keep_alive) => KEEP_ALIVE.to_vec(),
+            };
+            write_chunk(writer, &chunk, version).await?;
+        }
+    }
+}
+
+impl IntoResponse for Sse {
+    fn into_response(self) -> Response {
+        Response::builder().sse(self)
+    }
+}
+
+/// One server-sent event.
+///
+/// `Display` renders it in the `text/event-stream` format, blank line
+/// included. Data spanning several lines is split into one `data:` field per
+/// line; the browser joins them back with `\n`.
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use astor::Event;
+///
+/// let event = Event::new("first line\nsecond line")
+///     .event("update")
+///     .id("42")
+///     .retry(Duration::from_secs(5));
+///
+/// assert_eq!(
+///     event.to_string(),
+///     "event: update\nid: 42\nretry: 5000\ndata: first line\ndata: second line\n\n",
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Event {
+    data: String,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    /// An event carrying `data`, dispatched as a `message` event.
+    pub fn new(data: impl Into<String>) -> Self {
+        Self { data: data.into(), event: None, id: None, retry: None }
+    }
+
+    /// `event` — the event type, dispatched to
+    /// `addEventListener(name, …)` instead of `onmessage`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` contains a line break, which would end the field.
+    pub fn event(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        assert!(!name.contains(['\n', '\r']), "invalid SSE event name `{name}`");
+        self.event = Some(name);
+        self
+    }
+
+    /// `id` — the browser sends the last one it saw as `last-event-id` when
+    /// it reconnects, so the stream can resume where it left off.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` contains a line break or a NUL, which browsers reject.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        let id = id.into();
+        assert!(!id.contains(['\n', '\r', '\0']), "invalid SSE event id `{id}`");
+        self.id = Some(id);
+        self
+    }
+
+    /// `retry` — how long the browser waits before reconnecting, to the
+    /// millisecond.
+    pub fn retry(mut self, after: Duration) -> Self {
+        self.retry = Some(after);
+        self
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(event) = &self.event {
+            writeln!(f, "event: {event}")?;
+        }
+        if let Some(id) = &self.id {
+            writeln!(f, "id: {id}")?;
+        }
+        if let Some(retry) = self.retry {
+            writeln!(f, "retry: {}", retry.as_millis())?;
+        }
+        // `\r\n`, `\r` and `\n` all end a line in the event-stream format.
+        for line in self.data.split("\r\n").flat_map(|l| l.split(['\r', '\n'])) {
+            writeln!(f, "data: {line}")?;
+        }
+        writeln!(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::method::Method;
+    use crate::router::Router;
+    use crate::testing::{exchange, get};
+
+    /// Everything `sse` writes until it ends, or until `stop_after` when
+    /// given, at which point the server starts shutting down.
+    async fn write(sse: Sse, stop_after: Option<Duration>) -> String {
+        let (stop, shutdown) = watch::channel(false);
+        if let Some(after) = stop_after {
+            tokio::spawn(async move {
+                tokio::time::sleep(after).await;
+                let _ = stop.send(true);
+            });
+        }
+        let mut out = Vec::new();
+        sse.write_to(&mut out, Version::Http11, &shutdown).await.unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn events_render_every_field() {
+        assert_eq!(Event::new("").to_string(), "data: \n\n");
+        assert_eq!(
+            Event::new("a\r\nb\rc\nd").event("e").id("7").retry(Duration::from_millis(1500)).to_string(),
+            "event: e\nid: 7\nretry: 1500\ndata: a\ndata: b\ndata: c\ndata: d\n\n",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid SSE event name `a\nb`")]
+    fn event_names_reject_line_breaks() {
+        let _ = Event::new("").event("a\nb");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid SSE event id `1\0`")]
+    fn ids_reject_nul() {
+        let _ = Event::new("").id("1\0");
+    }
+
+    #[test]
+    #[should_panic(expected = "SSE keep-alive interval must be non-zero")]
+    fn keep_alive_rejects_zero() {
+        let (_tx, rx) = mpsc::channel(1);
+        let _ = Sse::new(rx).keep_alive(Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn sends_each_event_as_a_chunk_until_the_senders_drop() {
+        let (tx, rx) = mpsc::channel(4);
+        tx.send(Event::new("one")).await.unwrap();
+        tx.send(Event::new("two").id("2")).await.unwrap();
+        drop(tx);
+        assert_eq!(write(Sse::new(rx), None).await, "b\r\ndata: one\n\n\r\n11\r\nid: 2\ndata: two\n\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn silent_streams_send_keep_alives_until_shutdown() {
+        let (_tx, rx) = mpsc::channel(1);
+        let sse = Sse::new(rx).keep_alive(Duration::from_millis(20));
+        let out = write(sse, Some(Duration::from_millis(110))).await;
+        assert!(out.matches("3\r\n:\n\n\r\n").count() >= 3, "{out:?}");
+        assert_eq!(out.replace("3\r\n:\n\n\r\n", ""), "");
+    }
+
+    #[tokio::test]
+    async fn a_gone_client_fails_the_producer() {
+        let (tx, rx) = mpsc::channel(1);
+        let (client, mut server) = tokio::io::duplex(64);
+        drop(client);
+        let (_stop, shutdown) = watch::channel(false);
+        tx.send(Event::new("lost")).await.unwrap();
+        assert!(Sse::new(rx).write_to(&mut server, Version::Http11, &shutdown).await.is_err());
+        assert!(tx.send(Event::new("next")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn responses_disable_caching_and_proxy_buffering() {
+        async fn feed() -> Sse {
+            let (tx, rx) = mpsc::channel(1);
+            tokio::spawn(async move { tx.send(Event::new("hi").event("greet")).await });
+            Sse::new(rx)
+        }
+        let reply = get(Router::new().on(Method::Get, "/feed", feed, ()), "/feed").await;
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.header("content-type"), Some("text/event-stream"));
+        assert_eq!(reply.header("cache-control"), Some("no-cache"));
+        assert_eq!(reply.header("x-accel-buffering"), Some("no"));
+        assert_eq!(reply.header("transfer-encoding"), Some("chunked"));
+        assert_eq!(reply.text(), "event: greet\ndata: hi\n\n");
+    }
+
+    #[tokio::test]
+    async fn http10_streams_are_unframed_and_end_with_the_connection() {
+        async fn feed() -> Sse {
+            let (tx, rx) = mpsc::channel(2);
+            tokio::spawn(async move {
+                let _ = tx.send(Event::new("one")).await;
+                let _ = tx.send(Event::new("two").id("2")).await;
+            });
+            Sse::new(rx)
+        }
+        let app = Router::new().on(Method::Get, "/feed", feed, ());
+        let out = exchange(app, "GET /feed HTTP/1.0\r\n\r\nGET /feed HTTP/1.0\r\n\r\n").await;
+        assert_eq!(
+            out,
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncache-control: no-cache\r\n\
+             x-accel-buffering: no\r\nconnection: close\r\n\r\ndata: one\n\nid: 2\ndata: two\n\n",
+        );
+    }
+
+    #[tokio::test]
+    async fn http10_keep_alives_are_bare_comments() {
+        let (_tx, rx) = mpsc::channel(1);
+        let sse = Sse::new(rx).keep_alive(Duration::from_millis(20));
+        let (stop, shutdown) = watch::channel(false);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(70)).await;
+            let _ = stop.send(true);
+        });
+        let mut out = Vec::new();
+        sse.write_to(&mut out, Version::Http10, &shutdown).await.unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.len() >= 6 && out.replace(":\n\n", "").is_empty(), "{out:?}");
+    }
+}
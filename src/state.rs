@@ -0,0 +1,100 @@
+//! Shared application state, keyed by type.
+//!
+//! Registered once with [`Router::with_state`][crate::Router::with_state],
+//! read per request with [`Request::state`][crate::Request::state]. Every
+//! request on every connection holds an `Arc` to the same map — one atomic
+//! increment per request, no copies of the state itself.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// One value per type. Values are never mutated after startup — wrap
+/// anything that changes in a `Mutex`, `RwLock` or atomic yourself.
+#[derive(Clone, Default)]
+pub(crate) struct StateMap(HashMap<TypeId, Arc<dyn Any + Send + Sync>>);
+
+impl StateMap {
+    /// Stores `value`, replacing any earlier value of the same type.
+    pub(crate) fn insert<S: Send + Sync + 'static>(&mut self, value: S) {
+        self.0.insert(TypeId::of::<S>(), Arc::new(value));
+    }
+
+    pub(crate) fn contains(&self, id: TypeId) -> bool {
+        self.0.contains_key(&id)
+    }
+
+    pub(crate) fn get<S: Send + Sync + 'static>(&self) -> Option<&S> {
+        self.0.get(&TypeId::of::<S>())?.downcast_ref()
+    }
+
+    pub(crate) fn get_arc<S: Send + Sync + 'static>(&self) -> Option<Arc<S>> {
+        Arc::clone(self.0.get(&TypeId::of::<S>())?).downcast().ok()
+    }
+
+    /// Adds every value from `other` whose type `self` does not hold yet.
+    pub(crate) fn merge(&mut self, other: &StateMap) {
+        for (id, value) in &other.0 {
+            self.0.entry(*id).or_insert_with(|| Arc::clone(value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::method::Method;
+    use crate::middleware::Next;
+    use crate::request::Request;
+    use crate::response::Response;
+    use crate::router::Router;
+    use crate::testing::get;
+
+    #[test]
+    fn keyed_by_type() {
+        let mut map = StateMap::default();
+        map.insert(1u32);
+        map.insert("one");
+        map.insert(2u32);
+        assert_eq!(map.get::<u32>(), Some(&2));
+        assert_eq!(map.get::<&str>(), Some(&"one"));
+        assert_eq!(map.get::<u64>(), None);
+        assert!(map.contains(TypeId::of::<u32>()));
+    }
+
+    #[test]
+    fn get_arc_shares_the_stored_value() {
+        let mut map = StateMap::default();
+        map.insert(String::from("pool"));
+        assert!(Arc::ptr_eq(&map.get_arc::<String>().unwrap(), &map.get_arc::<String>().unwrap()));
+    }
+
+    #[test]
+    fn merge_keeps_existing_values() {
+        let (mut a, mut b) = (StateMap::default(), StateMap::default());
+        a.insert(1u32);
+        b.insert(2u32);
+        b.insert('b');
+        a.merge(&b);
+        assert_eq!((a.get::<u32>(), a.get::<char>()), (Some(&1), Some(&'b')));
+    }
+
+    #[tokio::test]
+    async fn handlers_and_middleware_read_the_same_state() {
+        async fn count(req: Request, next: Next) -> Response {
+            req.state::<AtomicU32>().unwrap().fetch_add(1, Ordering::Relaxed);
+            next.call(req).await
+        }
+        async fn seen(req: Request) -> String {
+            let seen = req.state::<AtomicU32>().unwrap().load(Ordering::Relaxed);
+            format!("{seen} {}", req.state::<u8>().is_none())
+        }
+        let router = Router::new()
+            .with_state(AtomicU32::new(0))
+            .middleware(count)
+            .on(Method::Get, "/", seen, ());
+        assert_eq!(get(router, "/").await.text(), "1 true");
+    }
+}
@@ -35,6 +35,7 @@
 /// Variants are grouped by class and listed alphabetically within each group.
 /// Use the variant name — never a raw integer.
 #[allow(clippy::enum_variant_names)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum Status {
     // ── 1xx Informational ─────────────────────────────────────────────────────
     Continue,                      // 100
@@ -0,0 +1,80 @@
+//! Unit-test helpers: requests through a [`Router`] without a socket.
+
+use crate::router::Router;
+
+/// The first response astor wrote back, parsed.
+pub(crate) struct Reply {
+    /// Decoded if it was sent chunked.
+    pub(crate) body: Vec<u8>,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) status: u16,
+}
+
+impl Reply {
+    /// The first value of header `name`, case-insensitively.
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    pub(crate) fn text(&self) -> &str {
+        std::str::from_utf8(&self.body).expect("response body is not UTF-8")
+    }
+}
+
+/// Writes `raw` — one or more requests, byte for byte — on a fresh
+/// connection, then hangs up. Returns everything astor wrote back.
+pub(crate) async fn exchange(router: Router, raw: impl Into<Vec<u8>>) -> String {
+    String::from_utf8_lossy(&crate::server::serve_bytes(router, raw.into()).await).into_owned()
+}
+
+/// [`exchange`], parsed up to the end of the first response.
+pub(crate) async fn send(router: Router, raw: impl Into<Vec<u8>>) -> Reply {
+    parse(exchange(router, raw).await.as_bytes())
+}
+
+/// `GET path` with no body.
+pub(crate) async fn get(router: Router, path: &str) -> Reply {
+    request(router, "GET", path).await
+}
+
+/// `method path` with no body.
+pub(crate) async fn request(router: Router, method: &str, path: &str) -> Reply {
+    send(router, format!("{method} {path} HTTP/1.1\r\nhost: test\r\n\r\n")).await
+}
+
+/// Parses the first response in `raw`.
+pub(crate) fn parse(raw: &[u8]) -> Reply {
+    let end = raw.windows(4).position(|w| w == b"\r\n\r\n").expect("no complete response head");
+    let head = std::str::from_utf8(&raw[..end]).expect("response head is not UTF-8");
+    let mut lines = head.split("\r\n");
+    let status = lines.next().unwrap()[9..12].parse().unwrap();
+    let headers: Vec<(String, String)> = lines
+        .map(|l| {
+            let (k, v) = l.split_once(": ").expect("malformed header line");
+            (k.to_owned(), v.to_owned())
+        })
+        .collect();
+    let rest = &raw[end + 4..];
+    let mut reply = Reply { body: Vec::new(), headers, status };
+    if reply.header("transfer-encoding") == Some("chunked") {
+        reply.body = dechunk(rest);
+    } else if let Some(len) = reply.header("content-length") {
+        let len: usize = len.parse().unwrap();
+        reply.body = rest[..len.min(rest.len())].to_vec();
+    }
+    reply
+}
+
+/// The data of a chunked body, up to its last chunk.
+fn dechunk(mut raw: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    loop {
+        let eol = raw.windows(2).position(|w| w == b"\r\n").expect("truncated chunk size");
+        let size = usize::from_str_radix(std::str::from_utf8(&raw[..eol]).unwrap(), 16).unwrap();
+        if size == 0 {
+            return body;
+        }
+        body.extend_from_slice(&raw[eol + 2..eol + 2 + size]);
+        raw = &raw[eol + 2 + size + 2..];
+    }
+}
@@ -0,0 +1,319 @@
+//! Connection takeover after `101 Switching Protocols`.
+//!
+//! A response built with
+//! [`Response::builder().upgrade(…)`][crate::Response::builder] carries a
+//! callback. Once the server has written that response, it stops speaking
+//! HTTP on the connection and hands it to the callback as an [`Upgraded`] —
+//! in the connection's own task, so graceful shutdown still waits for it.
+
+use std::any::Any;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, BufReader, ReadBuf};
+use tokio::net::tcp;
+#[cfg(unix)]
+use tokio::net::unix;
+use tokio::sync::watch;
+
+use crate::body::Reader;
+
+/// The connection's write half, boxed like the read half.
+pub(crate) type Writer = Box<dyn WriteHalf>;
+
+/// A write half that can be taken back by its concrete type, like
+/// [`ReadHalf`][crate::body::ReadHalf].
+pub(crate) trait WriteHalf: AsyncWrite + Send + Unpin {
+    fn as_any(&self) -> &dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: AsyncWrite + Send + Unpin + 'static> WriteHalf for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// What runs on the connection after the `101`.
+pub(crate) type OnUpgrade = Box<dyn FnOnce(Upgraded) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+
+/// A connection that no longer speaks HTTP — yours to run any protocol on.
+///
+/// Read and write it directly, or take the socket back with
+/// [`into_parts`][Upgraded::into_parts]. Reads start with whatever the peer
+/// sent right after the request head, so nothing it sent early is lost. The
+/// connection closes when the callback returns.
+///
+/// ```rust,no_run
+/// use astor::{Request, Response, Status, Upgraded};
+/// use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+///
+/// // A line-echo protocol, switched to with `upgrade: echo`.
+/// async fn echo(req: Request) -> Response {
+///     if req.header("upgrade") != Some("echo") {
+///         return Response::status(Status::UpgradeRequired);
+///     }
+///     Response::builder().upgrade("echo", |conn: Upgraded| async move {
+///         let shutdown = conn.shutdown();
+///         let (reader, mut writer) = tokio::io::split(conn);
+///         let mut lines = BufReader::new(reader).lines();
+///         tokio::select! {
+///             () = shutdown => {}
+///             () = async {
+///                 while let Ok(Some(line)) = lines.next_line().await {
+///                     if writer.write_all(format!("{line}\n").as_bytes()).await.is_err() {
+///                         return;
+///                     }
+///                 }
+///             } => {}
+///         }
+///     })
+/// }
+/// ```
+pub struct Upgraded {
+    /// Still holds any bytes the peer sent right after the request head.
+    pub(crate) reader: Reader,
+    /// Turns `true` when the server starts shutting down.
+    pub(crate) stopped: watch::Receiver<bool>,
+    pub(crate) writer: Writer,
+}
+
+impl Upgraded {
+    /// Resolves once the server starts shutting down.
+    ///
+    /// Graceful shutdown waits for every upgraded connection, like any
+    /// in-flight request. Long-lived protocols should watch for this and
+    /// wind down — the future is independent of the connection, so take it
+    /// before [`into_parts`][Upgraded::into_parts].
+    pub fn shutdown(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut stopped = self.stopped.clone();
+        async move {
+            // `Err` only once the server is gone — nothing left to wait for.
+            let _ = stopped.wait_for(|&stop| stop).await;
+        }
+    }
+
+    /// The socket itself, for code that needs the concrete stream — a
+    /// protocol library, `peer_addr`, socket options, `reunite`.
+    ///
+    /// astor reads ahead, so the peer may already have sent bytes that sit
+    /// in its buffer rather than in the socket: they come back as
+    /// [`Parts::buffered`], and belong before anything read from
+    /// [`Parts::transport`].
+    ///
+    /// # Errors
+    ///
+    /// Gives the connection back unchanged when it is not a TCP or Unix
+    /// socket astor accepted — an in-memory stream in a test, for one. It
+    /// still works as an [`Upgraded`].
+    ///
+    /// ```rust,no_run
+    /// use astor::{Response, Transport, Upgraded};
+    ///
+    /// fn takeover() -> Response {
+    ///     Response::builder().upgrade("custom", |conn: Upgraded| async move {
+    ///         let Ok(parts) = conn.into_parts() else { return };
+    ///         match parts.transport {
+    ///             Transport::Tcp(read, write) => {
+    ///                 let stream = read.reunite(write).unwrap();
+    ///                 let _ = stream.set_nodelay(true);
+    ///                 // … hand `parts.buffered` and `stream` to the protocol …
+    ///             }
+    ///             #[cfg(unix)]
+    ///             Transport::Unix(read, write) => {
+    ///                 // … the same over the Unix socket …
+    ///                 # drop((read, write));
+    ///             }
+    ///             _ => {}
+    ///         }
+    ///     })
+    /// }
+    /// ```
+    pub fn into_parts(self) -> Result<Parts, Self> {
+        let conn = match self.split() {
+            Ok((buffered, read, write)) => return Ok(Parts { buffered, transport: Transport::Tcp(read, write) }),
+            Err(conn) => conn,
+        };
+        #[cfg(unix)]
+        let conn = match conn.split() {
+            Ok((buffered, read, write)) => return Ok(Parts { buffered, transport: Transport::Unix(read, write) }),
+            Err(conn) => conn,
+        };
+        Err(conn)
+    }
+
+    /// The buffered bytes and the halves, if they are an `R` behind a
+    /// [`BufReader`] and a `W`. Gives the connection back untouched
+    /// otherwise.
+    fn split<R: AsyncRead + 'static, W: 'static>(self) -> Result<(Vec<u8>, R, W), Self> {
+        // Deref first: the boxes themselves are halves too.
+        if !(*self.reader).as_any().is::<BufReader<R>>() || !(*self.writer).as_any().is::<W>() {
+            return Err(self);
+        }
+        let reader = self.reader.into_any().downcast::<BufReader<R>>().expect("type checked above");
+        let writer = self.writer.into_any().downcast::<W>().expect("type checked above");
+        Ok((reader.buffer().to_vec(), reader.into_inner(), *writer))
+    }
+}
+
+/// An [`Upgraded`] connection taken apart by
+/// [`into_parts`][Upgraded::into_parts].
+#[derive(Debug)]
+pub struct Parts {
+    /// Bytes the peer sent that astor had already read off the socket.
+    /// They come before anything still to be read from `transport`.
+    pub buffered: Vec<u8>,
+    /// The socket's two halves.
+    pub transport: Transport,
+}
+
+/// The socket under an [`Upgraded`] connection, split into owned halves —
+/// `reunite` them for the whole stream.
+///
+/// All variants are listed alphabetically — add new ones in order.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Transport {
+    /// A TCP connection, from [`Server::bind`][crate::Server::bind].
+    Tcp(tcp::OwnedReadHalf, tcp::OwnedWriteHalf),
+    /// A Unix socket connection, from
+    /// [`Server::bind_unix`][crate::Server::bind_unix].
+    #[cfg(unix)]
+    Unix(unix::OwnedReadHalf, unix::OwnedWriteHalf),
+}
+
+impl fmt::Debug for Upgraded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Upgraded").finish_non_exhaustive()
+    }
+}
+
+impl AsyncRead for Upgraded {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.reader).poll_read(cx, buf)
+    }
+}
+
+impl AsyncBufRead for Upgraded {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        Pin::new(&mut self.get_mut().reader).poll_fill_buf(cx)
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        Pin::new(&mut self.reader).consume(amt);
+    }
+}
+
+impl AsyncWrite for Upgraded {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.writer).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.writer).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+
+    /// `read` and `write` as the server would hand them to an upgrade:
+    /// buffered, with the first `consumed` bytes the peer sent parsed.
+    async fn upgraded<R, W>(read: R, write: W, consumed: usize) -> Upgraded
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let mut reader = BufReader::new(read);
+        reader.fill_buf().await.unwrap();
+        reader.consume(consumed);
+        let (_stop, stopped) = watch::channel(false);
+        Upgraded { reader: Box::new(reader), stopped, writer: Box::new(write) }
+    }
+
+    #[tokio::test]
+    async fn into_parts_gives_back_the_tcp_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (read, write) = listener.accept().await.unwrap().0.into_split();
+        client.write_all(b"\r\nearly").await.unwrap();
+
+        let Parts { buffered, transport } = upgraded(read, write, 2).await.into_parts().unwrap();
+        assert_eq!(buffered, b"early");
+        let Transport::Tcp(read, write) = transport else { panic!("not TCP: {transport:?}") };
+        let mut stream = read.reunite(write).unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), client.local_addr().unwrap());
+
+        client.write_all(b"late").await.unwrap();
+        let mut late = [0; 4];
+        stream.read_exact(&mut late).await.unwrap();
+        assert_eq!(&late, b"late");
+        stream.write_all(b"back").await.unwrap();
+        let mut back = [0; 4];
+        client.read_exact(&mut back).await.unwrap();
+        assert_eq!(&back, b"back");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn into_parts_gives_back_the_unix_socket() {
+        let (mut client, conn) = tokio::net::UnixStream::pair().unwrap();
+        let (read, write) = conn.into_split();
+        client.write_all(b"early").await.unwrap();
+
+        let Parts { buffered, transport } = upgraded(read, write, 0).await.into_parts().unwrap();
+        assert_eq!(buffered, b"early");
+        let Transport::Unix(read, write) = transport else { panic!("not Unix: {transport:?}") };
+        let mut stream = read.reunite(write).unwrap();
+        stream.write_all(b"back").await.unwrap();
+        let mut back = [0; 4];
+        client.read_exact(&mut back).await.unwrap();
+        assert_eq!(&back, b"back");
+    }
+
+    #[tokio::test]
+    async fn other_streams_are_given_back_with_their_buffer() {
+        let (mut client, conn) = tokio::io::duplex(64);
+        let (read, write) = tokio::io::split(conn);
+        client.write_all(b"GET-rest").await.unwrap();
+        // Not a socket: `into_parts` hands the connection back intact.
+        let Err(mut conn) = upgraded(read, write, 4).await.into_parts() else { panic!("a pipe is not a socket") };
+
+        client.write_all(b"+more").await.unwrap();
+        let mut got = [0; 9];
+        conn.read_exact(&mut got).await.unwrap();
+        assert_eq!(&got, b"rest+more");
+        conn.write_all(b"ok").await.unwrap();
+        let mut ok = [0; 2];
+        client.read_exact(&mut ok).await.unwrap();
+        assert_eq!(&ok, b"ok");
+    }
+
+    #[tokio::test]
+    async fn shutdown_resolves_when_the_server_stops() {
+        let (_client, conn) = tokio::io::duplex(64);
+        let (read, write) = tokio::io::split(conn);
+        let (stop, stopped) = watch::channel(false);
+        let conn = Upgraded { reader: Box::new(BufReader::new(read)), stopped, writer: Box::new(write) };
+        let shutdown = tokio::spawn(conn.shutdown());
+        tokio::task::yield_now().await;
+        assert!(!shutdown.is_finished());
+        stop.send(true).unwrap();
+        shutdown.await.unwrap();
+    }
+}
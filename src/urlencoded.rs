@@ -0,0 +1,111 @@
+//! `application/x-www-form-urlencoded` decoding, shared by query strings and
+//! form bodies.
+//!
+//! Follows the WHATWG URL standard's parser: split on `&`, split each pair on
+//! the first `=`, turn `+` into a space, then percent-decode. A `%` not
+//! followed by two hex digits is kept as-is, and byte sequences that do not
+//! decode to UTF-8 become U+FFFD — a malformed query never fails the request.
+//!
+//! One deviation: WHATWG keeps `=x` as `("", "x")`, while [`parse`] drops
+//! every pair whose key is empty, as
+//! [`Request::query_pairs`][crate::Request::query_pairs] always has.
+
+/// Parses `key=value&key2=value2` into decoded pairs, in order.
+///
+/// - Pairs with no `=` get an empty-string value.
+/// - Pairs with an empty key (`&=x`, `&&`) are skipped, unlike WHATWG.
+/// - Repeated keys are all kept.
+pub(crate) fn parse(raw: &str) -> Vec<(String, String)> {
+    raw.split('&')
+        .filter_map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            if k.is_empty() { return None; }
+            Some((decode(k), decode(v)))
+        })
+        .collect()
+}
+
+/// `true` for `application/x-www-form-urlencoded`, parameters and case
+/// ignored.
+pub(crate) fn is_form(content_type: &str) -> bool {
+    content_type.split(';').next().unwrap_or("").trim()
+        .eq_ignore_ascii_case("application/x-www-form-urlencoded")
+}
+
+/// Decodes one key or value: `+` → space, `%XX` → byte.
+pub(crate) fn decode(s: &str) -> String {
+    if !s.contains(['%', '+']) {
+        return s.to_owned();
+    }
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => match (bytes.get(i + 1).and_then(hex), bytes.get(i + 2).and_then(hex)) {
+                (Some(hi), Some(lo)) => {
+                    out.push(hi << 4 | lo);
+                    i += 2;
+                }
+                _ => out.push(b'%'),
+            },
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    match String::from_utf8(out) {
+        Ok(s)  => s,
+        Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    }
+}
+
+/// The value of one hex digit.
+fn hex(b: &u8) -> Option<u8> {
+    (*b as char).to_digit(16).map(|d| d as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owned(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|&(k, v)| (k.to_owned(), v.to_owned())).collect()
+    }
+
+    #[test]
+    fn decodes_percent_and_plus() {
+        assert_eq!(decode("hello+w%C3%B6rld"), "hello wörld");
+        assert_eq!(decode("a%2Bb%3D%26"), "a+b=&");
+        assert_eq!(decode("%41%6a"), "Aj");
+        assert_eq!(decode("plain"), "plain");
+    }
+
+    #[test]
+    fn keeps_malformed_escapes_and_replaces_bad_utf8() {
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%zz%4"), "%zz%4");
+        assert_eq!(decode("%FF%C3"), "\u{fffd}\u{fffd}");
+    }
+
+    #[test]
+    fn parses_pairs_in_order_with_repeats() {
+        assert_eq!(
+            parse("tag=a&q=x+y&tag=b&flag&=skip&&k=v=w"),
+            owned(&[("tag", "a"), ("q", "x y"), ("tag", "b"), ("flag", ""), ("k", "v=w")]),
+        );
+        assert!(parse("").is_empty());
+    }
+
+    #[test]
+    fn decodes_keys_too() {
+        assert_eq!(parse("a%5B%5D=1"), owned(&[("a[]", "1")]));
+    }
+
+    #[test]
+    fn recognizes_the_form_content_type() {
+        assert!(is_form("application/x-www-form-urlencoded"));
+        assert!(is_form("Application/X-WWW-Form-Urlencoded; charset=utf-8"));
+        assert!(!is_form("multipart/form-data; boundary=x"));
+    }
+}
@@ -0,0 +1,759 @@
+//! WebSockets (RFC 6455) — upgrade a request, then exchange messages.
+//!
+//! Take a [`WebSocketUpgrade`] as a handler argument and answer with
+//! [`on_upgrade`][WebSocketUpgrade::on_upgrade]. astor sends
+//! `101 Switching Protocols`, stops speaking HTTP on the connection and runs
+//! the callback with a [`WebSocket`]:
+//!
+//! ```rust,no_run
+//! use astor::ws::{Message, WebSocket, WebSocketUpgrade};
+//! use astor::{Method, Response, Router};
+//!
+//! async fn echo(ws: WebSocketUpgrade) -> Response {
+//!     ws.on_upgrade(|mut socket: WebSocket| async move {
+//!         while let Ok(Some(message)) = socket.recv().await {
+//!             if let Message::Text(_) | Message::Binary(_) = message {
+//!                 if socket.send(message).await.is_err() {
+//!                     return;
+//!                 }
+//!             }
+//!         }
+//!     })
+//! }
+//!
+//! let app = Router::new().on(Method::Get, "/ws", echo, ());
+//! ```
+//!
+//! A request that is not a WebSocket handshake is rejected with an
+//! [`UpgradeError`] before the handler runs.
+//!
+//! # What astor handles
+//!
+//! - **Framing** — fragmented messages are reassembled, up to
+//!   [`max_message_size`][WebSocketUpgrade::max_message_size].
+//! - **Validation** — unmasked client frames, reserved bits, bad opcodes,
+//!   oversized or fragmented control frames and invalid UTF-8 fail the
+//!   connection with the close code RFC 6455 prescribes.
+//! - **Control frames** — pings are answered with a pong, a close frame is
+//!   echoed. Both still reach the handler as [`Message`]s.
+//! - **Shutdown** — on `SIGTERM` / `Ctrl-C` a socket waiting in
+//!   [`recv`][WebSocket::recv] sends close code `1001` (going away) and
+//!   finishes the close handshake, so graceful shutdown is not held up by
+//!   idle sockets. A peer that never answers a close astor sent is dropped
+//!   after [`close_timeout`][WebSocketUpgrade::close_timeout].
+//!
+//! No extensions (`permessage-deflate`) and no subprotocol negotiation.
+//!
+//! # nginx
+//!
+//! `Upgrade` and `Connection` are hop-by-hop headers: nginx drops them unless
+//! told otherwise, and the upstream keep-alive setup clears `Connection` on
+//! purpose. Give WebSocket routes their own `location`:
+//!
+//! ```nginx
+//! location /ws {
+//!     proxy_pass http://astor;
+//!     proxy_http_version 1.1;
+//!     proxy_set_header Upgrade $http_upgrade;
+//!     proxy_set_header Connection "upgrade";
+//!     proxy_read_timeout 1h;
+//! }
+//! ```
+//!
+//! `proxy_read_timeout` closes a socket that stays silent that long — ping
+//! from either side more often than that.
+
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use crate::body::Reader;
+use crate::crypto;
+use crate::error::{UpgradeError, WebSocketError};
+use crate::extract::FromRequestParts;
+use crate::method::Method;
+use crate::request::Request;
+use crate::response::Response;
+use crate::upgrade::{Upgraded, Writer};
+
+/// Appended to the client's key before hashing (RFC 6455 §1.3).
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const DEFAULT_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+// ── WebSocketUpgrade ──────────────────────────────────────────────────────────
+
+/// A validated WebSocket handshake, waiting to be accepted.
+///
+/// As a handler argument it checks for a `GET` with `upgrade: websocket`,
+/// `connection: upgrade`, `sec-websocket-version: 13` and a well-formed
+/// `sec-websocket-key`, and rejects with [`UpgradeError`] otherwise.
+#[derive(Debug)]
+pub struct WebSocketUpgrade {
+    accept: String,
+    close_timeout: Duration,
+    max_message_size: usize,
+}
+
+impl WebSocketUpgrade {
+    /// How long the peer has to answer a close frame astor sent before the
+    /// connection is dropped without one. Defaults to 5 s.
+    pub fn close_timeout(mut self, timeout: Duration) -> Self {
+        self.close_timeout = timeout;
+        self
+    }
+
+    /// Caps the size of one message, reassembled from its fragments.
+    /// Defaults to 16 MiB. A larger message fails the socket with
+    /// [`WebSocketError::MessageTooLarge`] and close code `1009`.
+    pub fn max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// Accepts the handshake: the `101 Switching Protocols` response to
+    /// return from the handler. Once it is sent, `callback` runs with the
+    /// socket; the connection closes when the callback returns.
+    ///
+    /// Middleware may still replace the response — then there is no
+    /// upgrade and `callback` never runs.
+    pub fn on_upgrade<F, Fut>(self, callback: F) -> Response
+    where
+        F: FnOnce(WebSocket) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let Self { accept, close_timeout, max_message_size } = self;
+        Response::builder()
+            .header("sec-websocket-accept", &accept)
+            .upgrade("websocket", move |upgraded: Upgraded| {
+                callback(WebSocket::new(upgraded, close_timeout, max_message_size))
+            })
+    }
+}
+
+impl FromRequestParts for WebSocketUpgrade {
+    type Rejection = UpgradeError;
+
+    fn from_request_parts(req: &mut Request) -> Result<Self, UpgradeError> {
+        let has_token = |name: &str, token: &str| {
+            req.header(name)
+                .is_some_and(|v| v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+        };
+        if req.method() != Method::Get || !has_token("upgrade", "websocket") || !has_token("connection", "upgrade") {
+            return Err(UpgradeError::NotUpgrade);
+        }
+        if req.header("sec-websocket-version").map(str::trim) != Some("13") {
+            return Err(UpgradeError::UnsupportedVersion);
+        }
+        let key = req.header("sec-websocket-key").map(str::trim).unwrap_or_default();
+        if !is_key(key) {
+            return Err(UpgradeError::InvalidKey);
+        }
+        let accept = crypto::base64_encode(&crypto::sha1(format!("{key}{GUID}").as_bytes()));
+        Ok(Self { accept, close_timeout: DEFAULT_CLOSE_TIMEOUT, max_message_size: DEFAULT_MAX_MESSAGE_SIZE })
+    }
+}
+
+/// 16 bytes in padded base64: 22 characters, then `==`.
+fn is_key(key: &str) -> bool {
+    key.len() == 24
+        && key.ends_with("==")
+        && key.bytes().take(22).all(|b| crypto::BASE64.contains(&b))
+}
+
+// ── Message ───────────────────────────────────────────────────────────────────
+
+/// One WebSocket message.
+///
+/// All variants are listed alphabetically — add new ones in order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Message {
+    /// Binary data.
+    Binary(Vec<u8>),
+    /// The close handshake. `None` when the peer gave no code.
+    Close(Option<CloseFrame>),
+    /// A ping. astor has already answered it with a pong. At most 125 bytes.
+    Ping(Vec<u8>),
+    /// A pong — the answer to a ping, or a heartbeat. At most 125 bytes.
+    Pong(Vec<u8>),
+    /// UTF-8 text.
+    Text(String),
+}
+
+/// The code and reason carried by a close frame.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CloseFrame {
+    /// `1000` for a normal closure — see RFC 6455 §7.4 for the rest.
+    pub code: u16,
+    /// Human-readable, at most 123 bytes.
+    pub reason: String,
+}
+
+// ── WebSocket ─────────────────────────────────────────────────────────────────
+
+/// An open WebSocket connection.
+///
+/// [`recv`][WebSocket::recv] is cancel-safe: wait on it in a
+/// `tokio::select!` next to your own channel to push updates while still
+/// reading. Dropping the socket closes the connection without a close
+/// handshake — send a [`Message::Close`] first.
+pub struct WebSocket {
+    /// Bytes read but not yet parsed into frames.
+    buf: Vec<u8>,
+    /// When the peer's answer to our close frame is overdue.
+    close_deadline: Option<Instant>,
+    close_timeout: Duration,
+    /// A close frame has been sent: nothing more may be.
+    closing: bool,
+    /// The peer closed, or the connection failed: nothing more to read.
+    done: bool,
+    /// Opcode and data of a message still arriving in fragments.
+    fragments: Option<(u8, Vec<u8>)>,
+    max_message_size: usize,
+    /// Frames queued but not yet written — pongs and closes astor sends on
+    /// its own, and anything a cancelled `send` left behind.
+    outgoing: Vec<u8>,
+    reader: Reader,
+    stopped: watch::Receiver<bool>,
+    writer: Writer,
+}
+
+/// One frame off the wire, unmasked.
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+impl WebSocket {
+    fn new(upgraded: Upgraded, close_timeout: Duration, max_message_size: usize) -> Self {
+        let Upgraded { reader, stopped, writer } = upgraded;
+        Self {
+            buf: Vec::new(),
+            close_deadline: None,
+            close_timeout,
+            closing: false,
+            done: false,
+            fragments: None,
+            max_message_size,
+            outgoing: Vec::new(),
+            reader,
+            stopped,
+            writer,
+        }
+    }
+
+    /// The next message. `Ok(None)` once the connection is over — after
+    /// the peer's [`Message::Close`], or if it disconnected without one.
+    ///
+    /// # Errors
+    ///
+    /// [`WebSocketError`] when the peer breaks the protocol — astor has
+    /// already sent the matching close frame — or the connection fails.
+    /// Once astor has sent a close, a peer that does not answer within
+    /// [`close_timeout`][WebSocketUpgrade::close_timeout] fails it with
+    /// [`WebSocketError::Io`] of kind `TimedOut`. Either way the socket
+    /// yields nothing more.
+    pub async fn recv(&mut self) -> Result<Option<Message>, WebSocketError> {
+        loop {
+            self.flush().await?;
+            if self.done {
+                return Ok(None);
+            }
+            match self.next_frame() {
+                Ok(Some(frame)) => match self.message(frame) {
+                    Ok(Some(message)) => {
+                        self.flush().await?;
+                        return Ok(Some(message));
+                    }
+                    Ok(None) => continue,
+                    Err(e) => return Err(self.fail(e).await),
+                },
+                Ok(None) => {}
+                Err(e) => return Err(self.fail(e).await),
+            }
+
+            self.buf.reserve(8 * 1024);
+            let stopping = async { self.stopped.wait_for(|&stop| stop).await.is_ok() };
+            tokio::select! {
+                biased;
+
+                true = stopping, if !self.closing => {
+                    self.queue_close(Some(1001), "");
+                }
+
+                read = until(self.close_deadline, self.reader.read_buf(&mut self.buf)) => match read {
+                    Ok(0) => {
+                        self.done = true;
+                        return Ok(None);
+                    }
+                    Ok(_)  => {}
+                    Err(e) => return Err(self.fail(e.into()).await),
+                },
+            }
+        }
+    }
+
+    /// Sends a message. Sending [`Message::Close`] starts the close
+    /// handshake: keep calling [`recv`][WebSocket::recv] until it returns
+    /// the peer's close.
+    ///
+    /// # Errors
+    ///
+    /// [`WebSocketError::Closed`] once a close frame has gone out,
+    /// [`WebSocketError::ControlFrameTooLarge`] for a ping or pong payload
+    /// over 125 bytes or a close reason over 123 — the socket stays usable —
+    /// and [`WebSocketError::Io`] when the connection fails.
+    pub async fn send(&mut self, message: Message) -> Result<(), WebSocketError> {
+        if self.closing {
+            return Err(WebSocketError::Closed);
+        }
+        let oversized = match &message {
+            Message::Close(Some(frame))               => frame.reason.len() > 123,
+            Message::Ping(data) | Message::Pong(data) => data.len() > 125,
+            _                                         => false,
+        };
+        if oversized {
+            return Err(WebSocketError::ControlFrameTooLarge);
+        }
+        match message {
+            Message::Binary(data) => self.queue(OP_BINARY, &data),
+            Message::Close(None) => self.queue_close(None, ""),
+            Message::Close(Some(frame)) => self.queue_close(Some(frame.code), &frame.reason),
+            Message::Ping(data) => self.queue(OP_PING, &data),
+            Message::Pong(data) => self.queue(OP_PONG, &data),
+            Message::Text(text) => self.queue(OP_TEXT, text.as_bytes()),
+        }
+        self.flush().await
+    }
+
+    // ── Reading ───────────────────────────────────────────────────────────────
+
+    /// Parses one frame off the front of `buf`. `Ok(None)` until all of it
+    /// has arrived.
+    fn next_frame(&mut self) -> Result<Option<Frame>, WebSocketError> {
+        let Some(&[b0, b1]) = self.buf.get(..2) else { return Ok(None) };
+        let (fin, opcode) = (b0 & 0x80 != 0, b0 & 0x0F);
+        if b0 & 0x70 != 0 {
+            return Err(WebSocketError::Protocol("reserved bits set"));
+        }
+        if b1 & 0x80 == 0 {
+            return Err(WebSocketError::Protocol("unmasked client frame"));
+        }
+        let control = opcode & 0x8 != 0;
+        match opcode {
+            OP_CONTINUATION | OP_TEXT | OP_BINARY | OP_CLOSE | OP_PING | OP_PONG => {}
+            _ => return Err(WebSocketError::Protocol("unknown opcode")),
+        }
+        if control && !fin {
+            return Err(WebSocketError::Protocol("fragmented control frame"));
+        }
+
+        let (len, mut at) = match b1 & 0x7F {
+            126 => match self.buf.get(2..4) {
+                Some(b) => (u64::from(u16::from_be_bytes([b[0], b[1]])), 4),
+                None    => return Ok(None),
+            },
+            127 => match self.buf.get(2..10) {
+                Some(b) => (u64::from_be_bytes(b.try_into().expect("8 bytes")), 10),
+                None    => return Ok(None),
+            },
+            len => (u64::from(len), 2),
+        };
+        if control && len > 125 {
+            return Err(WebSocketError::Protocol("control frame over 125 bytes"));
+        }
+        let buffered = self.fragments.as_ref().map_or(0, |(_, data)| data.len());
+        let len = usize::try_from(len).map_err(|_| WebSocketError::MessageTooLarge)?;
+        if !control && buffered.saturating_add(len) > self.max_message_size {
+            return Err(WebSocketError::MessageTooLarge);
+        }
+
+        let Some(mask) = self.buf.get(at..at + 4) else { return Ok(None) };
+        let mask = [mask[0], mask[1], mask[2], mask[3]];
+        at += 4;
+        if self.buf.len() < at + len {
+            return Ok(None);
+        }
+        let mut payload: Vec<u8> = self.buf.drain(..at + len).skip(at).collect();
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+        Ok(Some(Frame { fin, opcode, payload }))
+    }
+
+    /// Turns a frame into a message, answering control frames on the way.
+    /// `Ok(None)` for a fragment of a message not yet complete.
+    fn message(&mut self, frame: Frame) -> Result<Option<Message>, WebSocketError> {
+        let Frame { fin, opcode, payload } = frame;
+        let (opcode, data) = match opcode {
+            OP_CLOSE => {
+                let close = close_frame(&payload)?;
+                self.done = true;
+                if !self.closing {
+                    self.queue_close(close.as_ref().map(|c| c.code), "");
+                }
+                return Ok(Some(Message::Close(close)));
+            }
+            OP_PING => {
+                if !self.closing {
+                    self.queue(OP_PONG, &payload);
+                }
+                return Ok(Some(Message::Ping(payload)));
+            }
+            OP_PONG => return Ok(Some(Message::Pong(payload))),
+            OP_CONTINUATION => match self.fragments.take() {
+                Some((opcode, mut data)) => {
+                    data.extend_from_slice(&payload);
+                    (opcode, data)
+                }
+                None => return Err(WebSocketError::Protocol("continuation frame without a message")),
+            },
+            _ if self.fragments.is_some() => {
+                return Err(WebSocketError::Protocol("new message inside a fragmented one"));
+            }
+            _ => (opcode, payload),
+        };
+        if !fin {
+            self.fragments = Some((opcode, data));
+            return Ok(None);
+        }
+        Ok(Some(match opcode {
+            OP_TEXT => Message::Text(String::from_utf8(data).map_err(|_| WebSocketError::InvalidUtf8)?),
+            _       => Message::Binary(data),
+        }))
+    }
+
+    /// Ends the connection after a failure: sends the close code it calls
+    /// for, if the connection can still carry one, and reads nothing more.
+    async fn fail(&mut self, e: WebSocketError) -> WebSocketError {
+        self.done = true;
+        if let Some(code) = e.close_code() {
+            if !self.closing {
+                self.queue_close(Some(code), "");
+            }
+            let _ = self.flush().await;
+        }
+        e
+    }
+
+    // ── Writing ───────────────────────────────────────────────────────────────
+
+    /// Appends an unmasked, unfragmented frame to `outgoing`.
+    fn queue(&mut self, opcode: u8, payload: &[u8]) {
+        self.outgoing.push(0x80 | opcode);
+        match payload.len() {
+            len @ 0..=125 => self.outgoing.push(len as u8),
+            len @ 126..=0xFFFF => {
+                self.outgoing.push(126);
+                self.outgoing.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                self.outgoing.push(127);
+                self.outgoing.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        self.outgoing.extend_from_slice(payload);
+    }
+
+    /// Queues a close frame — no payload without a code — and starts the
+    /// clock on the peer's answer.
+    fn queue_close(&mut self, code: Option<u16>, reason: &str) {
+        let mut payload = Vec::new();
+        if let Some(code) = code {
+            payload.extend_from_slice(&code.to_be_bytes());
+            payload.extend_from_slice(reason.as_bytes());
+        }
+        self.queue(OP_CLOSE, &payload);
+        self.closing = true;
+        self.close_deadline = Some(Instant::now() + self.close_timeout);
+    }
+
+    /// Writes out `outgoing`. Cancel-safe: whatever is not written yet stays
+    /// queued for the next call.
+    async fn flush(&mut self) -> Result<(), WebSocketError> {
+        while !self.outgoing.is_empty() {
+            match until(self.close_deadline, self.writer.write(&self.outgoing)).await {
+                Ok(0) => return Err(self.broken(io::ErrorKind::WriteZero.into())),
+                Ok(n) => drop(self.outgoing.drain(..n)),
+                Err(e) => return Err(self.broken(e)),
+            }
+        }
+        until(self.close_deadline, self.writer.flush()).await.map_err(|e| self.broken(e))
+    }
+
+    /// A write failed: the connection is gone both ways.
+    fn broken(&mut self, e: io::Error) -> WebSocketError {
+        self.closing = true;
+        self.done = true;
+        self.outgoing.clear();
+        WebSocketError::Io(e)
+    }
+}
+
+/// `io`, failing with `TimedOut` once `deadline` — if any — has passed.
+async fn until<T>(deadline: Option<Instant>, io: impl Future<Output = io::Result<T>>) -> io::Result<T> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, io).await.unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into())),
+        None           => io.await,
+    }
+}
+
+/// Reads a close frame's payload: empty, or a code then a UTF-8 reason.
+fn close_frame(payload: &[u8]) -> Result<Option<CloseFrame>, WebSocketError> {
+    let (code, reason) = match payload {
+        [] => return Ok(None),
+        [hi, lo, reason @ ..] => (u16::from_be_bytes([*hi, *lo]), reason),
+        [_] => return Err(WebSocketError::Protocol("one-byte close payload")),
+    };
+    // RFC 6455 §7.4: 1004–1006 and 1015 are never sent on the wire.
+    if !matches!(code, 1000..=1003 | 1007..=1014 | 3000..=4999) {
+        return Err(WebSocketError::Protocol("invalid close code"));
+    }
+    let reason = std::str::from_utf8(reason).map_err(|_| WebSocketError::InvalidUtf8)?;
+    Ok(Some(CloseFrame { code, reason: reason.to_owned() }))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{BufReader, DuplexStream};
+
+    use super::*;
+    use crate::router::Router;
+    use crate::testing::{exchange, parse};
+
+    const MASK: [u8; 4] = [0x37, 0xFA, 0x21, 0x3D];
+
+    /// A socket over an in-memory pipe, the peer's end of it, and the
+    /// switch that starts server shutdown.
+    fn socket(close_timeout: Duration) -> (WebSocket, DuplexStream, watch::Sender<bool>) {
+        let (peer, conn) = tokio::io::duplex(64 * 1024);
+        let (read, write) = tokio::io::split(conn);
+        let (stop, stopped) = watch::channel(false);
+        let upgraded = Upgraded { reader: Box::new(BufReader::new(read)), stopped, writer: Box::new(write) };
+        (WebSocket::new(upgraded, close_timeout, 1024), peer, stop)
+    }
+
+    /// A client frame, masked with [`MASK`].
+    fn frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = vec![u8::from(fin) << 7 | opcode];
+        match payload.len() {
+            len @ 0..=125 => out.push(0x80 | len as u8),
+            len => {
+                out.push(0x80 | 126);
+                out.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+        }
+        out.extend_from_slice(&MASK);
+        out.extend(payload.iter().enumerate().map(|(i, b)| b ^ MASK[i % 4]));
+        out
+    }
+
+    /// A close frame's payload.
+    fn close(code: u16, reason: &str) -> Vec<u8> {
+        [&code.to_be_bytes()[..], reason.as_bytes()].concat()
+    }
+
+    /// The next server frame: first byte and payload. Server frames must
+    /// not be masked.
+    async fn read_frame(peer: &mut DuplexStream) -> (u8, Vec<u8>) {
+        let mut head = [0; 2];
+        peer.read_exact(&mut head).await.unwrap();
+        assert_eq!(head[1] & 0x80, 0, "server frame is masked");
+        let len = match head[1] {
+            126 => usize::from(peer.read_u16().await.unwrap()),
+            127 => usize::try_from(peer.read_u64().await.unwrap()).unwrap(),
+            len => usize::from(len),
+        };
+        let mut payload = vec![0; len];
+        peer.read_exact(&mut payload).await.unwrap();
+        (head[0], payload)
+    }
+
+    #[tokio::test]
+    async fn handshake_answers_with_the_accept_key() {
+        async fn handler(ws: WebSocketUpgrade) -> Response {
+            ws.on_upgrade(|_| async {})
+        }
+        let app = || Router::new().on(Method::Get, "/ws", handler, ());
+        let head = "GET /ws HTTP/1.1\r\nhost: a\r\nupgrade: websocket\r\nconnection: keep-alive, Upgrade\r\n";
+
+        let out = exchange(app(), format!("{head}sec-websocket-version: 13\r\nsec-websocket-key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n")).await;
+        let reply = parse(out.as_bytes());
+        assert_eq!(reply.status, 101);
+        assert_eq!(reply.header("upgrade"), Some("websocket"));
+        assert_eq!(reply.header("sec-websocket-accept"), Some("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+
+        let out = exchange(app(), format!("{head}sec-websocket-version: 8\r\nsec-websocket-key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n")).await;
+        let reply = parse(out.as_bytes());
+        assert_eq!((reply.status, reply.header("sec-websocket-version")), (426, Some("13")));
+
+        let out = exchange(app(), format!("{head}sec-websocket-version: 13\r\nsec-websocket-key: short==\r\n\r\n")).await;
+        assert_eq!(parse(out.as_bytes()).status, 400);
+    }
+
+    #[tokio::test]
+    async fn unmasks_client_frames_and_sends_unmasked_ones() {
+        let (mut ws, mut peer, _stop) = socket(DEFAULT_CLOSE_TIMEOUT);
+        peer.write_all(&frame(true, OP_TEXT, b"hello")).await.unwrap();
+        assert_eq!(ws.recv().await.unwrap(), Some(Message::Text("hello".to_owned())));
+
+        ws.send(Message::Text("hi".to_owned())).await.unwrap();
+        assert_eq!(read_frame(&mut peer).await, (0x81, b"hi".to_vec()));
+        ws.send(Message::Binary(vec![7; 300])).await.unwrap();
+        assert_eq!(read_frame(&mut peer).await, (0x82, vec![7; 300]));
+    }
+
+    #[tokio::test]
+    async fn fails_unmasked_frames_with_1002() {
+        let (mut ws, mut peer, _stop) = socket(DEFAULT_CLOSE_TIMEOUT);
+        peer.write_all(&[0x81, 0x02, b'h', b'i']).await.unwrap();
+        let e = ws.recv().await.unwrap_err();
+        assert!(matches!(e, WebSocketError::Protocol("unmasked client frame")), "{e}");
+        assert_eq!(read_frame(&mut peer).await, (0x88, close(1002, "")));
+        assert_eq!(ws.recv().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn reassembles_fragments_around_control_frames() {
+        let (mut ws, mut peer, _stop) = socket(DEFAULT_CLOSE_TIMEOUT);
+        let mut raw = frame(false, OP_TEXT, b"hel");
+        raw.extend(frame(true, OP_PING, b"?"));
+        raw.extend(frame(false, OP_CONTINUATION, b"l"));
+        raw.extend(frame(true, OP_CONTINUATION, b"o"));
+        peer.write_all(&raw).await.unwrap();
+
+        assert_eq!(ws.recv().await.unwrap(), Some(Message::Ping(b"?".to_vec())));
+        assert_eq!(read_frame(&mut peer).await, (0x8A, b"?".to_vec()));
+        assert_eq!(ws.recv().await.unwrap(), Some(Message::Text("hello".to_owned())));
+    }
+
+    #[tokio::test]
+    async fn rejects_broken_fragmentation() {
+        let cases: [(Vec<u8>, &str); 2] = [
+            (frame(true, OP_CONTINUATION, b"x"), "continuation frame without a message"),
+            ([frame(false, OP_TEXT, b"a"), frame(true, OP_BINARY, b"b")].concat(), "new message inside a fragmented one"),
+        ];
+        for (raw, why) in cases {
+            let (mut ws, mut peer, _stop) = socket(DEFAULT_CLOSE_TIMEOUT);
+            peer.write_all(&raw).await.unwrap();
+            let e = ws.recv().await.unwrap_err();
+            assert!(matches!(e, WebSocketError::Protocol(w) if w == why), "{e}");
+            assert_eq!(read_frame(&mut peer).await, (0x88, close(1002, "")));
+        }
+    }
+
+    #[tokio::test]
+    async fn caps_messages_across_fragments() {
+        let (mut ws, mut peer, _stop) = socket(DEFAULT_CLOSE_TIMEOUT);
+        let raw = [frame(false, OP_BINARY, &[0; 1000]), frame(true, OP_CONTINUATION, &[0; 25])].concat();
+        peer.write_all(&raw).await.unwrap();
+        assert!(matches!(ws.recv().await, Err(WebSocketError::MessageTooLarge)));
+        assert_eq!(read_frame(&mut peer).await, (0x88, close(1009, "")));
+    }
+
+    #[tokio::test]
+    async fn fails_invalid_utf8_with_1007() {
+        let (mut ws, mut peer, _stop) = socket(DEFAULT_CLOSE_TIMEOUT);
+        peer.write_all(&frame(true, OP_TEXT, &[0xC3, 0x28])).await.unwrap();
+        assert!(matches!(ws.recv().await, Err(WebSocketError::InvalidUtf8)));
+        assert_eq!(read_frame(&mut peer).await, (0x88, close(1007, "")));
+    }
+
+    #[tokio::test]
+    async fn limits_control_frames_from_the_peer() {
+        let cases: [(Vec<u8>, &str); 2] = [
+            (frame(true, OP_PING, &[0; 126]), "control frame over 125 bytes"),
+            (frame(false, OP_PING, b"x"), "fragmented control frame"),
+        ];
+        for (raw, why) in cases {
+            let (mut ws, mut peer, _stop) = socket(DEFAULT_CLOSE_TIMEOUT);
+            peer.write_all(&raw).await.unwrap();
+            let e = ws.recv().await.unwrap_err();
+            assert!(matches!(e, WebSocketError::Protocol(w) if w == why), "{e}");
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_to_send_oversized_control_frames() {
+        let (mut ws, mut peer, _stop) = socket(DEFAULT_CLOSE_TIMEOUT);
+        let too_long = CloseFrame { code: 1000, reason: "x".repeat(124) };
+        for message in [Message::Ping(vec![0; 126]), Message::Pong(vec![0; 126]), Message::Close(Some(too_long))] {
+            assert!(matches!(ws.send(message).await, Err(WebSocketError::ControlFrameTooLarge)));
+        }
+
+        // Nothing went out, and the socket still works.
+        ws.send(Message::Ping(vec![1; 125])).await.unwrap();
+        assert_eq!(read_frame(&mut peer).await, (0x89, vec![1; 125]));
+        let longest = CloseFrame { code: 1000, reason: "x".repeat(123) };
+        ws.send(Message::Close(Some(longest))).await.unwrap();
+        assert_eq!(read_frame(&mut peer).await, (0x88, close(1000, &"x".repeat(123))));
+    }
+
+    #[tokio::test]
+    async fn echoes_the_peers_close() {
+        let (mut ws, mut peer, _stop) = socket(DEFAULT_CLOSE_TIMEOUT);
+        peer.write_all(&frame(true, OP_CLOSE, &close(1000, "bye"))).await.unwrap();
+        let frame = CloseFrame { code: 1000, reason: "bye".to_owned() };
+        assert_eq!(ws.recv().await.unwrap(), Some(Message::Close(Some(frame))));
+        assert_eq!(read_frame(&mut peer).await, (0x88, close(1000, "")));
+        assert_eq!(ws.recv().await.unwrap(), None);
+        assert!(matches!(ws.send(Message::Text("late".to_owned())).await, Err(WebSocketError::Closed)));
+    }
+
+    #[tokio::test]
+    async fn completes_a_close_it_started() {
+        let (mut ws, mut peer, _stop) = socket(DEFAULT_CLOSE_TIMEOUT);
+        ws.send(Message::Close(None)).await.unwrap();
+        assert_eq!(read_frame(&mut peer).await, (0x88, Vec::new()));
+        assert!(matches!(ws.send(Message::Ping(Vec::new())).await, Err(WebSocketError::Closed)));
+
+        // Pings still arrive, but are no longer answered.
+        peer.write_all(&frame(true, OP_PING, b"?")).await.unwrap();
+        peer.write_all(&frame(true, OP_CLOSE, &close(1000, ""))).await.unwrap();
+        assert_eq!(ws.recv().await.unwrap(), Some(Message::Ping(b"?".to_vec())));
+        let frame = CloseFrame { code: 1000, reason: String::new() };
+        assert_eq!(ws.recv().await.unwrap(), Some(Message::Close(Some(frame))));
+        assert_eq!(ws.recv().await.unwrap(), None);
+
+        drop(ws);
+        let mut rest = Vec::new();
+        peer.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty(), "{rest:?}");
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_a_peer_that_never_answers_the_close() {
+        let (mut ws, mut peer, _stop) = socket(Duration::from_millis(50));
+        ws.send(Message::Close(None)).await.unwrap();
+        let started = Instant::now();
+        let e = ws.recv().await.unwrap_err();
+        assert!(matches!(&e, WebSocketError::Io(io) if io.kind() == io::ErrorKind::TimedOut), "{e}");
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(ws.recv().await.unwrap(), None);
+        assert_eq!(read_frame(&mut peer).await, (0x88, Vec::new()));
+    }
+
+    #[tokio::test]
+    async fn closes_with_1001_on_shutdown() {
+        let (mut ws, mut peer, stop) = socket(DEFAULT_CLOSE_TIMEOUT);
+        let recv = tokio::spawn(async move { ws.recv().await.map_err(|e| e.to_string()) });
+        stop.send(true).unwrap();
+        assert_eq!(read_frame(&mut peer).await, (0x88, close(1001, "")));
+        peer.write_all(&frame(true, OP_CLOSE, &close(1001, ""))).await.unwrap();
+        let frame = CloseFrame { code: 1001, reason: String::new() };
+        assert_eq!(recv.await.unwrap(), Ok(Some(Message::Close(Some(frame)))));
+    }
+}